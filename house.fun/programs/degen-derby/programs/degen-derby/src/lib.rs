@@ -172,14 +172,12 @@ pub mod degen_derby {
 
         let house_fee_from_losing = (total_losing_pool * HOUSE_FEE_BPS as u64) / 10000;
         let house_fee_from_winning = (total_winning_pool * HOUSE_FEE_BPS as u64) / 10000;
-        let net_losing_pool = total_losing_pool.checked_sub(house_fee_from_losing + house_fee_from_winning).unwrap_or(0);
+        let net_losing_pool = total_losing_pool.saturating_sub(house_fee_from_losing + house_fee_from_winning);
 
         // Proportional winnings minus house fee
-        let winnings_from_pool = if total_winning_pool > 0 {
-            (player_bet.amount * net_losing_pool) / total_winning_pool
-        } else {
-            0
-        };
+        let winnings_from_pool = (player_bet.amount * net_losing_pool)
+            .checked_div(total_winning_pool)
+            .unwrap_or(0);
 
         let total_payout = player_bet.amount + winnings_from_pool;
 
@@ -239,7 +237,7 @@ pub mod degen_derby {
 }
 
 // Helper function for weighted random selection
-fn select_weighted_winner(bets: &Vec<u64>, random_hash: &[u8; 32]) -> usize {
+fn select_weighted_winner(bets: &[u64], random_hash: &[u8; 32]) -> usize {
     let total_bets: u64 = bets.iter().sum();
     if total_bets == 0 {
         // Random selection if no bets
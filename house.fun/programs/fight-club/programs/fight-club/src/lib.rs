@@ -1,6 +1,11 @@
 use anchor_lang::prelude::*;
 use anchor_lang::solana_program::clock::Clock;
-use pyth_solana_receiver_sdk::price_update::{PriceUpdateV2, FeedId};
+use pyth_solana_receiver_sdk::error::GetPriceError;
+use pyth_solana_receiver_sdk::price_update::{FeedId, Price, PriceUpdateV2, VerificationLevel};
+
+pub mod performance;
+
+use performance::{performance, Quote};
 
 // Program ID - Replace with actual after deployment
 declare_id!("5BZ86FTWQGrFnMLk17D882N7shNqoVuohbkKo2Ljt7GN");
@@ -10,6 +15,15 @@ pub const HOUSE_FEE_BPS: u16 = 100; // 1% house fee
 pub const MIN_BET_LAMPORTS: u64 = 1_000_000; // 0.001 SOL
 pub const MAX_BET_LAMPORTS: u64 = 100_000_000_000; // 100 SOL
 pub const MATCH_TIMEOUT_SLOTS: u64 = 300; // ~2 minutes
+pub const PAYOUT_RATIO_SCALE: u128 = 1_000_000_000_000; // Fixed-point scale of `FightMatch::payout_ratio`
+pub const CLAIM_WINDOW_SLOTS: u64 = 216_000; // ~1 day before rounding dust can be swept
+pub const MAX_MATCH_SECONDS: i64 = 604_800; // Matches resolve within a week of creation
+pub const MAX_PRICE_LAG_SECONDS: i64 = 10; // Closing prices are published at most this long after resolution
+pub const MAX_CONF_BPS: u64 = 50; // Widest Pyth confidence interval accepted, relative to the price
+pub const RESOLUTION_GRACE_SECONDS: i64 = 3_600; // Time to resolve before a match can be cancelled instead
+pub const MIN_FIGHTERS: usize = 2;
+pub const MAX_FIGHTERS: usize = 8;
+pub const MAX_TOKEN_LEN: usize = 10;
 
 #[program]
 pub mod fight_club {
@@ -28,61 +42,90 @@ pub mod fight_club {
         Ok(())
     }
 
-    /// Create a new fight match with Pyth Price Feeds
+    /// Create a new fight match with Pyth Price Feeds. Betting closes at
+    /// `betting_closes_at` and the fight is decided on prices published at
+    /// `resolves_at` (unix timestamps). `tie_breaker` decides a dead heat:
+    /// refund everyone, or award it to one fighter.
+    #[allow(clippy::too_many_arguments)]
     pub fn create_match_v2(
         ctx: Context<CreateMatchV2>,
         token_a: String,
         token_b: String,
         feed_id_a: [u8; 32],
         feed_id_b: [u8; 32],
+        tie_breaker: TieBreaker,
+        betting_closes_at: i64,
+        resolves_at: i64,
     ) -> Result<()> {
-        let fight_match = &mut ctx.accounts.fight_match;
-        let house = &mut ctx.accounts.house;
         let clock = Clock::get()?;
+        let specs = vec![
+            FighterSpec { token: token_a, feed_id: feed_id_a },
+            FighterSpec { token: token_b, feed_id: feed_id_b },
+        ];
+        let updates = price_updates(&ctx.accounts.price_update_a, &ctx.accounts.price_update_b, &[])?;
+        let fighters = start_fighters(specs, &updates, &clock)?;
 
+        let fight_match = &mut ctx.accounts.fight_match;
         fight_match.creator = ctx.accounts.creator.key();
-        fight_match.token_a = token_a;
-        fight_match.token_b = token_b;
-        fight_match.feed_id_a = feed_id_a;
-        fight_match.feed_id_b = feed_id_b;
-        
-        // Fetch initial prices from Pyth
-        let price_update_a = &ctx.accounts.price_update_a;
-        let price_data_a = price_update_a.get_price_no_older_than(&clock, 60, &feed_id_a)?;
-        fight_match.start_price_a = price_data_a.price;
-
-        let price_update_b = &ctx.accounts.price_update_b;
-        let price_data_b = price_update_b.get_price_no_older_than(&clock, 60, &feed_id_b)?;
-        fight_match.start_price_b = price_data_b.price;
-
-        fight_match.total_bet_a = 0;
-        fight_match.total_bet_b = 0;
-        fight_match.player_count_a = 0;
-        fight_match.player_count_b = 0;
-        fight_match.status = MatchStatus::Open;
-        fight_match.created_at_slot = clock.slot;
-        fight_match.winner = None;
         fight_match.bump = ctx.bumps.fight_match;
-
-        house.total_matches += 1;
+        fight_match.open(fighters, tie_breaker, betting_closes_at, resolves_at, &clock)?;
+        ctx.accounts.house.total_matches += 1;
 
         msg!(
             "Fight created V2: {} ({}) vs {} ({})", 
-            fight_match.token_a, 
-            price_data_a.price,
-            fight_match.token_b,
-            price_data_b.price
+            fight_match.fighters[0].token, 
+            fight_match.fighters[0].start_price,
+            fight_match.fighters[1].token,
+            fight_match.fighters[1].start_price
+        );
+        Ok(())
+    }
+
+    /// Create a battle royale between 2 to `MAX_FIGHTERS` tokens. Price
+    /// updates for the first two fighters go in `price_update_a` and
+    /// `price_update_b`, and those for the rest in the remaining accounts,
+    /// in fighter order. Otherwise it plays like `create_match_v2`: the
+    /// best performer takes the whole pari-mutuel pool.
+    pub fn create_battle_royale<'info>(
+        ctx: Context<'_, '_, 'info, 'info, CreateBattleRoyale<'info>>,
+        fighters: Vec<FighterSpec>,
+        tie_breaker: TieBreaker,
+        betting_closes_at: i64,
+        resolves_at: i64,
+    ) -> Result<()> {
+        let clock = Clock::get()?;
+        let updates = price_updates(
+            &ctx.accounts.price_update_a,
+            &ctx.accounts.price_update_b,
+            ctx.remaining_accounts,
+        )?;
+        let fighters = start_fighters(fighters, &updates, &clock)?;
+
+        let fight_match = &mut ctx.accounts.fight_match;
+        fight_match.creator = ctx.accounts.creator.key();
+        fight_match.bump = ctx.bumps.fight_match;
+        fight_match.open(fighters, tie_breaker, betting_closes_at, resolves_at, &clock)?;
+        ctx.accounts.house.total_matches += 1;
+
+        msg!(
+            "Battle royale created: {} fighters, {}",
+            fight_match.fighters.len(),
+            fight_match
+                .fighters
+                .iter()
+                .map(|fighter| fighter.token.as_str())
+                .collect::<Vec<_>>()
+                .join(" vs ")
         );
         Ok(())
     }
 
-    /// Place bet on a fighter (token A or B)
+    /// Place bet on a fighter (token A or B, or any royale fighter)
     pub fn place_bet(
         ctx: Context<PlaceBet>,
         amount: u64,
-        side: u8, // 0 = Token A, 1 = Token B
+        side: u8, // Index into `FightMatch::fighters`
     ) -> Result<()> {
-        require!(side == 0 || side == 1, FightClubError::InvalidSide);
         require!(amount >= MIN_BET_LAMPORTS, FightClubError::BetTooSmall);
         require!(amount <= MAX_BET_LAMPORTS, FightClubError::BetTooLarge);
 
@@ -90,11 +133,20 @@ pub mod fight_club {
         let player_bet = &mut ctx.accounts.player_bet;
         let player = &ctx.accounts.player;
 
+        require!(
+            (side as usize) < fight_match.fighters.len(),
+            FightClubError::InvalidSide
+        );
+
         // Ensure match is still open
         require!(
             fight_match.status == MatchStatus::Open,
             FightClubError::MatchNotOpen
         );
+        require!(
+            Clock::get()?.unix_timestamp < fight_match.betting_closes_at,
+            FightClubError::BettingClosed
+        );
 
         // Check if player already bet on this match
         require!(
@@ -111,13 +163,9 @@ pub mod fight_club {
         player_bet.bump = ctx.bumps.player_bet;
 
         // Update match totals
-        if side == 0 {
-            fight_match.total_bet_a += amount;
-            fight_match.player_count_a += 1;
-        } else {
-            fight_match.total_bet_b += amount;
-            fight_match.player_count_b += 1;
-        }
+        let fighter = &mut fight_match.fighters[side as usize];
+        fighter.total_bet += amount;
+        fighter.player_count += 1;
 
         // Transfer SOL to match escrow
         let transfer_ix = anchor_lang::solana_program::system_instruction::transfer(
@@ -138,16 +186,28 @@ pub mod fight_club {
         msg!(
             "Bet placed: {} lamports on {} in match {}",
             amount,
-            if side == 0 { &fight_match.token_a } else { &fight_match.token_b },
+            fight_match.fighters[side as usize].token,
             fight_match.key()
         );
         Ok(())
     }
 
-    /// Resolve match and determine winner using Pyth Price feeds
-    pub fn resolve_with_pyth(
-        ctx: Context<ResolveWithPyth>
+    /// Resolve match and determine winner using Pyth Price feeds, once its
+    /// scheduled end has passed, from prices published at that end. Anyone
+    /// can resolve a match until `RESOLUTION_GRACE_SECONDS` after its end;
+    /// after that it can only be cancelled with `cancel_unresolved`. A tie
+    /// under `TieBreaker::Push`, or a winner that leaves one side of the
+    /// pool empty, pushes the match: every bet is refunded in full. Battle
+    /// royales pass the price updates of fighters after the first two in
+    /// the remaining accounts, in fighter order.
+    pub fn resolve_with_pyth<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ResolveWithPyth<'info>>
     ) -> Result<()> {
+        let updates = price_updates(
+            &ctx.accounts.price_update_a,
+            &ctx.accounts.price_update_b,
+            ctx.remaining_accounts,
+        )?;
         let fight_match = &mut ctx.accounts.fight_match;
         let house = &mut ctx.accounts.house;
         let clock = Clock::get()?;
@@ -157,26 +217,50 @@ pub mod fight_club {
             fight_match.status == MatchStatus::Open,
             FightClubError::MatchNotOpen
         );
+        require!(
+            clock.unix_timestamp >= fight_match.resolves_at,
+            FightClubError::MatchNotOver
+        );
+        require!(
+            clock.unix_timestamp < fight_match.resolves_at + RESOLUTION_GRACE_SECONDS,
+            FightClubError::ResolutionWindowClosed
+        );
 
-        // Fetch final prices from Pyth
-        let price_update_a = &ctx.accounts.price_update_a;
-        let price_data_a = price_update_a.get_price_no_older_than(&clock, 60, &fight_match.feed_id_a)?;
-        fight_match.end_price_a = price_data_a.price;
+        require!(
+            updates.len() == fight_match.fighters.len(),
+            FightClubError::PriceUpdatesMismatch
+        );
 
-        let price_update_b = &ctx.accounts.price_update_b;
-        let price_data_b = price_update_b.get_price_no_older_than(&clock, 60, &fight_match.feed_id_b)?;
-        fight_match.end_price_b = price_data_b.price;
+        // Fetch closing prices from Pyth, as published at the scheduled end,
+        // and measure each fighter's price change over the match
+        let resolves_at = fight_match.resolves_at;
+        let mut perf = Vec::with_capacity(updates.len());
+        for (fighter, update) in fight_match.fighters.iter_mut().zip(&updates) {
+            let price_data = price_at(update, &fighter.feed_id, resolves_at)?;
+            fighter.end_price = price_data.price;
+            perf.push(performance(
+                Quote::new(fighter.start_price, fighter.start_expo),
+                Quote::new(price_data.price, price_data.exponent),
+            )?);
+        }
 
-        // Calculate performance (%)
-        // Perf = (End - Start) * 10000 / Start (using BPS for precision)
-        let perf_a = (fight_match.end_price_a - fight_match.start_price_a) * 10000 / fight_match.start_price_a;
-        let perf_b = (fight_match.end_price_b - fight_match.start_price_b) * 10000 / fight_match.start_price_b;
+        let pools: Vec<u64> = fight_match.fighters.iter().map(|fighter| fighter.total_bet).collect();
+        let (winner, status) = decide_outcome(&perf, &pools, fight_match.tie_breaker);
+        fight_match.status = status;
+        fight_match.winner = Some(winner);
+        fight_match.resolved_at_slot = clock.slot;
 
-        let winner_side = if perf_a >= perf_b { 0 } else { 1 };
+        let total_pool = fight_match.total_pool();
+        if let MatchStatus::Pushed(reason) = status {
+            msg!("Match pushed ({:?}), {} lamports to refund", reason, total_pool);
+            return Ok(());
+        }
 
-        // Calculate house fee from total pool
-        let total_pool = fight_match.total_bet_a + fight_match.total_bet_b;
-        let house_fee = total_pool * HOUSE_FEE_BPS as u64 / 10000;
+        // Settle once: the house fee comes off the whole pool and the rest
+        // is shared by the winning side at a fixed payout per lamport staked
+        let winning_pool = fight_match.winning_pool();
+        let settlement = Settlement::new(total_pool, winning_pool);
+        let house_fee = settlement.house_fee;
         house.treasury += house_fee;
         house.total_volume += total_pool;
 
@@ -186,22 +270,22 @@ pub mod fight_club {
         **house.to_account_info().try_borrow_mut_lamports()? += house_fee;
 
         // Update match
-        fight_match.status = MatchStatus::Resolved;
-        fight_match.winner = Some(winner_side);
-        fight_match.resolved_at_slot = clock.slot;
         fight_match.house_fee = house_fee;
+        fight_match.net_pool = settlement.net_pool;
+        fight_match.payout_ratio = settlement.payout_ratio;
 
         msg!(
             "Match resolved via Pyth! Winner: {}. Pool: {} lamports",
-            if winner_side == 0 { &fight_match.token_a } else { &fight_match.token_b },
+            fight_match.winning_fighter().map_or("", |fighter| fighter.token.as_str()),
             total_pool
         );
         Ok(())
     }
 
-    /// Claim winnings for a resolved match
+    /// Claim winnings for a resolved match at the payout ratio fixed when
+    /// it was resolved
     pub fn claim_winnings(ctx: Context<ClaimWinnings>) -> Result<()> {
-        let fight_match = &ctx.accounts.fight_match;
+        let fight_match = &mut ctx.accounts.fight_match;
         let player_bet = &mut ctx.accounts.player_bet;
         let player = &ctx.accounts.player;
 
@@ -212,9 +296,8 @@ pub mod fight_club {
         );
 
         // Verify player won
-        let winner_side = fight_match.winner.ok_or(FightClubError::MatchNotResolved)?;
         require!(
-            player_bet.side == winner_side,
+            fight_match.winner == Some(MatchWinner::Fighter(player_bet.side)),
             FightClubError::NotWinner
         );
 
@@ -224,60 +307,17 @@ pub mod fight_club {
             FightClubError::AlreadyClaimed
         );
 
-        // Calculate winnings
-        // Calculate net winnings after house fee
-        let total_losing_pool = if winner_side == 0 {
-            fight_match.total_bet_b
-        } else {
-            fight_match.total_bet_a
-        };
-        
-        let total_winning_pool = if winner_side == 0 {
-            fight_match.total_bet_a
-        } else {
-            fight_match.total_bet_b
-        };
-
-        // The house already took its fee from the total pool during resolve_with_pyth
-        // To be perfectly accurate with pari-mutuel payout, we reduce the losing pool proportionally
-        let house_fee_from_losing = (total_losing_pool * HOUSE_FEE_BPS as u64) / 10000;
-        let house_fee_from_winning = (total_winning_pool * HOUSE_FEE_BPS as u64) / 10000;
-        
-        // The net losing pool available to distribute to winners (minus the fee already taken)
-        let net_losing_pool = total_losing_pool.checked_sub(house_fee_from_losing + house_fee_from_winning).unwrap_or(0);
-
-        // Proportional winnings: (player_bet / total_winning_pool) * net_losing_pool
-        let winnings_from_pool = if total_winning_pool > 0 {
-            (player_bet.amount * net_losing_pool) / total_winning_pool
-        } else {
-            0
-        };
-
-        let total_payout = player_bet.amount + winnings_from_pool;
+        let total_payout = fight_match.settlement().payout(player_bet.amount);
+        let winnings_from_pool = total_payout.saturating_sub(player_bet.amount);
 
         // Mark as claimed
         player_bet.claimed = true;
+        fight_match.claimed_stake += player_bet.amount;
+        fight_match.total_claimed += total_payout;
 
-        // Transfer winnings
-        let match_key = fight_match.key();
-        let seeds = &[
-            b"match",
-            match_key.as_ref(),
-            &[fight_match.bump],
-        ];
-        let signer = &[&seeds[..]];
-
-        anchor_lang::system_program::transfer(
-            CpiContext::new_with_signer(
-                ctx.accounts.system_program.to_account_info(),
-                anchor_lang::system_program::Transfer {
-                    from: fight_match.to_account_info(),
-                    to: player.to_account_info(),
-                },
-                signer,
-            ),
-            total_payout,
-        )?;
+        // The match account holds data, so pay out directly from its lamports
+        **fight_match.to_account_info().try_borrow_mut_lamports()? -= total_payout;
+        **player.to_account_info().try_borrow_mut_lamports()? += total_payout;
 
         msg!(
             "Winnings claimed: {} lamports (bet: {}, winnings: {})",
@@ -311,15 +351,37 @@ pub mod fight_club {
         Ok(())
     }
 
-    /// Refund bet for cancelled match
+    /// Cancel a match nobody managed to resolve with a valid price within
+    /// `RESOLUTION_GRACE_SECONDS` of its end, so its bets can be refunded.
+    /// Permissionless.
+    pub fn cancel_unresolved(ctx: Context<CancelUnresolved>) -> Result<()> {
+        let fight_match = &mut ctx.accounts.fight_match;
+        let clock = Clock::get()?;
+
+        require!(
+            fight_match.status == MatchStatus::Open,
+            FightClubError::MatchNotOpen
+        );
+        require!(
+            clock.unix_timestamp >= fight_match.resolves_at + RESOLUTION_GRACE_SECONDS,
+            FightClubError::ResolutionWindowOpen
+        );
+
+        fight_match.status = MatchStatus::Cancelled;
+
+        msg!("Unresolved match cancelled: {}", fight_match.key());
+        Ok(())
+    }
+
+    /// Refund bet for a cancelled or pushed match
     pub fn refund_bet(ctx: Context<RefundBet>) -> Result<()> {
         let fight_match = &ctx.accounts.fight_match;
         let player_bet = &mut ctx.accounts.player_bet;
         let player = &ctx.accounts.player;
 
-        // Verify match is cancelled
+        // Verify match is cancelled or pushed
         require!(
-            fight_match.status == MatchStatus::Cancelled,
+            matches!(fight_match.status, MatchStatus::Cancelled | MatchStatus::Pushed(_)),
             FightClubError::MatchNotCancelled
         );
 
@@ -333,30 +395,46 @@ pub mod fight_club {
         player_bet.claimed = true;
 
         // Refund the bet amount
-        let match_key = fight_match.key();
-        let seeds = &[
-            b"match",
-            match_key.as_ref(),
-            &[fight_match.bump],
-        ];
-        let signer = &[&seeds[..]];
-
-        anchor_lang::system_program::transfer(
-            CpiContext::new_with_signer(
-                ctx.accounts.system_program.to_account_info(),
-                anchor_lang::system_program::Transfer {
-                    from: fight_match.to_account_info(),
-                    to: player.to_account_info(),
-                },
-                signer,
-            ),
-            player_bet.amount,
-        )?;
+        **fight_match.to_account_info().try_borrow_mut_lamports()? -= player_bet.amount;
+        **player.to_account_info().try_borrow_mut_lamports()? += player_bet.amount;
 
         msg!("Bet refunded: {} lamports", player_bet.amount);
         Ok(())
     }
 
+    /// Sweep rounding dust left in a resolved match's escrow to the house
+    /// once the claim window has passed. Enough is always left behind to pay
+    /// every winner who hasn't claimed yet.
+    pub fn sweep_dust(ctx: Context<SweepDust>) -> Result<()> {
+        let fight_match = &mut ctx.accounts.fight_match;
+        let house = &mut ctx.accounts.house;
+        let clock = Clock::get()?;
+
+        require!(
+            fight_match.status == MatchStatus::Resolved,
+            FightClubError::MatchNotResolved
+        );
+        require!(
+            clock.slot > fight_match.resolved_at_slot + CLAIM_WINDOW_SLOTS,
+            FightClubError::ClaimWindowOpen
+        );
+
+        let info = fight_match.to_account_info();
+        let rent = Rent::get()?.minimum_balance(info.data_len());
+        let unclaimed_stake = fight_match.winning_pool() - fight_match.claimed_stake;
+        let reserved = rent + fight_match.settlement().payout(unclaimed_stake);
+        let dust = info.lamports().saturating_sub(reserved);
+        require!(dust > 0, FightClubError::NoDust);
+
+        **info.try_borrow_mut_lamports()? -= dust;
+        **house.to_account_info().try_borrow_mut_lamports()? += dust;
+        house.treasury += dust;
+        fight_match.dust_swept += dust;
+
+        msg!("Swept {} lamports of dust from match {}", dust, fight_match.key());
+        Ok(())
+    }
+
     /// Withdraw treasury (admin only)
     pub fn withdraw_treasury(ctx: Context<WithdrawTreasury>, amount: u64) -> Result<()> {
         let house = &ctx.accounts.house;
@@ -404,7 +482,31 @@ pub struct CreateMatchV2<'info> {
     #[account(
         init,
         payer = creator,
-        space = 8 + FightMatch::SIZE,
+        space = 8 + FightMatch::space(MIN_FIGHTERS),
+        seeds = [b"match".as_ref(), house.total_matches.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub fight_match: Account<'info, FightMatch>,
+    
+    #[account(mut)]
+    pub house: Account<'info, FightClubHouse>,
+    
+    #[account(mut)]
+    pub creator: Signer<'info>,
+
+    pub price_update_a: Account<'info, PriceUpdateV2>,
+    pub price_update_b: Account<'info, PriceUpdateV2>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(fighters: Vec<FighterSpec>)]
+pub struct CreateBattleRoyale<'info> {
+    #[account(
+        init,
+        payer = creator,
+        space = 8 + FightMatch::space(fighters.len()),
         seeds = [b"match".as_ref(), house.total_matches.to_le_bytes().as_ref()],
         bump
     )]
@@ -427,7 +529,7 @@ pub struct CreateMatch<'info> {
     #[account(
         init,
         payer = creator,
-        space = 8 + FightMatch::SIZE,
+        space = 8 + FightMatch::space(MIN_FIGHTERS),
         seeds = [b"match".as_ref(), house.total_matches.to_le_bytes().as_ref()],
         bump
     )]
@@ -468,13 +570,15 @@ pub struct ResolveWithPyth<'info> {
     #[account(mut)]
     pub fight_match: Account<'info, FightMatch>,
     
-    #[account(mut)]
+    #[account(
+        mut,
+        seeds = [b"fight_club_house"],
+        bump = house.bump,
+    )]
     pub house: Account<'info, FightClubHouse>,
 
     pub price_update_a: Account<'info, PriceUpdateV2>,
     pub price_update_b: Account<'info, PriceUpdateV2>,
-    
-    pub authority: Signer<'info>,
 }
 
 #[derive(Accounts)]
@@ -490,11 +594,7 @@ pub struct ResolveMatch<'info> {
 
 #[derive(Accounts)]
 pub struct ClaimWinnings<'info> {
-    #[account(
-        mut,
-        seeds = [b"match".as_ref(), fight_match.key().as_ref()],
-        bump = fight_match.bump,
-    )]
+    #[account(mut)]
     pub fight_match: Account<'info, FightMatch>,
     
     #[account(
@@ -520,13 +620,15 @@ pub struct CancelMatch<'info> {
     pub authority: Signer<'info>,
 }
 
+#[derive(Accounts)]
+pub struct CancelUnresolved<'info> {
+    #[account(mut)]
+    pub fight_match: Account<'info, FightMatch>,
+}
+
 #[derive(Accounts)]
 pub struct RefundBet<'info> {
-    #[account(
-        mut,
-        seeds = [b"match".as_ref(), fight_match.key().as_ref()],
-        bump = fight_match.bump,
-    )]
+    #[account(mut)]
     pub fight_match: Account<'info, FightMatch>,
     
     #[account(
@@ -542,6 +644,19 @@ pub struct RefundBet<'info> {
     pub system_program: Program<'info, System>,
 }
 
+#[derive(Accounts)]
+pub struct SweepDust<'info> {
+    #[account(mut)]
+    pub fight_match: Account<'info, FightMatch>,
+    
+    #[account(
+        mut,
+        seeds = [b"fight_club_house"],
+        bump = house.bump,
+    )]
+    pub house: Account<'info, FightClubHouse>,
+}
+
 #[derive(Accounts)]
 pub struct WithdrawTreasury<'info> {
     #[account(
@@ -575,28 +690,254 @@ impl FightClubHouse {
 #[account]
 pub struct FightMatch {
     pub creator: Pubkey,
-    pub token_a: String,      // 4 bytes length + 10 chars max
-    pub token_b: String,      // 4 bytes length + 10 chars max
-    pub feed_id_a: [u8; 32],
-    pub feed_id_b: [u8; 32],
-    pub start_price_a: i64,
-    pub start_price_b: i64,
-    pub end_price_a: i64,
-    pub end_price_b: i64,
-    pub total_bet_a: u64,
-    pub total_bet_b: u64,
-    pub player_count_a: u32,
-    pub player_count_b: u32,
+    pub fighters: Vec<Fighter>, // Token A and B, or 2 to `MAX_FIGHTERS` royale fighters
     pub status: MatchStatus,
     pub created_at_slot: u64,
     pub resolved_at_slot: u64,
-    pub winner: Option<u8>,   // 0 = Token A, 1 = Token B
+    pub betting_closes_at: i64, // No bets at or after this unix timestamp
+    pub resolves_at: i64,       // Unix timestamp the closing prices are taken at
+    pub winner: Option<MatchWinner>,
+    pub tie_breaker: TieBreaker,
     pub house_fee: u64,
+    pub net_pool: u64,        // Pool after the house fee, shared by the winners
+    pub payout_ratio: u128,   // Lamports paid per lamport staked on the winner, scaled by `PAYOUT_RATIO_SCALE`
+    pub claimed_stake: u64,   // Winning stakes claimed so far
+    pub total_claimed: u64,   // Lamports paid out to winners so far
+    pub dust_swept: u64,      // Rounding dust swept to the house
     pub bump: u8,
 }
 
 impl FightMatch {
-    pub const SIZE: usize = 32 + (4 + 10) + (4 + 10) + 32 + 32 + 8 + 8 + 8 + 8 + 8 + 8 + 4 + 4 + 1 + 8 + 8 + 1 + 8 + 1;
+    /// Account size for a match between `fighters` fighters.
+    pub const fn space(fighters: usize) -> usize {
+        32 + (4 + fighters * Fighter::SIZE) + 2 + 8 + 8 + 8 + 8 + (1 + 2) + 2 + 8 + 8 + 16 + 8 + 8 + 8 + 1
+    }
+
+    /// Start a match between `fighters` on the given schedule.
+    fn open(
+        &mut self,
+        fighters: Vec<Fighter>,
+        tie_breaker: TieBreaker,
+        betting_closes_at: i64,
+        resolves_at: i64,
+        clock: &Clock,
+    ) -> Result<()> {
+        require!(
+            !matches!(tie_breaker, TieBreaker::Fighter(side) if side as usize >= fighters.len()),
+            FightClubError::InvalidSide
+        );
+        // Betting has to close before the fight ends, or the last bets are
+        // placed knowing the result
+        require!(
+            clock.unix_timestamp < betting_closes_at
+                && betting_closes_at < resolves_at
+                && resolves_at <= clock.unix_timestamp + MAX_MATCH_SECONDS,
+            FightClubError::InvalidSchedule
+        );
+
+        self.fighters = fighters;
+        self.status = MatchStatus::Open;
+        self.created_at_slot = clock.slot;
+        self.betting_closes_at = betting_closes_at;
+        self.resolves_at = resolves_at;
+        self.winner = None;
+        self.tie_breaker = tie_breaker;
+        self.house_fee = 0;
+        self.net_pool = 0;
+        self.payout_ratio = 0;
+        self.claimed_stake = 0;
+        self.total_claimed = 0;
+        self.dust_swept = 0;
+        Ok(())
+    }
+
+    /// Total staked on every fighter.
+    pub fn total_pool(&self) -> u64 {
+        self.fighters.iter().map(|fighter| fighter.total_bet).sum()
+    }
+
+    pub fn winning_fighter(&self) -> Option<&Fighter> {
+        match self.winner {
+            Some(MatchWinner::Fighter(side)) => self.fighters.get(side as usize),
+            Some(MatchWinner::Tie) | None => None,
+        }
+    }
+
+    /// Total staked on the winning side.
+    pub fn winning_pool(&self) -> u64 {
+        self.winning_fighter().map_or(0, |fighter| fighter.total_bet)
+    }
+
+    /// The settlement fixed at resolution.
+    pub fn settlement(&self) -> Settlement {
+        Settlement {
+            house_fee: self.house_fee,
+            net_pool: self.net_pool,
+            payout_ratio: self.payout_ratio,
+        }
+    }
+}
+
+/// A token in a match, as given when creating it.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct FighterSpec {
+    pub token: String,
+    pub feed_id: [u8; 32],
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct Fighter {
+    pub token: String,        // 4 bytes length + 10 chars max
+    pub feed_id: [u8; 32],
+    pub start_price: i64,
+    pub start_expo: i32,      // Pyth exponent of the start price
+    pub end_price: i64,
+    pub total_bet: u64,
+    pub player_count: u32,
+}
+
+impl Fighter {
+    pub const SIZE: usize = (4 + MAX_TOKEN_LEN) + 32 + 8 + 4 + 8 + 8 + 4;
+}
+
+/// Fighters for `specs`, starting at their current prices in `updates`,
+/// one per fighter in the same order.
+fn start_fighters(specs: Vec<FighterSpec>, updates: &[PriceUpdateV2], clock: &Clock) -> Result<Vec<Fighter>> {
+    require!(
+        (MIN_FIGHTERS..=MAX_FIGHTERS).contains(&specs.len()),
+        FightClubError::InvalidFighterCount
+    );
+    require!(
+        updates.len() == specs.len(),
+        FightClubError::PriceUpdatesMismatch
+    );
+
+    let mut fighters = Vec::with_capacity(specs.len());
+    for (spec, update) in specs.into_iter().zip(updates) {
+        require!(spec.token.len() <= MAX_TOKEN_LEN, FightClubError::TokenNameTooLong);
+        let price_data = update.get_price_no_older_than(clock, 60, &spec.feed_id)?;
+        require!(is_confident(&price_data), FightClubError::PriceTooUncertain);
+        fighters.push(Fighter {
+            token: spec.token,
+            feed_id: spec.feed_id,
+            start_price: price_data.price,
+            start_expo: price_data.exponent,
+            end_price: 0,
+            total_bet: 0,
+            player_count: 0,
+        });
+    }
+    Ok(fighters)
+}
+
+/// Price updates of a match's fighters: the two named accounts, then any
+/// passed as remaining accounts, which must be Pyth receiver accounts too.
+fn price_updates<'info>(
+    price_update_a: &Account<'info, PriceUpdateV2>,
+    price_update_b: &Account<'info, PriceUpdateV2>,
+    remaining: &'info [AccountInfo<'info>],
+) -> Result<Vec<PriceUpdateV2>> {
+    let mut updates = vec![(**price_update_a).clone(), (**price_update_b).clone()];
+    for info in remaining {
+        let update: Account<'info, PriceUpdateV2> = Account::try_from(info)?;
+        updates.push((*update).clone());
+    }
+    Ok(updates)
+}
+
+/// Price of `feed_id` at `at` from a fully verified update: the first one
+/// published at or after it, no more than `MAX_PRICE_LAG_SECONDS` late, with
+/// a confidence interval no wider than `MAX_CONF_BPS` of the price.
+fn price_at(price_update: &PriceUpdateV2, feed_id: &FeedId, at: i64) -> Result<Price> {
+    require!(
+        price_update.verification_level.gte(VerificationLevel::Full),
+        GetPriceError::InsufficientVerificationLevel
+    );
+    let price = price_update.get_price_unchecked(feed_id)?;
+    require!(
+        published_for(price.publish_time, price_update.price_message.prev_publish_time, at),
+        FightClubError::PriceNotAtResolution
+    );
+    require!(is_confident(&price), FightClubError::PriceTooUncertain);
+    Ok(price)
+}
+
+/// Whether a price is positive and its confidence interval is within
+/// `MAX_CONF_BPS` of it.
+pub fn is_confident(price: &Price) -> bool {
+    price.price > 0 && price.conf as u128 * 10_000 <= price.price as u128 * MAX_CONF_BPS as u128
+}
+
+/// Whether an update published at `publish_time`, whose feed last
+/// published at `prev_publish_time`, is the price at `at`. Pyth makes that
+/// the single update with `prev_publish_time < at <= publish_time`, so
+/// whoever resolves can't choose between updates. It must also come no
+/// more than `MAX_PRICE_LAG_SECONDS` after `at`.
+pub fn published_for(publish_time: i64, prev_publish_time: i64, at: i64) -> bool {
+    prev_publish_time < at && at <= publish_time && publish_time - at <= MAX_PRICE_LAG_SECONDS
+}
+
+/// Winner and status of a match from each fighter's performance and the
+/// amount staked on each. The best performer wins. Matches that can't be
+/// settled pari-mutuel are pushed: a tie for first the tie-breaker doesn't
+/// settle, nobody backing the winner, or nobody backing anyone else.
+pub fn decide_outcome(
+    perf: &[i128],
+    pools: &[u64],
+    tie_breaker: TieBreaker,
+) -> (MatchWinner, MatchStatus) {
+    let best = perf.iter().copied().max().unwrap_or_default();
+    let mut leaders = (0..perf.len()).filter(|&side| perf[side] == best);
+    let side = match (leaders.next(), leaders.next()) {
+        (Some(side), None) => side,
+        // The tie-breaker only picks among the fighters tied for first
+        _ => match tie_breaker {
+            TieBreaker::Fighter(side) if perf.get(side as usize) == Some(&best) => side as usize,
+            _ => return (MatchWinner::Tie, MatchStatus::Pushed(PushReason::Tie)),
+        },
+    };
+    let winner = MatchWinner::Fighter(side as u8);
+    let winning_pool = pools.get(side).copied().unwrap_or_default();
+    let status = if winning_pool == 0 {
+        MatchStatus::Pushed(PushReason::NoWinningBets)
+    } else if pools.iter().sum::<u64>() == winning_pool {
+        MatchStatus::Pushed(PushReason::NoLosingBets)
+    } else {
+        MatchStatus::Resolved
+    };
+    (winner, status)
+}
+
+/// Pari-mutuel settlement of a match, computed once at resolution.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Settlement {
+    pub house_fee: u64,
+    pub net_pool: u64,
+    pub payout_ratio: u128,
+}
+
+impl Settlement {
+    /// Take the house fee off `total_pool` and share the rest across
+    /// `winning_pool`. The ratio rounds down, so payouts never exceed the
+    /// net pool.
+    pub fn new(total_pool: u64, winning_pool: u64) -> Self {
+        let house_fee = (total_pool as u128 * HOUSE_FEE_BPS as u128 / 10_000) as u64;
+        let net_pool = total_pool - house_fee;
+        let payout_ratio = (net_pool as u128 * PAYOUT_RATIO_SCALE)
+            .checked_div(winning_pool as u128)
+            .unwrap_or(0);
+        Settlement {
+            house_fee,
+            net_pool,
+            payout_ratio,
+        }
+    }
+
+    /// Payout, stake included, for `stake` lamports on the winner. Paying
+    /// stakes separately never costs more than paying their sum at once.
+    pub fn payout(&self, stake: u64) -> u64 {
+        (stake as u128 * self.payout_ratio / PAYOUT_RATIO_SCALE) as u64
+    }
 }
 
 #[account]
@@ -604,7 +945,7 @@ pub struct PlayerBet {
     pub player: Pubkey,
     pub match_pda: Pubkey,
     pub amount: u64,
-    pub side: u8,            // 0 = Token A, 1 = Token B
+    pub side: u8,            // Index into `FightMatch::fighters`: 0 = Token A, 1 = Token B
     pub claimed: bool,
     pub bump: u8,
 }
@@ -615,11 +956,32 @@ impl PlayerBet {
 
 // Enums
 
-#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
 pub enum MatchStatus {
     Open,
     Resolved,
     Cancelled,
+    Pushed(PushReason), // Settled without a pari-mutuel payout; every bet is refunded
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PushReason {
+    Tie,
+    NoWinningBets,
+    NoLosingBets,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum MatchWinner {
+    Fighter(u8), // Index into `FightMatch::fighters`: 0 = Token A, 1 = Token B
+    Tie,
+}
+
+/// How a match is decided when both fighters perform exactly the same.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TieBreaker {
+    Push,        // Refund everyone
+    Fighter(u8), // Award the match to this fighter
 }
 
 // Errors
@@ -648,4 +1010,189 @@ pub enum FightClubError {
     UnauthorizedHouse,
     #[msg("Insufficient treasury balance")]
     InsufficientTreasury,
+    #[msg("Claim window has not closed yet")]
+    ClaimWindowOpen,
+    #[msg("No dust to sweep")]
+    NoDust,
+    #[msg("Betting must close after creation and before the match resolves")]
+    InvalidSchedule,
+    #[msg("Betting is closed for this match")]
+    BettingClosed,
+    #[msg("Match has not reached its resolution time")]
+    MatchNotOver,
+    #[msg("Price was not published at the match's resolution time")]
+    PriceNotAtResolution,
+    #[msg("Price confidence interval is too wide")]
+    PriceTooUncertain,
+    #[msg("Match can no longer be resolved")]
+    ResolutionWindowClosed,
+    #[msg("Match can still be resolved")]
+    ResolutionWindowOpen,
+    #[msg("Price must be positive")]
+    InvalidPrice,
+    #[msg("Price performance is out of range")]
+    PerformanceOverflow,
+    #[msg("A match needs 2 to 8 fighters")]
+    InvalidFighterCount,
+    #[msg("Token name is too long")]
+    TokenNameTooLong,
+    #[msg("Price updates don't match the match's fighters")]
+    PriceUpdatesMismatch,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    fn bets() -> impl Strategy<Value = Vec<u64>> {
+        prop::collection::vec(MIN_BET_LAMPORTS..=MAX_BET_LAMPORTS, 0..64)
+    }
+
+    proptest! {
+        #[test]
+        fn claims_and_fee_account_for_the_whole_pool(winners in bets(), losers in bets()) {
+            let winning_pool: u64 = winners.iter().sum();
+            let total_pool = winning_pool + losers.iter().sum::<u64>();
+            let settlement = Settlement::new(total_pool, winning_pool);
+
+            let claims: u64 = winners.iter().map(|&stake| settlement.payout(stake)).sum();
+            prop_assert!(claims <= settlement.net_pool);
+            let dust = settlement.net_pool - claims;
+            prop_assert_eq!(claims + settlement.house_fee + dust, total_pool);
+            if winning_pool > 0 {
+                // Each claim rounds down by less than a lamport, and so does the ratio
+                prop_assert!(dust <= winners.len() as u64 + 1);
+            }
+        }
+
+        #[test]
+        fn winners_never_lose_more_than_the_fee(winners in bets(), losers in bets()) {
+            let winning_pool: u64 = winners.iter().sum();
+            let total_pool = winning_pool + losers.iter().sum::<u64>();
+            let settlement = Settlement::new(total_pool, winning_pool);
+
+            for &stake in winners.iter() {
+                let fee = (stake as u128 * HOUSE_FEE_BPS as u128).div_ceil(10_000) as u64;
+                prop_assert!(settlement.payout(stake) + fee + 1 >= stake);
+            }
+        }
+
+        #[test]
+        fn sweep_reserve_covers_unclaimed_winners(winners in bets(), claimed in 0usize..64) {
+            let winning_pool: u64 = winners.iter().sum();
+            let settlement = Settlement::new(winning_pool * 2, winning_pool);
+            let claimed = claimed.min(winners.len());
+
+            let unclaimed = &winners[claimed..];
+            let reserve = settlement.payout(unclaimed.iter().sum());
+            let owed: u64 = unclaimed.iter().map(|&stake| settlement.payout(stake)).sum();
+            prop_assert!(reserve >= owed);
+        }
+    }
+
+    #[test]
+    fn ties_push_or_go_to_the_tie_breaker() {
+        let pools = [MIN_BET_LAMPORTS, MIN_BET_LAMPORTS];
+        assert_eq!(
+            decide_outcome(&[5, 5], &pools, TieBreaker::Push),
+            (MatchWinner::Tie, MatchStatus::Pushed(PushReason::Tie))
+        );
+        assert_eq!(
+            decide_outcome(&[5, 5], &pools, TieBreaker::Fighter(1)),
+            (MatchWinner::Fighter(1), MatchStatus::Resolved)
+        );
+        assert_eq!(
+            decide_outcome(&[-3, 5], &pools, TieBreaker::Fighter(0)),
+            (MatchWinner::Fighter(1), MatchStatus::Resolved)
+        );
+    }
+
+    #[test]
+    fn one_sided_pools_push() {
+        assert_eq!(
+            decide_outcome(&[10, 5], &[0, MIN_BET_LAMPORTS], TieBreaker::Push),
+            (MatchWinner::Fighter(0), MatchStatus::Pushed(PushReason::NoWinningBets))
+        );
+        assert_eq!(
+            decide_outcome(&[10, 5], &[MIN_BET_LAMPORTS, 0], TieBreaker::Push),
+            (MatchWinner::Fighter(0), MatchStatus::Pushed(PushReason::NoLosingBets))
+        );
+        assert_eq!(
+            decide_outcome(&[10, 5], &[0, 0], TieBreaker::Push),
+            (MatchWinner::Fighter(0), MatchStatus::Pushed(PushReason::NoWinningBets))
+        );
+    }
+
+    #[test]
+    fn best_royale_fighter_takes_the_pool() {
+        let pools = [MIN_BET_LAMPORTS, 0, MIN_BET_LAMPORTS, MIN_BET_LAMPORTS];
+        assert_eq!(
+            decide_outcome(&[-4, 7, 12, 3], &pools, TieBreaker::Push),
+            (MatchWinner::Fighter(2), MatchStatus::Resolved)
+        );
+        assert_eq!(
+            decide_outcome(&[-4, 12, 7, 3], &pools, TieBreaker::Push),
+            (MatchWinner::Fighter(1), MatchStatus::Pushed(PushReason::NoWinningBets))
+        );
+        // Only the losers' stakes count as the other side
+        assert_eq!(
+            decide_outcome(&[12, 7, -4, 3], &[MIN_BET_LAMPORTS, 0, 0, 0], TieBreaker::Push),
+            (MatchWinner::Fighter(0), MatchStatus::Pushed(PushReason::NoLosingBets))
+        );
+    }
+
+    #[test]
+    fn royale_tie_breaker_only_settles_ties_for_first() {
+        let pools = [MIN_BET_LAMPORTS; 4];
+        assert_eq!(
+            decide_outcome(&[12, 3, 12, -4], &pools, TieBreaker::Fighter(2)),
+            (MatchWinner::Fighter(2), MatchStatus::Resolved)
+        );
+        assert_eq!(
+            decide_outcome(&[12, 3, 12, -4], &pools, TieBreaker::Fighter(1)),
+            (MatchWinner::Tie, MatchStatus::Pushed(PushReason::Tie))
+        );
+        assert_eq!(
+            decide_outcome(&[12, 3, 12, -4], &pools, TieBreaker::Fighter(7)),
+            (MatchWinner::Tie, MatchStatus::Pushed(PushReason::Tie))
+        );
+    }
+
+    #[test]
+    fn closing_prices_are_published_at_resolution() {
+        let at = 1_700_000_000;
+        assert!(published_for(at, at - 1, at));
+        assert!(published_for(at + 3, at - 2, at));
+        assert!(published_for(at + MAX_PRICE_LAG_SECONDS, at - 1, at));
+        assert!(!published_for(at + MAX_PRICE_LAG_SECONDS + 1, at - 1, at));
+        // Only the first update at or after resolution: not one before it,
+        // and not a later one when an earlier one already covered `at`
+        assert!(!published_for(at - 1, at - 2, at));
+        assert!(!published_for(at + 2, at, at));
+        assert!(!published_for(at + 2, at + 1, at));
+    }
+
+    #[test]
+    fn wide_or_nonpositive_prices_are_rejected() {
+        let price = |price, conf| Price {
+            price,
+            conf,
+            exponent: -8,
+            publish_time: 0,
+        };
+        assert!(is_confident(&price(100_000, 100_000 * MAX_CONF_BPS / 10_000)));
+        assert!(!is_confident(&price(100_000, 100_000 * MAX_CONF_BPS / 10_000 + 1)));
+        assert!(!is_confident(&price(0, 0)));
+        assert!(!is_confident(&price(-100_000, 0)));
+        assert!(is_confident(&price(i64::MAX, 0)));
+        assert!(!is_confident(&price(i64::MAX, u64::MAX)));
+    }
+
+    #[test]
+    fn settlement_has_no_winners_to_pay() {
+        let settlement = Settlement::new(10 * MIN_BET_LAMPORTS, 0);
+        assert_eq!(settlement.payout_ratio, 0);
+        assert_eq!(settlement.net_pool + settlement.house_fee, 10 * MIN_BET_LAMPORTS);
+    }
 }
@@ -0,0 +1,125 @@
+//! Fixed-point price performance of a fighter over a match.
+//!
+//! Pyth prices are an `i64` mantissa and a power-of-ten exponent, and a
+//! feed's exponent isn't guaranteed to stay the same between the start and
+//! the end of a match. Both prices are brought to the smaller exponent
+//! before comparing them, and everything runs in checked `i128`, so a huge
+//! raw price or a wild exponent fails resolution with an error instead of
+//! panicking (or dividing by zero) inside the program.
+
+use anchor_lang::prelude::*;
+
+use crate::FightClubError;
+
+/// Fixed-point scale of a performance: `PERFORMANCE_SCALE` is +100%.
+pub const PERFORMANCE_SCALE: i128 = 1_000_000_000_000;
+
+/// A Pyth price: `price * 10^expo`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Quote {
+    pub price: i64,
+    pub expo: i32,
+}
+
+impl Quote {
+    pub fn new(price: i64, expo: i32) -> Self {
+        Quote { price, expo }
+    }
+}
+
+/// Relative change from `start` to `end`, scaled by `PERFORMANCE_SCALE`
+/// and rounded toward zero. Both prices must be positive.
+pub fn performance(start: Quote, end: Quote) -> Result<i128> {
+    require!(start.price > 0 && end.price > 0, FightClubError::InvalidPrice);
+
+    let expo = start.expo.min(end.expo);
+    let start = normalize(start, expo)?;
+    let end = normalize(end, expo)?;
+
+    end.checked_sub(start)
+        .and_then(|change| change.checked_mul(PERFORMANCE_SCALE))
+        .map(|change| change / start)
+        .ok_or_else(|| error!(FightClubError::PerformanceOverflow))
+}
+
+/// Mantissa of `quote` at exponent `expo`, which must not exceed its own.
+fn normalize(quote: Quote, expo: i32) -> Result<i128> {
+    let shift = quote.expo.abs_diff(expo);
+    10i128
+        .checked_pow(shift)
+        .and_then(|factor| factor.checked_mul(quote.price as i128))
+        .ok_or_else(|| error!(FightClubError::PerformanceOverflow))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    fn perf(start: (i64, i32), end: (i64, i32)) -> Result<i128> {
+        performance(Quote::new(start.0, start.1), Quote::new(end.0, end.1))
+    }
+
+    #[test]
+    fn measures_relative_change() {
+        assert_eq!(perf((100, -2), (150, -2)).unwrap(), PERFORMANCE_SCALE / 2);
+        assert_eq!(perf((100, -2), (50, -2)).unwrap(), -PERFORMANCE_SCALE / 2);
+        assert_eq!(perf((100, -2), (100, -2)).unwrap(), 0);
+        // 1.00 to 1.5000 across an exponent change
+        assert_eq!(perf((100, -2), (15_000, -4)).unwrap(), PERFORMANCE_SCALE / 2);
+    }
+
+    #[test]
+    fn survives_extreme_prices() {
+        assert_eq!(perf((i64::MAX, -8), (i64::MAX, -8)).unwrap(), 0);
+        assert_eq!(
+            perf((1, -8), (i64::MAX, -8)).unwrap(),
+            (i64::MAX as i128 - 1) * PERFORMANCE_SCALE
+        );
+        assert_eq!(
+            perf((i64::MAX, -8), (1, -8)).unwrap(),
+            (1 - i64::MAX as i128) * PERFORMANCE_SCALE / i64::MAX as i128
+        );
+        assert!(perf((1, 0), (i64::MAX, -18)).is_ok());
+    }
+
+    #[test]
+    fn rejects_pathological_prices() {
+        assert!(perf((0, -8), (100, -8)).is_err());
+        assert!(perf((100, -8), (0, -8)).is_err());
+        assert!(perf((-100, -8), (100, -8)).is_err());
+        assert!(perf((i64::MIN, -8), (i64::MAX, -8)).is_err());
+        assert!(perf((1, i32::MAX), (1, i32::MIN)).is_err());
+        assert!(perf((i64::MAX, 0), (1, -40)).is_err());
+    }
+
+    proptest! {
+        #[test]
+        fn never_panics(
+            start in any::<i64>(),
+            end in any::<i64>(),
+            start_expo in any::<i32>(),
+            end_expo in any::<i32>(),
+        ) {
+            let _ = perf((start, start_expo), (end, end_expo));
+        }
+
+        #[test]
+        fn follows_the_price(start in 1..=i64::MAX, end in 1..=i64::MAX, expo in -18i32..=0) {
+            let change = perf((start, expo), (end, expo)).unwrap();
+            prop_assert_eq!(change.signum(), (end as i128 - start as i128).signum());
+        }
+
+        #[test]
+        fn ignores_the_exponent_prices_are_quoted_in(
+            start in 1..=i64::MAX / 10,
+            end in 1..=i64::MAX / 10,
+            expo in -17i32..=0,
+        ) {
+            prop_assert_eq!(
+                perf((start, expo), (end, expo)).unwrap(),
+                perf((start * 10, expo - 1), (end, expo)).unwrap()
+            );
+        }
+    }
+}
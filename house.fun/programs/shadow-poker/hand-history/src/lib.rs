@@ -0,0 +1,1001 @@
+//! Decoder and replay verifier for Shadow Poker hand histories.
+//!
+//! Every hand emits an ordered log of Anchor events (`HandStarted`,
+//! `ForcedBetPosted`, `ActionTaken`, `BoardDealt`, `CardsShown`,
+//! `PotAwarded`, or `HandAborted` when a mental poker hand is called off)
+//! and closes with `HandCompleted`, which carries a hash
+//! chained over all of them. This crate decodes those events from
+//! transaction logs, rebuilds the hand, re-applies the program's betting
+//! rules to every step, and checks the result against the committed hash.
+//! The main pot and side pots are rebuilt from what each player put in, and
+//! when every player who can win a pot shows down, its winner is also
+//! checked with the program's hand evaluator.
+
+use std::fmt;
+
+use anchor_lang::{AnchorDeserialize, Discriminator};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use shadow_poker::{
+    chain_history, hand_eval::best_hand, next_seat_in, pots::{self, Pot, Share}, ActionTaken,
+    BettingStructure, BoardDealt, Card, CardsShown, ForcedBetKind, ForcedBetPosted, GameVariant,
+    HandAborted, HandCompleted, HandStarted, PlayerActionType, PotAwarded,
+    MENTAL_POKER_PENALTY_BBS, STRADDLE_BBS,
+};
+
+const PROGRAM_DATA_PREFIX: &str = "Program data: ";
+
+/// A decoded hand-history event.
+#[derive(Clone, Debug)]
+pub enum HandEvent {
+    Started(HandStarted),
+    ForcedBet(ForcedBetPosted),
+    Action(ActionTaken),
+    Board(BoardDealt),
+    Shown(CardsShown),
+    Awarded(PotAwarded),
+    Aborted(HandAborted),
+    Completed(HandCompleted),
+}
+
+/// An event together with the raw bytes the program hashed.
+#[derive(Clone, Debug)]
+pub struct RecordedEvent {
+    pub event: HandEvent,
+    pub data: Vec<u8>,
+}
+
+/// Decode raw event data (discriminator followed by the Borsh payload).
+pub fn decode_event(data: &[u8]) -> Option<HandEvent> {
+    fn parse<E: Discriminator + AnchorDeserialize>(data: &[u8]) -> Option<E> {
+        let payload = data.strip_prefix(E::DISCRIMINATOR)?;
+        E::try_from_slice(payload).ok()
+    }
+
+    parse::<HandStarted>(data)
+        .map(HandEvent::Started)
+        .or_else(|| parse::<ForcedBetPosted>(data).map(HandEvent::ForcedBet))
+        .or_else(|| parse::<ActionTaken>(data).map(HandEvent::Action))
+        .or_else(|| parse::<BoardDealt>(data).map(HandEvent::Board))
+        .or_else(|| parse::<CardsShown>(data).map(HandEvent::Shown))
+        .or_else(|| parse::<PotAwarded>(data).map(HandEvent::Awarded))
+        .or_else(|| parse::<HandAborted>(data).map(HandEvent::Aborted))
+        .or_else(|| parse::<HandCompleted>(data).map(HandEvent::Completed))
+}
+
+/// Pull every hand-history event out of transaction log lines, in order.
+/// Lines that aren't Shadow Poker events are skipped.
+pub fn decode_logs<'a>(logs: impl IntoIterator<Item = &'a str>) -> Vec<RecordedEvent> {
+    logs.into_iter()
+        .filter_map(|line| line.strip_prefix(PROGRAM_DATA_PREFIX))
+        .filter_map(|encoded| STANDARD.decode(encoded.trim()).ok())
+        .filter_map(|data| decode_event(&data).map(|event| RecordedEvent { event, data }))
+        .collect()
+}
+
+/// Why a hand failed to replay.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ReplayError {
+    /// The log doesn't open with `HandStarted`.
+    MissingStart,
+    /// An event belongs to another table or hand.
+    ForeignEvent { seq: u32 },
+    /// Events are missing or out of order.
+    OutOfSequence { expected: u32, found: u32 },
+    /// An event refers to a seat that wasn't dealt in.
+    UnknownSeat { seq: u32, seat: u8 },
+    /// A forced bet doesn't match the table's blinds and antes.
+    InvalidForcedBet { seq: u32 },
+    /// A player acted out of turn.
+    OutOfTurn { seq: u32, expected: Option<u8>, found: u8 },
+    /// An action broke the betting rules.
+    IllegalAction { seq: u32 },
+    /// Board cards were dealt in the wrong quantity or aren't in the deck.
+    InvalidBoard { seq: u32 },
+    /// The pot was awarded to the wrong player or for the wrong amount.
+    InvalidAward { seq: u32 },
+    /// An aborted hand didn't refund or penalise players correctly.
+    InvalidAbort { seq: u32 },
+    /// Events followed the pot being awarded or the hand being aborted.
+    EventAfterAward { seq: u32 },
+    /// The recomputed history hash doesn't match `HandCompleted`.
+    HashMismatch,
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReplayError::MissingStart => write!(f, "hand history does not start with HandStarted"),
+            ReplayError::ForeignEvent { seq } => write!(f, "event {seq} belongs to another hand"),
+            ReplayError::OutOfSequence { expected, found } => {
+                write!(f, "expected event {expected}, found {found}")
+            }
+            ReplayError::UnknownSeat { seq, seat } => {
+                write!(f, "event {seq} refers to seat {seat}, which was not dealt in")
+            }
+            ReplayError::InvalidForcedBet { seq } => write!(f, "event {seq} is an invalid forced bet"),
+            ReplayError::OutOfTurn { seq, expected, found } => write!(
+                f,
+                "event {seq}: seat {found} acted but the action was on {expected:?}"
+            ),
+            ReplayError::IllegalAction { seq } => write!(f, "event {seq} is an illegal action"),
+            ReplayError::InvalidBoard { seq } => write!(f, "event {seq} deals an invalid board"),
+            ReplayError::InvalidAward { seq } => write!(f, "event {seq} awards the pot incorrectly"),
+            ReplayError::InvalidAbort { seq } => write!(f, "event {seq} aborts the hand incorrectly"),
+            ReplayError::EventAfterAward { seq } => {
+                write!(f, "event {seq} follows the end of the hand")
+            }
+            ReplayError::HashMismatch => write!(f, "history hash does not match the commitment"),
+        }
+    }
+}
+
+impl std::error::Error for ReplayError {}
+
+/// A seat as reconstructed by the replay.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SeatReplay {
+    pub seat: u8,
+    pub player: anchor_lang::prelude::Pubkey,
+    pub starting_stack: u64,
+    pub stack: u64,
+    pub current_bet: u64,
+    pub contribution: u64,
+    pub in_hand: bool,
+    pub all_in: bool,
+    pub shown: Option<Vec<Card>>,
+}
+
+/// A fully replayed hand.
+#[derive(Clone, Debug)]
+pub struct ReplayedHand {
+    pub table: anchor_lang::prelude::Pubkey,
+    pub hand_number: u64,
+    pub variant: GameVariant,
+    pub betting: BettingStructure,
+    pub button: u8,
+    pub seats: Vec<SeatReplay>,
+    pub board: Vec<Card>,
+    /// The second board when the hand was run twice.
+    pub second_board: Vec<Card>,
+    pub pot: u64,
+    /// Main pot and side pots, in the order they were awarded.
+    pub pots: Vec<Pot>,
+    /// Each share of a pot awarded, with its winner.
+    pub awards: Vec<PotAwarded>,
+    /// Total paid out to the winners, after rake.
+    pub payout: u64,
+    pub rake: u64,
+    /// Seats blamed for calling the hand off, if it was aborted.
+    pub aborted_by: Option<u16>,
+    /// Hash recomputed over the replayed events.
+    pub history_hash: [u8; 32],
+    /// Whether a `HandCompleted` commitment was present and matched.
+    pub verified: bool,
+}
+
+struct Replay {
+    hand: ReplayedHand,
+    max_players: u8,
+    big_blind_seat: u8,
+    small_blind_seat: u8,
+    small_blind: u64,
+    big_blind: u64,
+    ante: u64,
+    rake_bps: u16,
+    rake_cap: u64,
+    is_tournament: bool,
+    play_money: bool,
+    current_bet: u64,
+    min_raise: u64,
+    raises: u8,
+    to_act: Option<u8>,
+    betting_started: bool,
+    straddle_seat: Option<u8>,
+    /// Board length when nobody was left to act, after which the rest of
+    /// the board may be run twice.
+    action_closed_at: Option<usize>,
+    /// Shares the pots are awarded in, once the first one is.
+    shares: Option<Vec<Share>>,
+}
+
+impl Replay {
+    fn seat_mut(&mut self, seq: u32, seat: u8) -> Result<&mut SeatReplay, ReplayError> {
+        self.hand
+            .seats
+            .iter_mut()
+            .find(|s| s.seat == seat)
+            .ok_or(ReplayError::UnknownSeat { seq, seat })
+    }
+
+    fn mask(&self, f: impl Fn(&SeatReplay) -> bool) -> u16 {
+        self.hand
+            .seats
+            .iter()
+            .filter(|s| f(s))
+            .fold(0, |mask, s| mask | (1 << s.seat))
+    }
+
+    fn next_to_act(&self, from: u8) -> Option<u8> {
+        next_seat_in(self.max_players, from, self.mask(|s| s.in_hand && !s.all_in))
+    }
+
+    /// Mirror of the program's forced-bet helper.
+    fn post(seat: &mut SeatReplay, amount: u64) -> u64 {
+        let posted = amount.min(seat.stack);
+        seat.stack -= posted;
+        seat.current_bet += posted;
+        seat.contribution += posted;
+        if seat.stack == 0 {
+            seat.all_in = true;
+        }
+        posted
+    }
+
+    fn forced_bet(&mut self, e: &ForcedBetPosted) -> Result<(), ReplayError> {
+        let seq = e.seq;
+        if self.betting_started {
+            return Err(ReplayError::InvalidForcedBet { seq });
+        }
+        let (ante, small_blind, big_blind) = (self.ante, self.small_blind, self.big_blind);
+        let (small_blind_seat, big_blind_seat) = (self.small_blind_seat, self.big_blind_seat);
+        // Only the first player after the big blind may straddle, with three or more dealt in
+        let dealt = self.mask(|_| true);
+        let straddle_seat = next_seat_in(self.max_players, big_blind_seat, dealt)
+            .filter(|_| dealt.count_ones() >= 3);
+        let seat = self.seat_mut(seq, e.seat)?;
+
+        let expected = match e.kind {
+            ForcedBetKind::Ante => {
+                let posted = Self::post(seat, ante);
+                seat.current_bet = 0;
+                posted
+            }
+            ForcedBetKind::Posted => {
+                // Posted before the hand, so it was already off the stack
+                if e.amount != big_blind {
+                    return Err(ReplayError::InvalidForcedBet { seq });
+                }
+                seat.current_bet = e.amount;
+                seat.contribution += e.amount;
+                e.amount
+            }
+            ForcedBetKind::SmallBlind if e.seat == small_blind_seat => {
+                let owed = small_blind.saturating_sub(seat.current_bet);
+                Self::post(seat, owed)
+            }
+            ForcedBetKind::BigBlind if e.seat == big_blind_seat => {
+                let owed = big_blind.saturating_sub(seat.current_bet);
+                Self::post(seat, owed)
+            }
+            ForcedBetKind::Straddle if Some(e.seat) == straddle_seat => {
+                // Also posted before the hand
+                if e.amount != big_blind * STRADDLE_BBS {
+                    return Err(ReplayError::InvalidForcedBet { seq });
+                }
+                seat.current_bet = e.amount;
+                seat.contribution += e.amount;
+                self.straddle_seat = Some(e.seat);
+                e.amount
+            }
+            _ => return Err(ReplayError::InvalidForcedBet { seq }),
+        };
+        if expected != e.amount {
+            return Err(ReplayError::InvalidForcedBet { seq });
+        }
+        self.hand.pot += e.amount;
+        Ok(())
+    }
+
+    fn start_betting(&mut self) {
+        if !self.betting_started {
+            self.betting_started = true;
+            for seat in self.hand.seats.iter_mut() {
+                seat.all_in = seat.stack == 0;
+            }
+            self.current_bet = match self.straddle_seat {
+                Some(_) => self.big_blind * STRADDLE_BBS,
+                None => self.big_blind,
+            };
+            self.min_raise = self.big_blind;
+            let last_to_act = self.straddle_seat.unwrap_or(self.big_blind_seat);
+            self.to_act = Some(self.next_to_act(last_to_act).unwrap_or(last_to_act));
+        }
+    }
+
+    fn action(&mut self, e: &ActionTaken) -> Result<(), ReplayError> {
+        let seq = e.seq;
+        self.start_betting();
+
+        // Leaving mid-hand folds the player whether or not it is their turn
+        let out_of_turn_fold = e.action == PlayerActionType::Fold && !e.timed_out;
+        if self.to_act != Some(e.seat) && !out_of_turn_fold {
+            return Err(ReplayError::OutOfTurn {
+                seq,
+                expected: self.to_act,
+                found: e.seat,
+            });
+        }
+
+        let table_bet = self.current_bet;
+        let (bet, stack) = {
+            let seat = self.seat_mut(seq, e.seat)?;
+            (seat.current_bet, seat.stack)
+        };
+        let raise_bounds = self.raise_bounds(bet, stack);
+        let seat = self.seat_mut(seq, e.seat)?;
+        if !seat.in_hand || seat.all_in {
+            return Err(ReplayError::IllegalAction { seq });
+        }
+
+        let can_check = seat.current_bet >= table_bet;
+        // Disconnect protection keeps a timed-out player in as all-in
+        let legal = match e.action {
+            PlayerActionType::Check if e.protected => e.timed_out && !can_check && e.amount == 0,
+            _ if e.protected => false,
+            PlayerActionType::Check => can_check && e.amount == 0,
+            PlayerActionType::Fold => e.amount == 0 && !(e.timed_out && can_check),
+            PlayerActionType::Call => {
+                !e.timed_out && e.amount == table_bet.saturating_sub(seat.current_bet).min(seat.stack)
+            }
+            PlayerActionType::Bet | PlayerActionType::Raise => {
+                let total = seat.current_bet + e.amount;
+                !e.timed_out
+                    && e.amount <= seat.stack
+                    && raise_bounds.is_some_and(|(min, max)| (min..=max).contains(&total))
+            }
+        };
+        if !legal {
+            return Err(ReplayError::IllegalAction { seq });
+        }
+
+        if e.action == PlayerActionType::Fold {
+            seat.in_hand = false;
+        }
+        seat.stack -= e.amount;
+        seat.current_bet += e.amount;
+        seat.contribution += e.amount;
+        if seat.in_hand && (seat.stack == 0 || e.protected) {
+            seat.all_in = true;
+        }
+        let new_bet = seat.current_bet;
+        self.hand.pot += e.amount;
+        if matches!(e.action, PlayerActionType::Bet | PlayerActionType::Raise) {
+            if self.current_bet > 0 {
+                self.raises += 1;
+            }
+            self.min_raise = self.min_raise.max(new_bet - self.current_bet);
+            self.current_bet = new_bet;
+        }
+
+        if self.to_act == Some(e.seat) {
+            self.to_act = Some(self.next_to_act(e.seat).unwrap_or(e.seat));
+        }
+        Ok(())
+    }
+
+    fn board(&mut self, e: &BoardDealt) -> Result<(), ReplayError> {
+        self.start_betting();
+        let board_len = self.hand.board.len();
+        let contested = self.mask(|s| s.in_hand).count_ones() > 1;
+        if self.action_closed_at.is_none()
+            && contested
+            && self.mask(|s| s.in_hand && !s.all_in).count_ones() <= 1
+        {
+            self.action_closed_at = Some(board_len);
+        }
+        if e.second_run {
+            return self.second_board(e);
+        }
+
+        let expected = match board_len {
+            0 => 3,
+            3 | 4 => 1,
+            _ => return Err(ReplayError::InvalidBoard { seq: e.seq }),
+        };
+        if e.cards.len() != expected || !self.cards_fit(&e.cards) {
+            return Err(ReplayError::InvalidBoard { seq: e.seq });
+        }
+        self.hand.board.extend(e.cards.iter().copied());
+        for seat in self.hand.seats.iter_mut() {
+            seat.current_bet = 0;
+        }
+        self.current_bet = 0;
+        self.min_raise = self.big_blind;
+        self.raises = 0;
+        let button = self.hand.button;
+        self.to_act = Some(self.next_to_act(button).unwrap_or(button));
+        Ok(())
+    }
+
+    /// The rest of the board run a second time, from where action closed.
+    fn second_board(&mut self, e: &BoardDealt) -> Result<(), ReplayError> {
+        let valid = match self.action_closed_at {
+            Some(shared) => {
+                self.hand.board.len() == 5
+                    && self.hand.second_board.is_empty()
+                    && e.cards.len() == 5 - shared
+                    && self.cards_fit(&e.cards)
+            }
+            None => false,
+        };
+        if !valid {
+            return Err(ReplayError::InvalidBoard { seq: e.seq });
+        }
+        let shared = 5 - e.cards.len();
+        self.hand.second_board = self.hand.board[..shared].to_vec();
+        self.hand.second_board.extend(e.cards.iter().copied());
+        Ok(())
+    }
+
+    /// Mirror of `Table::raise_bounds`.
+    fn raise_bounds(&self, bet: u64, stack: u64) -> Option<(u64, u64)> {
+        let all_in = bet + stack;
+        if all_in <= self.current_bet {
+            return None;
+        }
+        let (min, max) = match self.hand.betting {
+            BettingStructure::NoLimit => (self.current_bet + self.min_raise, all_in),
+            BettingStructure::PotLimit => {
+                let pot_raise = self.current_bet + self.hand.pot + (self.current_bet - bet);
+                (self.current_bet + self.min_raise, pot_raise.min(all_in))
+            }
+            BettingStructure::FixedLimit { max_raises } => {
+                if self.current_bet > 0 && self.raises >= max_raises {
+                    return None;
+                }
+                let size = if self.hand.board.len() >= 4 {
+                    self.big_blind * 2
+                } else {
+                    self.big_blind
+                };
+                let total = self.current_bet + size;
+                (total, total.min(all_in))
+            }
+        };
+        Some((min.min(max), max))
+    }
+
+    /// Whether `cards` are in the deck and haven't been seen yet this hand.
+    fn cards_fit(&self, cards: &[Card]) -> bool {
+        let seen: Vec<&Card> = self
+            .hand
+            .board
+            .iter()
+            .chain(&self.hand.second_board)
+            .chain(self.hand.seats.iter().filter_map(|s| s.shown.as_ref()).flatten())
+            .collect();
+        cards.iter().enumerate().all(|(i, card)| {
+            card.is_in_deck(self.hand.variant) && !cards[..i].contains(card) && !seen.contains(&card)
+        })
+    }
+
+    fn shown(&mut self, e: &CardsShown) -> Result<(), ReplayError> {
+        let hole_cards = self.hand.variant.hole_cards() as usize;
+        if e.cards.len() != hole_cards || !self.cards_fit(&e.cards) {
+            return Err(ReplayError::IllegalAction { seq: e.seq });
+        }
+        let seat = self.seat_mut(e.seq, e.seat)?;
+        if !seat.in_hand || seat.shown.is_some() {
+            return Err(ReplayError::IllegalAction { seq: e.seq });
+        }
+        seat.shown = Some(e.cards.clone());
+        Ok(())
+    }
+
+    /// Mirror of `resolve_hand`: the pots are built from every player's
+    /// contribution and awarded share by share, main pot first. A contested
+    /// pot of a hand run twice is awarded once per board.
+    fn awarded(&mut self, e: &PotAwarded) -> Result<(), ReplayError> {
+        let seq = e.seq;
+        if self.shares.is_none() {
+            let contributions: Vec<(u8, u64)> =
+                self.hand.seats.iter().map(|s| (s.seat, s.contribution)).collect();
+            let live = self.mask(|s| s.in_hand);
+            let run_twice = self.hand.second_board.len() == 5 && live.count_ones() > 1;
+            let rake = if self.is_tournament || self.play_money || self.hand.board.is_empty() {
+                0
+            } else {
+                let raked = self.hand.pot - pots::uncalled(&contributions, live);
+                ((raked as u128 * self.rake_bps as u128 / 10000) as u64).min(self.rake_cap)
+            };
+            self.hand.pots = pots::build_pots(&contributions, live);
+            self.shares = Some(pots::shares(&self.hand.pots, rake, run_twice));
+        }
+
+        let expected = self
+            .shares
+            .as_ref()
+            .and_then(|shares| shares.get(self.hand.awards.len()).copied())
+            .ok_or(ReplayError::InvalidAward { seq })?;
+        if (e.pot, e.amount, e.rake, e.second_run)
+            != (expected.pot, expected.amount, expected.rake, expected.second_run)
+        {
+            return Err(ReplayError::InvalidAward { seq });
+        }
+        let pot = self.hand.pots[e.pot as usize];
+        if e.winner_seat >= 16 || pot.eligible & (1 << e.winner_seat) == 0 {
+            return Err(ReplayError::InvalidAward { seq });
+        }
+        let board = match e.second_run {
+            true => &self.hand.second_board,
+            false => &self.hand.board,
+        };
+        if !self.best_shown_hand(e.winner_seat, board, pot.eligible) {
+            return Err(ReplayError::InvalidAward { seq });
+        }
+        let seat = self.seat_mut(seq, e.winner_seat)?;
+        seat.stack += e.amount;
+
+        self.hand.awards.push(e.clone());
+        self.hand.payout += e.amount;
+        self.hand.rake += e.rake;
+        Ok(())
+    }
+
+    /// Whether every pot has been awarded or the hand called off.
+    fn finished(&self) -> bool {
+        self.hand.aborted_by.is_some()
+            || self
+                .shares
+                .as_ref()
+                .is_some_and(|shares| shares.len() == self.hand.awards.len())
+    }
+
+    /// Mirror of `abort_hand`: contributions go back, then each offender
+    /// pays up to `MENTAL_POKER_PENALTY_BBS` big blinds, shared among the
+    /// other players dealt in with any remainder to the first of them.
+    fn aborted(&mut self, e: &HandAborted) -> Result<(), ReplayError> {
+        let seq = e.seq;
+        let dealt = self.mask(|_| true);
+        if e.offenders == 0 || e.offenders & !dealt != 0 {
+            return Err(ReplayError::InvalidAbort { seq });
+        }
+        for seat in self.hand.seats.iter_mut() {
+            seat.stack += seat.contribution;
+            seat.contribution = 0;
+            seat.current_bet = 0;
+            seat.in_hand = false;
+        }
+
+        let receivers = dealt & !e.offenders;
+        let mut penalties = 0u64;
+        if receivers != 0 {
+            let max_penalty = self.big_blind * MENTAL_POKER_PENALTY_BBS;
+            for seat in self.hand.seats.iter_mut() {
+                if e.offenders & (1 << seat.seat) != 0 {
+                    let penalty = seat.stack.min(max_penalty);
+                    seat.stack -= penalty;
+                    penalties += penalty;
+                }
+            }
+            let each = penalties / receivers.count_ones() as u64;
+            let mut remainder = penalties % receivers.count_ones() as u64;
+            for seat in self.hand.seats.iter_mut().filter(|s| receivers & (1 << s.seat) != 0) {
+                seat.stack += each + remainder;
+                remainder = 0;
+            }
+        }
+        if e.penalties != penalties {
+            return Err(ReplayError::InvalidAbort { seq });
+        }
+
+        self.hand.pot = 0;
+        self.hand.aborted_by = Some(e.offenders);
+        Ok(())
+    }
+
+    /// Whether `winner` holds a best hand on `board` among the `eligible`
+    /// seats at showdown. Only checked once the board is complete and every
+    /// one of them still in the hand has shown.
+    fn best_shown_hand(&self, winner: u8, board: &[Card], eligible: u16) -> bool {
+        let contenders: Vec<&SeatReplay> = self
+            .hand
+            .seats
+            .iter()
+            .filter(|s| s.in_hand && eligible & (1 << s.seat) != 0)
+            .collect();
+        if contenders.len() < 2 || board.len() != 5 {
+            return true;
+        }
+        let mut values = Vec::with_capacity(contenders.len());
+        for seat in contenders {
+            let Some(cards) = &seat.shown else {
+                return true;
+            };
+            values.push((seat.seat, best_hand(self.hand.variant, cards, board)));
+        }
+        let best = values.iter().map(|(_, value)| *value).max().flatten();
+        values.iter().any(|(seat, value)| *seat == winner && *value == best)
+    }
+}
+
+/// Replay one hand's events against the program's rules.
+///
+/// `events` must hold a single hand in log order, starting with
+/// `HandStarted`. If it includes `HandCompleted`, the recomputed history
+/// hash must match it and `verified` is set on the result.
+pub fn replay(events: &[RecordedEvent]) -> Result<ReplayedHand, ReplayError> {
+    let (first, rest) = events.split_first().ok_or(ReplayError::MissingStart)?;
+    let HandEvent::Started(start) = &first.event else {
+        return Err(ReplayError::MissingStart);
+    };
+    if start.seq != 0 {
+        return Err(ReplayError::OutOfSequence { expected: 0, found: start.seq });
+    }
+
+    let seats = start
+        .seats
+        .iter()
+        .map(|s| SeatReplay {
+            seat: s.seat,
+            player: s.player,
+            starting_stack: s.stack,
+            stack: s.stack,
+            current_bet: 0,
+            contribution: 0,
+            in_hand: true,
+            all_in: false,
+            shown: None,
+        })
+        .collect();
+
+    let mut replay = Replay {
+        hand: ReplayedHand {
+            table: start.table,
+            hand_number: start.hand_number,
+            variant: start.variant,
+            betting: start.betting,
+            button: start.button,
+            seats,
+            board: vec![],
+            second_board: vec![],
+            pot: 0,
+            pots: vec![],
+            awards: vec![],
+            payout: 0,
+            rake: 0,
+            aborted_by: None,
+            history_hash: chain_history(&[0u8; 32], &first.data),
+            verified: false,
+        },
+        max_players: start.max_players,
+        big_blind_seat: start.big_blind_seat,
+        small_blind_seat: start.small_blind_seat,
+        small_blind: start.small_blind,
+        big_blind: start.big_blind,
+        ante: start.ante,
+        rake_bps: start.rake_bps,
+        rake_cap: start.rake_cap,
+        is_tournament: start.is_tournament,
+        play_money: start.play_money,
+        current_bet: 0,
+        min_raise: 0,
+        raises: 0,
+        to_act: None,
+        betting_started: false,
+        straddle_seat: None,
+        action_closed_at: None,
+        shares: None,
+    };
+
+    for (next_seq, recorded) in (1u32..).zip(rest) {
+        let (table, hand_number, seq) = match &recorded.event {
+            HandEvent::Started(e) => (e.table, e.hand_number, e.seq),
+            HandEvent::ForcedBet(e) => (e.table, e.hand_number, e.seq),
+            HandEvent::Action(e) => (e.table, e.hand_number, e.seq),
+            HandEvent::Board(e) => (e.table, e.hand_number, e.seq),
+            HandEvent::Shown(e) => (e.table, e.hand_number, e.seq),
+            HandEvent::Awarded(e) => (e.table, e.hand_number, e.seq),
+            HandEvent::Aborted(e) => (e.table, e.hand_number, e.seq),
+            HandEvent::Completed(e) => {
+                if e.table != start.table || e.hand_number != start.hand_number {
+                    return Err(ReplayError::ForeignEvent { seq: e.events });
+                }
+                if e.events != next_seq {
+                    return Err(ReplayError::OutOfSequence { expected: next_seq, found: e.events });
+                }
+                if e.history_hash != replay.hand.history_hash {
+                    return Err(ReplayError::HashMismatch);
+                }
+                replay.hand.verified = true;
+                break;
+            }
+        };
+        if table != start.table || hand_number != start.hand_number {
+            return Err(ReplayError::ForeignEvent { seq });
+        }
+        if seq != next_seq {
+            return Err(ReplayError::OutOfSequence { expected: next_seq, found: seq });
+        }
+        if replay.finished() {
+            return Err(ReplayError::EventAfterAward { seq });
+        }
+
+        match &recorded.event {
+            HandEvent::ForcedBet(e) => replay.forced_bet(e)?,
+            HandEvent::Action(e) => replay.action(e)?,
+            HandEvent::Board(e) => replay.board(e)?,
+            HandEvent::Shown(e) => replay.shown(e)?,
+            HandEvent::Awarded(e) => replay.awarded(e)?,
+            HandEvent::Aborted(e) => replay.aborted(e)?,
+            HandEvent::Started(_) | HandEvent::Completed(_) => {
+                return Err(ReplayError::ForeignEvent { seq })
+            }
+        }
+
+        replay.hand.history_hash = chain_history(&replay.hand.history_hash, &recorded.data);
+    }
+
+    Ok(replay.hand)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anchor_lang::{prelude::Pubkey, Event};
+    use shadow_poker::SeatSnapshot;
+
+    const STACK: u64 = 1_000;
+
+    fn card(rank: u8, suit: u8) -> Card {
+        Card { suit, rank }
+    }
+
+    /// Transaction log lines for one heads-up hand, as the program emits
+    /// them: seat 0 is on the button and posts the small blind.
+    struct Log {
+        table: Pubkey,
+        lines: Vec<String>,
+        seq: u32,
+        hash: [u8; 32],
+    }
+
+    impl Log {
+        fn new() -> Self {
+            let mut log = Log {
+                table: Pubkey::new_from_array([1; 32]),
+                lines: vec![],
+                seq: 0,
+                hash: [0u8; 32],
+            };
+            let table = log.table;
+            log.record(|seq| HandStarted {
+                table,
+                hand_number: 1,
+                seq,
+                max_players: 2,
+                variant: GameVariant::Holdem,
+                betting: BettingStructure::NoLimit,
+                button: 0,
+                small_blind_seat: 0,
+                big_blind_seat: 1,
+                small_blind: 5,
+                big_blind: 10,
+                ante: 0,
+                rake_bps: 500,
+                rake_cap: 100,
+                is_tournament: false,
+                play_money: false,
+                seats: (0..2)
+                    .map(|seat| SeatSnapshot {
+                        seat,
+                        player: Pubkey::new_from_array([seat + 2; 32]),
+                        stack: STACK,
+                    })
+                    .collect(),
+            });
+            log.record(|seq| ForcedBetPosted {
+                table,
+                hand_number: 1,
+                seq,
+                seat: 0,
+                kind: ForcedBetKind::SmallBlind,
+                amount: 5,
+            });
+            log.record(|seq| ForcedBetPosted {
+                table,
+                hand_number: 1,
+                seq,
+                seat: 1,
+                kind: ForcedBetKind::BigBlind,
+                amount: 10,
+            });
+            log
+        }
+
+        fn record<E: Event>(&mut self, event: impl FnOnce(u32) -> E) {
+            let data = event(self.seq).data();
+            self.hash = chain_history(&self.hash, &data);
+            self.seq += 1;
+            self.lines.push(format!("{PROGRAM_DATA_PREFIX}{}", STANDARD.encode(data)));
+        }
+
+        fn act(&mut self, seat: u8, action: PlayerActionType, amount: u64) {
+            self.timed_act(seat, action, amount, false);
+        }
+
+        fn timed_act(&mut self, seat: u8, action: PlayerActionType, amount: u64, protected: bool) {
+            let table = self.table;
+            self.record(|seq| ActionTaken {
+                table,
+                hand_number: 1,
+                seq,
+                seat,
+                action,
+                amount,
+                timed_out: protected,
+                protected,
+            });
+        }
+
+        fn board(&mut self, cards: &[Card]) {
+            let table = self.table;
+            self.record(|seq| BoardDealt {
+                table,
+                hand_number: 1,
+                seq,
+                cards: cards.to_vec(),
+                second_run: false,
+            });
+        }
+
+        fn show(&mut self, seat: u8, cards: [Card; 2]) {
+            let table = self.table;
+            self.record(|seq| CardsShown { table, hand_number: 1, seq, seat, cards: cards.to_vec() });
+        }
+
+        fn award(&mut self, winner_seat: u8, pot: u8, amount: u64, rake: u64) {
+            let table = self.table;
+            self.record(|seq| PotAwarded {
+                table,
+                hand_number: 1,
+                seq,
+                winner_seat,
+                pot,
+                amount,
+                rake,
+                second_run: false,
+            });
+        }
+
+        /// Close the hand with the commitment `resolve_hand` stores on-chain.
+        fn complete(&mut self) -> [u8; 32] {
+            let (table, events, history_hash) = (self.table, self.seq, self.hash);
+            let data = HandCompleted { table, hand_number: 1, events, history_hash }.data();
+            self.lines.push(format!("{PROGRAM_DATA_PREFIX}{}", STANDARD.encode(data)));
+            history_hash
+        }
+
+        fn events(&self) -> Vec<RecordedEvent> {
+            decode_logs(self.lines.iter().map(String::as_str))
+        }
+    }
+
+    /// A checked-down hand with one flop bet, won by seat 0's aces.
+    fn showdown(river: Card) -> Log {
+        use PlayerActionType::*;
+        let mut log = Log::new();
+        log.act(0, Call, 5);
+        log.act(1, Check, 0);
+        log.board(&[card(14, 0), card(13, 1), card(7, 2)]);
+        log.act(1, Check, 0);
+        log.act(0, Bet, 20);
+        log.act(1, Call, 20);
+        log.board(&[card(4, 3)]);
+        log.act(1, Check, 0);
+        log.act(0, Check, 0);
+        log.board(&[river]);
+        log.act(1, Check, 0);
+        log.act(0, Check, 0);
+        log.show(0, [card(14, 3), card(14, 2)]);
+        log.show(1, [card(13, 0), card(13, 3)]);
+        // 5% of the 60 pot is raked
+        log.award(0, 0, 57, 3);
+        log
+    }
+
+    #[test]
+    fn replays_a_recorded_hand_to_its_history_hash() {
+        let mut log = showdown(card(2, 0));
+        let committed = log.complete();
+        let events = log.events();
+        assert_eq!(events.len(), 19);
+
+        let hand = replay(&events).unwrap();
+        assert!(hand.verified);
+        assert_eq!(hand.history_hash, committed);
+        assert_eq!(hand.pot, 60);
+        assert_eq!((hand.payout, hand.rake), (57, 3));
+        assert_eq!(hand.awards.len(), 1);
+        assert_eq!(hand.awards[0].winner_seat, 0);
+        assert_eq!(hand.seats[0].stack, STACK + 27);
+        assert_eq!(hand.seats[1].stack, STACK - 30);
+    }
+
+    #[test]
+    fn rejects_a_tampered_event() {
+        let mut honest = showdown(card(2, 0));
+        honest.complete();
+
+        // A different river that changes nothing else replays cleanly but
+        // breaks the chain
+        let mut tampered = showdown(card(3, 0));
+        tampered.lines.push(honest.lines.last().unwrap().clone());
+        assert_eq!(replay(&tampered.events()).unwrap_err(), ReplayError::HashMismatch);
+
+        // Handing the pot to the worse hand fails the showdown check
+        let mut log = showdown(card(2, 0));
+        log.lines.pop();
+        log.seq -= 1;
+        log.award(1, 0, 57, 3);
+        log.complete();
+        assert_eq!(replay(&log.events()).unwrap_err(), ReplayError::InvalidAward { seq: 17 });
+
+        // So does paying out more than the pot after rake
+        let mut log = showdown(card(2, 0));
+        log.lines.pop();
+        log.seq -= 1;
+        log.award(0, 0, 60, 0);
+        log.complete();
+        assert_eq!(replay(&log.events()).unwrap_err(), ReplayError::InvalidAward { seq: 17 });
+    }
+
+    #[test]
+    fn rejects_reordered_events() {
+        let mut log = showdown(card(2, 0));
+        log.complete();
+        let mut events = log.events();
+        // The flop bet and call swapped
+        events.swap(7, 8);
+        assert_eq!(
+            replay(&events).unwrap_err(),
+            ReplayError::OutOfSequence { expected: 7, found: 8 }
+        );
+
+        // Dropping an event leaves a gap
+        let mut events = log.events();
+        events.remove(5);
+        assert_eq!(
+            replay(&events).unwrap_err(),
+            ReplayError::OutOfSequence { expected: 5, found: 6 }
+        );
+    }
+
+    #[test]
+    fn disconnected_player_wins_the_main_pot_against_a_bigger_bet() {
+        use PlayerActionType::*;
+        let mut log = Log::new();
+        // Seat 0 raises to 30 and seat 1 times out with protection left,
+        // staying in as all-in for their big blind
+        log.act(0, Raise, 25);
+        log.timed_act(1, Check, 0, true);
+        log.board(&[card(9, 0), card(8, 1), card(2, 2)]);
+        log.board(&[card(4, 3)]);
+        log.board(&[card(6, 0)]);
+        log.show(0, [card(13, 0), card(13, 3)]);
+        log.show(1, [card(14, 3), card(14, 2)]);
+        // Seat 1 takes the 20 they matched, less 5% rake; the unmatched 20
+        // goes back to seat 0 unraked
+        log.award(1, 0, 19, 1);
+        log.award(0, 1, 20, 0);
+        log.complete();
+
+        let hand = replay(&log.events()).unwrap();
+        assert!(hand.verified);
+        assert_eq!(hand.pots.len(), 2);
+        assert_eq!(hand.seats[0].stack, STACK - 10);
+        assert_eq!(hand.seats[1].stack, STACK + 9);
+
+        // The disconnected player can't be paid the bigger bet
+        let mut log = Log::new();
+        log.act(0, Raise, 25);
+        log.timed_act(1, Check, 0, true);
+        log.board(&[card(9, 0), card(8, 1), card(2, 2)]);
+        log.board(&[card(4, 3)]);
+        log.board(&[card(6, 0)]);
+        log.show(0, [card(13, 0), card(13, 3)]);
+        log.show(1, [card(14, 3), card(14, 2)]);
+        log.award(1, 0, 39, 1);
+        assert_eq!(replay(&log.events()).unwrap_err(), ReplayError::InvalidAward { seq: 10 });
+    }
+}
@@ -0,0 +1,260 @@
+//! Delegation of hand accounts to a MagicBlock ephemeral rollup.
+//!
+//! A delegated account is handed to the delegation program on the base
+//! layer: its data is parked in a buffer PDA of this program, the account
+//! is zeroed and reassigned, and the delegation program takes it over and
+//! restores the data. The rollup then runs this program's instructions
+//! against it and commits the state back, either periodically or when the
+//! magic program is asked to. On undelegation the delegation program calls
+//! back into this program (`UNDELEGATE_DISCRIMINATOR`) to recreate the
+//! account, as its owner, from a buffer it signs for.
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::{
+    instruction::{AccountMeta, Instruction},
+    program::{invoke, invoke_signed},
+    system_instruction, system_program,
+};
+
+pub const DELEGATION_PROGRAM_ID: Pubkey = pubkey!("DELeGGvXpWV2fqJUhqcF5ZSYMS4JTLjteaAMARRSaeSh");
+pub const MAGIC_PROGRAM_ID: Pubkey = pubkey!("Magic11111111111111111111111111111111111111");
+pub const MAGIC_CONTEXT_ID: Pubkey = pubkey!("MagicContext1111111111111111111111111111111");
+
+/// Instruction the delegation program calls on the owner to undelegate.
+pub const UNDELEGATE_DISCRIMINATOR: [u8; 8] = [196, 28, 41, 206, 48, 37, 51, 167];
+
+const DELEGATE_DISCRIMINATOR: [u8; 8] = [0; 8];
+// Bincode tags of the magic program's instructions
+const SCHEDULE_COMMIT: [u8; 4] = [1, 0, 0, 0];
+const SCHEDULE_COMMIT_AND_UNDELEGATE: [u8; 4] = [2, 0, 0, 0];
+
+#[derive(AnchorSerialize)]
+struct DelegateArgs {
+    commit_frequency_ms: u32,
+    seeds: Vec<Vec<u8>>,
+    validator: Option<Pubkey>,
+}
+
+/// Buffer PDA that holds an account's data while it is handed over.
+pub fn buffer_address(account: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"buffer", account.as_ref()], &crate::ID)
+}
+
+pub fn delegation_record_address(account: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"delegation", account.as_ref()], &DELEGATION_PROGRAM_ID).0
+}
+
+pub fn delegation_metadata_address(account: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"delegation-metadata", account.as_ref()],
+        &DELEGATION_PROGRAM_ID,
+    )
+    .0
+}
+
+/// Accounts every delegation shares.
+pub struct Delegation<'a, 'info> {
+    pub payer: &'a AccountInfo<'info>,
+    pub owner_program: &'a AccountInfo<'info>,
+    pub delegation_program: &'a AccountInfo<'info>,
+    pub system_program: &'a AccountInfo<'info>,
+    pub commit_frequency_ms: u32,
+}
+
+/// Delegation program accounts for one delegated account.
+pub struct DelegationRecord<'a, 'info> {
+    pub buffer: &'a AccountInfo<'info>,
+    pub record: &'a AccountInfo<'info>,
+    pub metadata: &'a AccountInfo<'info>,
+}
+
+impl<'info> Delegation<'_, 'info> {
+    /// Delegate `account`, a PDA of this program derived from `seeds`
+    /// (without the bump) and `bump`.
+    pub fn delegate(
+        &self,
+        account: &AccountInfo<'info>,
+        seeds: &[&[u8]],
+        bump: u8,
+        accounts: DelegationRecord<'_, 'info>,
+    ) -> Result<()> {
+        let (buffer_key, buffer_bump) = buffer_address(account.key);
+        require_keys_eq!(*accounts.buffer.key, buffer_key, ErrorCode::ConstraintSeeds);
+        require_keys_eq!(
+            *accounts.record.key,
+            delegation_record_address(account.key),
+            ErrorCode::ConstraintSeeds
+        );
+        require_keys_eq!(
+            *accounts.metadata.key,
+            delegation_metadata_address(account.key),
+            ErrorCode::ConstraintSeeds
+        );
+
+        let bump = [bump];
+        let mut signer_seeds = seeds.to_vec();
+        signer_seeds.push(&bump);
+        let buffer_bump = [buffer_bump];
+        let buffer_seeds: &[&[u8]] = &[b"buffer", account.key.as_ref(), &buffer_bump];
+
+        // Park the data in the buffer
+        let data_len = account.data_len();
+        create_pda(accounts.buffer, data_len, buffer_seeds, self.payer, self.system_program)?;
+        accounts
+            .buffer
+            .try_borrow_mut_data()?
+            .copy_from_slice(&account.try_borrow_data()?);
+
+        // Hand the emptied account over to the delegation program
+        account.try_borrow_mut_data()?.fill(0);
+        account.assign(&system_program::ID);
+        invoke_signed(
+            &system_instruction::assign(account.key, &DELEGATION_PROGRAM_ID),
+            &[account.clone(), self.system_program.clone()],
+            &[&signer_seeds],
+        )?;
+
+        let args = DelegateArgs {
+            commit_frequency_ms: self.commit_frequency_ms,
+            seeds: seeds.iter().map(|seed| seed.to_vec()).collect(),
+            validator: None,
+        };
+        let mut data = DELEGATE_DISCRIMINATOR.to_vec();
+        args.serialize(&mut data)?;
+        let delegate_ix = Instruction {
+            program_id: DELEGATION_PROGRAM_ID,
+            accounts: vec![
+                AccountMeta::new(*self.payer.key, true),
+                AccountMeta::new(*account.key, true),
+                AccountMeta::new_readonly(*self.owner_program.key, false),
+                AccountMeta::new(*accounts.buffer.key, false),
+                AccountMeta::new(*accounts.record.key, false),
+                AccountMeta::new(*accounts.metadata.key, false),
+                AccountMeta::new_readonly(system_program::ID, false),
+            ],
+            data,
+        };
+        invoke_signed(
+            &delegate_ix,
+            &[
+                self.payer.clone(),
+                account.clone(),
+                self.owner_program.clone(),
+                accounts.buffer.clone(),
+                accounts.record.clone(),
+                accounts.metadata.clone(),
+                self.system_program.clone(),
+                self.delegation_program.clone(),
+            ],
+            &[&signer_seeds],
+        )?;
+
+        // The delegation program has copied the data back; free the buffer
+        close_pda(accounts.buffer, self.payer)
+    }
+}
+
+/// Ask the magic program to commit `accounts` to the base layer, and to
+/// undelegate them afterwards if `undelegate` is set. Must run on the
+/// rollup.
+pub fn schedule_commit<'info>(
+    payer: &AccountInfo<'info>,
+    magic_context: &AccountInfo<'info>,
+    magic_program: &AccountInfo<'info>,
+    accounts: &[AccountInfo<'info>],
+    undelegate: bool,
+) -> Result<()> {
+    let mut metas = vec![
+        AccountMeta::new(*payer.key, true),
+        AccountMeta::new(*magic_context.key, false),
+    ];
+    metas.extend(accounts.iter().map(|a| AccountMeta::new_readonly(*a.key, false)));
+    let data = match undelegate {
+        true => SCHEDULE_COMMIT_AND_UNDELEGATE,
+        false => SCHEDULE_COMMIT,
+    };
+    let ix = Instruction {
+        program_id: MAGIC_PROGRAM_ID,
+        accounts: metas,
+        data: data.to_vec(),
+    };
+
+    let mut infos = vec![payer.clone(), magic_context.clone()];
+    infos.extend(accounts.iter().cloned());
+    infos.push(magic_program.clone());
+    invoke(&ix, &infos)?;
+    Ok(())
+}
+
+/// Recreate an undelegated account from the delegation program's buffer.
+/// The buffer must be owned by, and signed for by, the delegation program,
+/// so only it can hand accounts back.
+pub fn restore_undelegated<'info>(
+    account: &AccountInfo<'info>,
+    buffer: &AccountInfo<'info>,
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    seeds: &[Vec<u8>],
+) -> Result<()> {
+    require!(buffer.is_signer, ErrorCode::AccountNotSigner);
+    require_keys_eq!(*buffer.owner, DELEGATION_PROGRAM_ID, ErrorCode::AccountOwnedByWrongProgram);
+
+    let seeds: Vec<&[u8]> = seeds.iter().map(|seed| seed.as_slice()).collect();
+    let (address, bump) = Pubkey::find_program_address(&seeds, &crate::ID);
+    require_keys_eq!(*account.key, address, ErrorCode::ConstraintSeeds);
+    let bump = [bump];
+    let mut signer_seeds = seeds;
+    signer_seeds.push(&bump);
+
+    create_pda(account, buffer.data_len(), &signer_seeds, payer, system_program)?;
+    account
+        .try_borrow_mut_data()?
+        .copy_from_slice(&buffer.try_borrow_data()?);
+    Ok(())
+}
+
+/// Create a PDA of this program with `space` bytes, topping up any
+/// lamports it already holds to the rent-exempt minimum.
+fn create_pda<'info>(
+    account: &AccountInfo<'info>,
+    space: usize,
+    signer_seeds: &[&[u8]],
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+) -> Result<()> {
+    let rent = Rent::get()?.minimum_balance(space);
+    let infos = [payer.clone(), account.clone(), system_program.clone()];
+    if account.lamports() == 0 {
+        invoke_signed(
+            &system_instruction::create_account(payer.key, account.key, rent, space as u64, &crate::ID),
+            &infos,
+            &[signer_seeds],
+        )?;
+        return Ok(());
+    }
+
+    let top_up = rent.saturating_sub(account.lamports());
+    if top_up > 0 {
+        invoke(&system_instruction::transfer(payer.key, account.key, top_up), &infos)?;
+    }
+    invoke_signed(
+        &system_instruction::allocate(account.key, space as u64),
+        &infos,
+        &[signer_seeds],
+    )?;
+    invoke_signed(
+        &system_instruction::assign(account.key, &crate::ID),
+        &infos,
+        &[signer_seeds],
+    )?;
+    Ok(())
+}
+
+/// Close an account this program owns, returning its lamports to `to`.
+fn close_pda<'info>(account: &AccountInfo<'info>, to: &AccountInfo<'info>) -> Result<()> {
+    **to.try_borrow_mut_lamports()? += account.lamports();
+    **account.try_borrow_mut_lamports()? = 0;
+    account.assign(&system_program::ID);
+    account.resize(0)?;
+    Ok(())
+}
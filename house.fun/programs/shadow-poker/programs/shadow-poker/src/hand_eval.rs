@@ -0,0 +1,244 @@
+//! Showdown hand evaluation for every `GameVariant`.
+//!
+//! Hold'em and Short Deck play the best five cards from hole and board
+//! cards combined; Omaha must use exactly two hole cards and three from the
+//! board. Short Deck ranks a flush above a full house and lets the ace play
+//! low in A-6-7-8-9.
+
+use crate::{Card, GameVariant};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum HandCategory {
+    HighCard,
+    Pair,
+    TwoPair,
+    ThreeOfAKind,
+    Straight,
+    Flush,
+    FullHouse,
+    FourOfAKind,
+    StraightFlush,
+}
+
+/// Value of a five-card hand. Values from the same variant compare greater
+/// for stronger hands and equal for split pots.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct HandValue {
+    strength: u8,
+    pub category: HandCategory,
+    /// Ranks in tie-break order; a straight only carries its top card.
+    pub ranks: [u8; 5],
+}
+
+impl GameVariant {
+    /// Position of `category` in this variant's hand rankings.
+    fn strength(&self, category: HandCategory) -> u8 {
+        match (self, category) {
+            (GameVariant::ShortDeck, HandCategory::Flush) => HandCategory::FullHouse as u8,
+            (GameVariant::ShortDeck, HandCategory::FullHouse) => HandCategory::Flush as u8,
+            _ => category as u8,
+        }
+    }
+}
+
+/// Value of exactly five cards.
+pub fn evaluate_five(variant: GameVariant, cards: &[Card; 5]) -> HandValue {
+    // Group ranks by how often they appear, most frequent (then highest) first
+    let mut counts = [0u8; 15];
+    for card in cards {
+        counts[card.rank as usize] += 1;
+    }
+    let mut groups: Vec<(u8, u8)> = (2..=14u8)
+        .rev()
+        .filter(|&rank| counts[rank as usize] > 0)
+        .map(|rank| (counts[rank as usize], rank))
+        .collect();
+    groups.sort_by_key(|&(count, _)| std::cmp::Reverse(count));
+
+    let mut ranks = [0u8; 5];
+    let mut i = 0;
+    for &(count, rank) in &groups {
+        for _ in 0..count {
+            ranks[i] = rank;
+            i += 1;
+        }
+    }
+
+    let flush = cards.iter().all(|c| c.suit == cards[0].suit);
+    let straight_high = if groups.len() == 5 {
+        let low_straight = [14, variant.lowest_rank() + 3, variant.lowest_rank() + 2];
+        if ranks[0] - ranks[4] == 4 {
+            Some(ranks[0])
+        } else if ranks[..3] == low_straight && ranks[4] == variant.lowest_rank() {
+            // The ace plays low in the bottom straight
+            Some(ranks[1])
+        } else {
+            None
+        }
+    } else {
+        None
+    };
+
+    let category = match (straight_high, flush, groups[0].0, groups[1].0) {
+        (Some(_), true, _, _) => HandCategory::StraightFlush,
+        (_, _, 4, _) => HandCategory::FourOfAKind,
+        (_, _, 3, 2) => HandCategory::FullHouse,
+        (_, true, _, _) => HandCategory::Flush,
+        (Some(_), _, _, _) => HandCategory::Straight,
+        (_, _, 3, _) => HandCategory::ThreeOfAKind,
+        (_, _, 2, 2) => HandCategory::TwoPair,
+        (_, _, 2, _) => HandCategory::Pair,
+        _ => HandCategory::HighCard,
+    };
+    if let Some(high) = straight_high {
+        ranks = [high, 0, 0, 0, 0];
+    }
+
+    HandValue {
+        strength: variant.strength(category),
+        category,
+        ranks,
+    }
+}
+
+/// Best hand a player can make from their hole cards and the board, or
+/// `None` if the cards don't fit the variant.
+pub fn best_hand(variant: GameVariant, hole: &[Card], board: &[Card]) -> Option<HandValue> {
+    if hole.len() != variant.hole_cards() as usize || !(3..=5).contains(&board.len()) {
+        return None;
+    }
+
+    let mut best: Option<HandValue> = None;
+    let mut consider = |five: [Card; 5]| {
+        let value = evaluate_five(variant, &five);
+        if best.is_none_or(|b| value > b) {
+            best = Some(value);
+        }
+    };
+
+    match variant {
+        GameVariant::Holdem | GameVariant::ShortDeck => {
+            let cards: Vec<Card> = hole.iter().chain(board).copied().collect();
+            for mask in 0u32..1 << cards.len() {
+                if mask.count_ones() == 5 {
+                    consider(pick(&cards, mask));
+                }
+            }
+        }
+        GameVariant::PotLimitOmaha => {
+            for hole_mask in 0u32..1 << hole.len() {
+                if hole_mask.count_ones() != 2 {
+                    continue;
+                }
+                for board_mask in 0u32..1 << board.len() {
+                    if board_mask.count_ones() == 3 {
+                        let two = pick::<2>(hole, hole_mask);
+                        let three = pick::<3>(board, board_mask);
+                        consider([two[0], two[1], three[0], three[1], three[2]]);
+                    }
+                }
+            }
+        }
+    }
+    best
+}
+
+/// The `N` cards whose bits are set in `mask`.
+fn pick<const N: usize>(cards: &[Card], mask: u32) -> [Card; N] {
+    let mut picked = [Card { suit: 0, rank: 0 }; N];
+    for (slot, card) in picked
+        .iter_mut()
+        .zip(cards.iter().enumerate().filter(|(i, _)| mask & (1 << i) != 0).map(|(_, c)| c))
+    {
+        *slot = *card;
+    }
+    picked
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Cards written as rank then suit, e.g. "Ah 9s Tc".
+    fn cards(hand: &str) -> Vec<Card> {
+        hand.split_whitespace()
+            .map(|card| {
+                let (rank, suit) = card.split_at(1);
+                let rank = match rank {
+                    "A" => 14,
+                    "K" => 13,
+                    "Q" => 12,
+                    "J" => 11,
+                    "T" => 10,
+                    digit => digit.parse().unwrap(),
+                };
+                let suit = "hdcs".find(suit).unwrap() as u8;
+                Card { suit, rank }
+            })
+            .collect()
+    }
+
+    fn five(variant: GameVariant, hand: &str) -> HandValue {
+        evaluate_five(variant, &cards(hand).try_into().unwrap())
+    }
+
+    fn best(variant: GameVariant, hole: &str, board: &str) -> HandValue {
+        best_hand(variant, &cards(hole), &cards(board)).unwrap()
+    }
+
+    #[test]
+    fn omaha_plays_exactly_two_hole_cards_and_three_from_the_board() {
+        let omaha = GameVariant::PotLimitOmaha;
+        // Four hearts on board and one in hand is a flush in Hold'em only
+        let board = "2h 7h 9h Jh Kc";
+        assert_eq!(best(GameVariant::Holdem, "Ah Qc", board).category, HandCategory::Flush);
+        assert_ne!(best(omaha, "Ah Qc 3d 3s", board).category, HandCategory::Flush);
+        assert_eq!(best(omaha, "Ah 4h 3d 3s", board).category, HandCategory::Flush);
+
+        // Four of a kind in hand only plays two of them
+        let quads = best(omaha, "8c 8d 8h 8s", "2c 5d Ks");
+        assert_eq!(quads.category, HandCategory::Pair);
+
+        // Trips on board and one matching hole card is only trips: the
+        // second hole card has to play
+        let board = "Ah Ad Ac Kc 9s";
+        assert_eq!(best(GameVariant::Holdem, "Kd 2s", board).category, HandCategory::FullHouse);
+        let trips = best(omaha, "Kd 2s 3s 4c", board);
+        assert_eq!(trips.category, HandCategory::ThreeOfAKind);
+        assert_eq!(trips.ranks, [14, 14, 14, 13, 4]);
+        assert_eq!(best(omaha, "Kd Ks 3s 4c", board).category, HandCategory::FullHouse);
+
+        // Omaha needs four hole cards
+        assert!(best_hand(omaha, &cards("Ah Kh"), &cards("2h 7h 9h")).is_none());
+    }
+
+    #[test]
+    fn short_deck_flush_beats_full_house() {
+        let flush = "6h 9h Jh Qh Ah";
+        let full_house = "Ks Kd Kc 7s 7d";
+        assert!(five(GameVariant::ShortDeck, flush) > five(GameVariant::ShortDeck, full_house));
+        assert!(five(GameVariant::Holdem, flush) < five(GameVariant::Holdem, full_house));
+
+        // Everything else keeps its usual order
+        let quads = "9s 9d 9c 9h 6s";
+        assert!(five(GameVariant::ShortDeck, quads) > five(GameVariant::ShortDeck, flush));
+        let straight = "6s 7d 8c 9h Ts";
+        assert!(five(GameVariant::ShortDeck, full_house) > five(GameVariant::ShortDeck, straight));
+    }
+
+    #[test]
+    fn short_deck_ace_plays_low_in_a_six_to_nine_straight() {
+        let short = GameVariant::ShortDeck;
+        let wheel = five(short, "Ah 6d 7c 8s 9h");
+        assert_eq!(wheel.category, HandCategory::Straight);
+        assert_eq!(wheel.ranks, [9, 0, 0, 0, 0]);
+        assert!(wheel < five(short, "6s 7d 8c 9h Ts"));
+        assert!(wheel > five(short, "Ks Kd Kc 7s 6d"));
+        assert_eq!(five(short, "Ah 6h 7h 8h 9h").category, HandCategory::StraightFlush);
+
+        // Only the short deck's bottom straight; in Hold'em it's ace high
+        assert_eq!(five(GameVariant::Holdem, "Ah 6d 7c 8s 9h").category, HandCategory::HighCard);
+        assert_eq!(five(GameVariant::Holdem, "Ah 2d 3c 4s 5h").category, HandCategory::Straight);
+        assert_eq!(best(short, "Ah 6d", "7c 8s 9h Kd Qd").category, HandCategory::Straight);
+    }
+}
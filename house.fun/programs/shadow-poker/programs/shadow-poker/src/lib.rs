@@ -1,6 +1,23 @@
 use anchor_lang::prelude::*;
 // use arcium_anchor::prelude::*;
 use anchor_lang::solana_program::clock::Clock;
+use anchor_lang::solana_program::entrypoint::MAX_PERMITTED_DATA_INCREASE;
+use anchor_spl::token_2022::spl_token_2022::{
+    self,
+    extension::{BaseStateWithExtensions, ExtensionType, StateWithExtensions},
+};
+use anchor_spl::token_2022::Token2022;
+use anchor_spl::token_interface::{self, Mint, TokenAccount, TokenInterface};
+
+pub mod ephemeral_rollup;
+pub mod hand_eval;
+pub mod mental_poker;
+pub mod pots;
+
+use ephemeral_rollup::{
+    Delegation, DelegationRecord, DELEGATION_PROGRAM_ID, MAGIC_CONTEXT_ID, MAGIC_PROGRAM_ID,
+    UNDELEGATE_DISCRIMINATOR,
+};
 
 // Program ID - Replace with actual after deployment
 declare_id!("HT1ro9KCKv3bzrvrtjonrMWuHZeNYFPvscPWy8bMaogx");
@@ -10,8 +27,29 @@ pub const HOUSE_FEE_BPS: u16 = 50; // 0.5% house fee (lower for poker)
 pub const MIN_BUY_IN: u64 = 10_000_000; // 0.01 SOL minimum
 pub const MAX_BUY_IN: u64 = 1_000_000_000_000; // 1000 SOL maximum
 pub const MIN_PLAYERS: u8 = 2;
-pub const MAX_PLAYERS: u8 = 6;
+pub const MAX_PLAYERS: u8 = 10; // Full ring; seat masks are u16
 pub const TIMEOUT_SLOTS: u64 = 600; // 4 minutes timeout
+pub const MAX_TIMEOUT_STRIKES: u8 = 3; // Consecutive timeouts before a player is sat out
+pub const TIME_BANK_START_SLOTS: u64 = 75; // ~30 seconds of time bank on sitting down
+pub const TIME_BANK_ACCRUAL_SLOTS: u64 = 5; // Added for every hand dealt in
+pub const TIME_BANK_MAX_SLOTS: u64 = 450; // ~3 minutes
+pub const TIME_BANK_CHUNK_SLOTS: u64 = 75; // Most a single `use_time_bank` adds to the clock
+pub const DISCONNECT_PROTECTIONS: u8 = 2; // Timeouts per session played as all-in instead of folding
+pub const MAX_SESSION_SECONDS: i64 = 86_400; // Longest a session key can be authorized for
+pub const MAX_TOURNAMENT_TABLES: usize = 8;
+pub const MAX_BLIND_LEVELS: usize = 16;
+pub const MAX_PAYOUT_PLACES: usize = 10;
+pub const MAX_ALLOWLIST: usize = 32;
+pub const MAX_WAITLIST: usize = 32; // Players queued per waitlist
+pub const MAX_WAITLIST_TABLES: usize = 8; // Tables a waitlist seats players at
+pub const MAX_RAKE_BPS: u16 = 1000; // 10% rake ceiling
+pub const DEFAULT_RAKE_CAP: u64 = 100_000_000; // 0.1 SOL per hand
+pub const MAX_FIXED_LIMIT_RAISES: u8 = 8;
+pub const MAX_MENTAL_POKER_PLAYERS: u8 = 6;
+pub const MENTAL_POKER_PENALTY_BBS: u64 = 10; // Big blinds forfeited for withholding a key or cheating a shuffle
+pub const PLAY_CHIP_DECIMALS: u8 = 0;
+pub const STRADDLE_BBS: u64 = 2; // A straddle is a live blind of two big blinds
+pub const SMALL_BET_POT_DIVISOR: u64 = 4; // Postflop bets of at most a quarter of the pot count as small
 
 // const COMP_DEF_OFFSET_POKER: u32 = comp_def_offset("poker");
 
@@ -32,7 +70,12 @@ pub mod shadow_poker {
         Ok(())
     }
 
-    /// Create a new poker table
+    /// Create a new poker table.
+    ///
+    /// Pass `chip_mint` to play in an SPL token instead of SOL (the vault is
+    /// created next with `create_table_vault`), or the house play chip mint
+    /// for a play-money table.
+    #[allow(clippy::too_many_arguments)]
     pub fn create_table(
         ctx: Context<CreateTable>,
         min_buy_in: u64,
@@ -40,50 +83,95 @@ pub mod shadow_poker {
         small_blind: u64,
         big_blind: u64,
         max_players: u8,
+        ante: u64,
+        variant: GameVariant,
+        betting: BettingStructure,
+        dealing_mode: DealingMode,
     ) -> Result<()> {
+        require_valid_game(variant, betting)?;
+        require!(
+            (MIN_PLAYERS..=MAX_PLAYERS).contains(&max_players),
+            ShadowPokerError::InvalidPlayerCount
+        );
         require!(
-            max_players >= MIN_PLAYERS && max_players <= MAX_PLAYERS,
+            dealing_mode == DealingMode::Dealer || max_players <= MAX_MENTAL_POKER_PLAYERS,
             ShadowPokerError::InvalidPlayerCount
         );
-        require!(min_buy_in >= MIN_BUY_IN, ShadowPokerError::BuyInTooSmall);
-        require!(max_buy_in <= MAX_BUY_IN, ShadowPokerError::BuyInTooLarge);
+        require!(
+            small_blind > 0 && small_blind <= big_blind && ante <= small_blind,
+            ShadowPokerError::InvalidBlinds
+        );
+        // The SOL buy-in limits don't translate to other currencies
+        let chip_mint = ctx.accounts.chip_mint.as_ref().map(|mint| mint.key());
+        if chip_mint.is_none() {
+            require!(min_buy_in >= MIN_BUY_IN, ShadowPokerError::BuyInTooSmall);
+            require!(max_buy_in <= MAX_BUY_IN, ShadowPokerError::BuyInTooLarge);
+        }
+        require!(min_buy_in > 0, ShadowPokerError::BuyInTooSmall);
         require!(min_buy_in < max_buy_in, ShadowPokerError::InvalidBuyInRange);
+        if let Some(mint) = ctx.accounts.chip_mint.as_ref() {
+            require_supported_mint(mint)?;
+        }
 
         let table = &mut ctx.accounts.table;
         let house = &mut ctx.accounts.house;
         let clock = Clock::get()?;
 
-        table.creator = ctx.accounts.creator.key();
+        table.open(ctx.accounts.creator.key(), max_players, clock.slot, ctx.bumps.table);
         table.min_buy_in = min_buy_in;
         table.max_buy_in = max_buy_in;
         table.small_blind = small_blind;
         table.big_blind = big_blind;
-        table.max_players = max_players;
-        table.players = vec![];
-        table.status = TableStatus::Waiting;
-        table.pot = 0;
-        table.current_bet = 0;
-        table.dealer_index = 0;
-        table.current_player_index = 0;
-        table.created_at_slot = clock.slot;
-        table.house_fee = 0;
-        table.bump = ctx.bumps.table;
+        table.ante = ante;
+        table.variant = variant;
+        table.betting = betting;
+        table.dealing_mode = dealing_mode;
+        table.chip_mint = chip_mint;
+        table.play_money = chip_mint == Some(play_chip_mint_address());
+        table.tournament = None;
+        table.access_restricted = false;
 
         house.total_tables += 1;
 
-        msg!("Table created: {} with {} max players", table.key(), max_players);
+        msg!(
+            "{:?} {:?} table created: {} with {} max players",
+            betting,
+            variant,
+            table.key(),
+            max_players
+        );
         Ok(())
     }
 
     /// Join a table with buy-in
-    pub fn join_table(ctx: Context<JoinTable>, buy_in: u64) -> Result<()> {
+    ///
+    /// Private tables also need the `table_access` account, plus the
+    /// password and/or an invite token account when those are configured.
+    /// Token and play-money tables take the buy-in from `player_tokens`.
+    pub fn join_table(ctx: Context<JoinTable>, buy_in: u64, password: Option<Vec<u8>>) -> Result<()> {
         let table = &mut ctx.accounts.table;
         let player_state = &mut ctx.accounts.player_state;
         let player = &ctx.accounts.player;
 
-        // Validate table state
+        require!(table.tournament.is_none(), ShadowPokerError::TournamentTable);
+
+        if table.access_restricted {
+            let access = ctx
+                .accounts
+                .table_access
+                .as_ref()
+                .ok_or(ShadowPokerError::AccessDenied)?;
+            access.check(
+                &table.key(),
+                &player.key(),
+                password.as_deref(),
+                ctx.accounts.invite_token.as_deref(),
+            )?;
+        }
+
+        // Players can only sit down between hands
         require!(
-            table.status == TableStatus::Waiting,
+            table.status == TableStatus::Waiting || table.status == TableStatus::Finished,
             ShadowPokerError::TableNotJoinable
         );
 
@@ -93,59 +181,84 @@ pub mod shadow_poker {
             ShadowPokerError::InvalidBuyIn
         );
 
-        // Check table not full
-        require!(
-            table.players.len() < table.max_players as usize,
-            ShadowPokerError::TableFull
-        );
-
         // Check player not already at table
         require!(
             !table.players.contains(&player.key()),
             ShadowPokerError::AlreadyAtTable
         );
 
-        // Add player to table
-        table.players.push(player.key());
+        // Take the first free seat
+        let seat = table
+            .players
+            .iter()
+            .position(|p| *p == Pubkey::default())
+            .ok_or(ShadowPokerError::TableFull)?;
+        table.players[seat] = player.key();
 
         // Initialize player state
-        player_state.player = player.key();
-        player_state.table = table.key();
-        player_state.stack = buy_in;
-        player_state.current_bet = 0;
-        player_state.is_active = true;
-        player_state.has_acted = false;
+        seat_player(player_state, player.key(), table.key(), seat as u8, buy_in);
+        // Once the game is running, new players wait for the big blind (or post one)
+        player_state.needs_big_blind = table.hand_number > 0;
         player_state.bump = ctx.bumps.player_state;
 
         // Transfer buy-in to table escrow
-        let transfer_ix = anchor_lang::solana_program::system_instruction::transfer(
+        let chips = table_chips(
+            table,
             &player.key(),
-            &table.key(),
-            buy_in,
-        );
-        
-        anchor_lang::solana_program::program::invoke(
-            &transfer_ix,
-            &[
-                player.to_account_info(),
-                table.to_account_info(),
-                ctx.accounts.system_program.to_account_info(),
-            ],
+            ctx.accounts.chip_mint.as_ref(),
+            ctx.accounts.player_tokens.as_ref(),
+            ctx.accounts.vault.as_ref(),
+            ctx.accounts.token_program.as_ref(),
         )?;
+        if let Some(chips) = chips {
+            chips.deposit(player, buy_in)?;
+        } else {
+            let transfer_ix = anchor_lang::solana_program::system_instruction::transfer(
+                &player.key(),
+                &table.key(),
+                buy_in,
+            );
+
+            anchor_lang::solana_program::program::invoke(
+                &transfer_ix,
+                &[
+                    player.to_account_info(),
+                    table.to_account_info(),
+                    ctx.accounts.system_program.to_account_info(),
+                ],
+            )?;
+        }
 
         msg!(
-            "Player {} joined table {} with {} lamports",
+            "Player {} joined table {} in seat {} with {} chips",
             player.key(),
             table.key(),
+            seat,
             buy_in
         );
         Ok(())
     }
 
-    /// Start a hand (deal cards)
-    pub fn start_hand(ctx: Context<StartHand>) -> Result<()> {
+    /// Start a hand: move the button, collect antes and blinds, and give the
+    /// action to the first player.
+    ///
+    /// Every seated player's `PlayerState` must be passed writable in
+    /// `remaining_accounts`, in seat order, followed by the session token
+    /// of every straddle posted through a session key that won't play, so
+    /// its refund comes off the key's spending.
+    pub fn start_hand<'info>(ctx: Context<'_, '_, 'info, 'info, StartHand<'info>>) -> Result<()> {
         let table = &mut ctx.accounts.table;
-        let clock = Clock::get()?;
+        let authority = ctx.accounts.authority.key();
+
+        // Seated players can deal the next hand themselves; blinds are derived on-chain
+        if !table.players.contains(&authority) {
+            require_table_operator(
+                table,
+                &ctx.accounts.house,
+                &authority,
+                ctx.accounts.dealer_approval.as_ref(),
+            )?;
+        }
 
         // Validate table state
         require!(
@@ -153,47 +266,334 @@ pub mod shadow_poker {
             ShadowPokerError::HandInProgress
         );
 
-        // Need minimum players
+        let (state_infos, session_infos) = ctx
+            .remaining_accounts
+            .split_at(table.seated_count().min(ctx.remaining_accounts.len()));
+        let mut states = load_player_states(table, state_infos)?;
+
+        // Reset per-hand state and work out who can be dealt in
+        table.betting_round += 1;
+        let mut ready_mask: u16 = 0;
+        let mut waiting_mask: u16 = 0;
+        for state in states.iter_mut() {
+            let seat = state.seat_index;
+            state.current_bet = 0;
+            state.bet_round = table.betting_round;
+            state.hand_contribution = 0;
+            state.has_acted = false;
+            state.is_all_in = false;
+            state.is_active = false;
+            state.insurance = None;
+
+            if state.is_sitting_out
+                || (state.stack == 0 && state.posted_blind == 0 && state.straddle == 0)
+            {
+                continue; // Busted and sitting-out players are dealt out
+            }
+            if state.needs_big_blind {
+                waiting_mask |= 1 << seat;
+            } else {
+                ready_mask |= 1 << seat;
+            }
+        }
+
+        require!(
+            ready_mask.count_ones() + waiting_mask.count_ones() >= MIN_PLAYERS as u32,
+            ShadowPokerError::NotEnoughPlayers
+        );
+
+        // Choose the button and blinds. The big blind moves forward one live
+        // player per hand; the small blind and button follow the previous
+        // blinds even when those seats are now dead.
+        let (button, small_blind, big_blind) = if table.hand_number == 0 {
+            let seat_count = table.max_players;
+            let button = table
+                .next_seat(table.dealer_index + seat_count - 1, ready_mask)
+                .ok_or(ShadowPokerError::NotEnoughPlayers)?;
+            let first = table
+                .next_seat(button, ready_mask)
+                .ok_or(ShadowPokerError::NotEnoughPlayers)?;
+            if ready_mask.count_ones() == 2 {
+                (button, button, first)
+            } else {
+                let second = table
+                    .next_seat(first, ready_mask)
+                    .ok_or(ShadowPokerError::NotEnoughPlayers)?;
+                (button, first, second)
+            }
+        } else {
+            let big_blind = table
+                .next_seat(table.big_blind_index, ready_mask | waiting_mask)
+                .ok_or(ShadowPokerError::NotEnoughPlayers)?;
+            let dealt = ready_mask | (1 << big_blind);
+            if dealt.count_ones() == 2 {
+                // Heads-up: the button posts the small blind
+                let button = table
+                    .next_seat(big_blind, dealt)
+                    .ok_or(ShadowPokerError::NotEnoughPlayers)?;
+                (button, button, big_blind)
+            } else {
+                (table.small_blind_index, table.big_blind_index, big_blind)
+            }
+        };
+
+        let dealt_mask = ready_mask | (1 << big_blind);
         require!(
-            table.players.len() >= MIN_PLAYERS as usize,
+            dealt_mask.count_ones() >= MIN_PLAYERS as u32,
             ShadowPokerError::NotEnoughPlayers
         );
 
+        // Only the first player after the big blind can straddle; anyone
+        // else who posted one gets it back
+        let straddle_seat = table
+            .next_seat(big_blind, dealt_mask)
+            .filter(|_| dealt_mask.count_ones() >= 3);
+        let mut straddled = false;
+        for state in states.iter_mut() {
+            if state.straddle > 0 && Some(state.seat_index) != straddle_seat {
+                if let Some(session_key) = state.straddle_session {
+                    refund_session_spend(session_infos, &session_key, state.straddle)?;
+                }
+                state.stack += state.straddle;
+                state.straddle = 0;
+            }
+        }
+
+        let mut pot: u64 = 0;
+        let mut all_in_mask: u16 = 0;
+        let mut seats = vec![];
+        let mut forced_bets = vec![];
+        for state in states.iter_mut() {
+            let seat = state.seat_index;
+            if dealt_mask & (1 << seat) == 0 {
+                continue;
+            }
+            state.is_active = true;
+            state.needs_big_blind = false;
+            state.time_bank_slots =
+                (state.time_bank_slots + TIME_BANK_ACCRUAL_SLOTS).min(TIME_BANK_MAX_SLOTS);
+            seats.push(SeatSnapshot {
+                seat,
+                player: state.player,
+                stack: state.stack,
+            });
+
+            // Antes are dead money and don't count towards the bet to call
+            let ante = post_forced_bet(state, table.ante);
+            state.current_bet = 0;
+            pot += ante;
+            if ante > 0 {
+                forced_bets.push((seat, ForcedBetKind::Ante, ante));
+            }
+
+            // A blind posted out of position plays as a live bet
+            if state.posted_blind > 0 {
+                forced_bets.push((seat, ForcedBetKind::Posted, state.posted_blind));
+                state.current_bet = state.posted_blind;
+                state.hand_contribution += state.posted_blind;
+                pot += state.posted_blind;
+                state.posted_blind = 0;
+            }
+            if seat == small_blind && dealt_mask & (1 << small_blind) != 0 {
+                let owed = table.small_blind.saturating_sub(state.current_bet);
+                let posted = post_forced_bet(state, owed);
+                pot += posted;
+                forced_bets.push((seat, ForcedBetKind::SmallBlind, posted));
+            }
+            if seat == big_blind {
+                let owed = table.big_blind.saturating_sub(state.current_bet);
+                let posted = post_forced_bet(state, owed);
+                pot += posted;
+                forced_bets.push((seat, ForcedBetKind::BigBlind, posted));
+            }
+            if state.straddle > 0 {
+                forced_bets.push((seat, ForcedBetKind::Straddle, state.straddle));
+                state.current_bet = state.straddle;
+                state.hand_contribution += state.straddle;
+                state.is_all_in = state.stack == 0;
+                pot += state.straddle;
+                state.straddle = 0;
+                straddled = true;
+            }
+            if state.is_all_in {
+                all_in_mask |= 1 << seat;
+            }
+        }
+
+        // Mental poker hands are shuffled by the players before any action
+        if table.dealing_mode == DealingMode::MentalPoker {
+            let deck = ctx.accounts.deck.as_mut().ok_or(ShadowPokerError::MissingDeck)?;
+            require!(deck.ready, ShadowPokerError::DeckNotReady);
+            let first = next_seat_in(table.players.len() as u8, button, dealt_mask)
+                .ok_or(ShadowPokerError::NotEnoughPlayers)?;
+            let shufflers = (0..dealt_mask.count_ones())
+                .scan(first, |seat, _| {
+                    let current = *seat;
+                    *seat = next_seat_in(table.players.len() as u8, current, dealt_mask)?;
+                    Some(current)
+                })
+                .collect();
+            deck.reset(table.hand_number + 1, shufflers, Clock::get()?.slot + TIMEOUT_SLOTS);
+        }
+
         // Reset table for new hand
-        table.status = TableStatus::Dealing;
-        table.pot = 0;
-        table.current_bet = table.big_blind; // Minimum bet is big blind
+        table.status = match table.dealing_mode {
+            DealingMode::Dealer => TableStatus::Dealing,
+            DealingMode::MentalPoker => TableStatus::Shuffling,
+        };
+        table.pot = pot;
+        table.current_bet = match straddled {
+            true => table.big_blind * STRADDLE_BBS,
+            false => table.big_blind,
+        };
+        table.min_raise = table.big_blind;
+        table.raises = 0;
         table.community_cards = vec![];
-        table.current_player_index = (table.dealer_index + 3) % table.players.len() as u8; // UTG starts
+        table.run_twice_from = None;
+        table.second_board = vec![];
+        table.dealer_index = button;
+        table.small_blind_index = small_blind;
+        table.big_blind_index = big_blind;
+        table.active_mask = dealt_mask;
+        table.all_in_mask = all_in_mask;
+        // The straddler acts last preflop, like the big blind
+        let last_to_act = match (straddled, straddle_seat) {
+            (true, Some(seat)) => seat,
+            _ => big_blind,
+        };
+        table.aggressor_seat = last_to_act;
+        table.postflop_aggression = false;
+        table.hand_number += 1;
+
+        // Record who was dealt in with whom
+        for state in states.iter_mut() {
+            if dealt_mask & (1 << state.seat_index) == 0 {
+                continue;
+            }
+            let seat = state.seat_index;
+            for other in seats.iter().filter(|s| s.seat != seat) {
+                state.opponent_mut(other.seat, other.player).hands_together += 1;
+            }
+        }
+
+        // Start a fresh hand history
+        table.event_seq = 0;
+        table.history_hash = [0u8; 32];
+        let table_key = table.key();
+        let hand_number = table.hand_number;
+        let event = HandStarted {
+            table: table_key,
+            hand_number,
+            seq: table.event_seq,
+            max_players: table.max_players,
+            variant: table.variant,
+            betting: table.betting,
+            button,
+            small_blind_seat: small_blind,
+            big_blind_seat: big_blind,
+            small_blind: table.small_blind,
+            big_blind: table.big_blind,
+            ante: table.ante,
+            rake_bps: table.rake_bps,
+            rake_cap: table.rake_cap,
+            is_tournament: table.tournament.is_some(),
+            play_money: table.play_money,
+            seats,
+        };
+        record_event(table, event);
+        for (seat, kind, amount) in forced_bets {
+            let event = ForcedBetPosted {
+                table: table_key,
+                hand_number,
+                seq: table.event_seq,
+                seat,
+                kind,
+                amount,
+            };
+            record_event(table, event);
+        }
 
-        // Reset all player states for new hand
-        // (This would be done via a separate instruction or iteration)
+        // Action starts left of the big blind (the button when heads-up) or
+        // of the straddle
+        table.current_player_index = table
+            .next_seat(last_to_act, table.actionable_mask())
+            .unwrap_or(last_to_act);
+        table.action_deadline_slot = Clock::get()?.slot + TIMEOUT_SLOTS;
 
-        // Move dealer button
-        table.dealer_index = (table.dealer_index + 1) % table.players.len() as u8;
+        for state in states.iter() {
+            state.exit(&crate::ID)?;
+        }
 
-        msg!("New hand started on table {}", table.key());
+        msg!(
+            "Hand {} started on table {}: button {}, blinds {}/{}, pot {}",
+            table.hand_number,
+            table.key(),
+            button,
+            small_blind,
+            big_blind,
+            pot
+        );
         Ok(())
     }
 
-    /// Post blind (small or big)
+    /// Post a big blind out of position so a newly seated player is dealt
+    /// into the next hand instead of waiting for the big blind to reach them,
+    /// or a straddle on tables that allow it. The straddle plays only if the
+    /// player is first to act after the big blind with three or more players
+    /// dealt in; otherwise it goes back to their stack when the hand starts.
+    /// Regular blinds and antes are collected by `start_hand`.
+    ///
+    /// May be signed by a session key, which pays the blind out of its cap.
     pub fn post_blind(ctx: Context<PostBlind>, blind_type: BlindType) -> Result<()> {
-        let table = &mut ctx.accounts.table;
+        let table = &ctx.accounts.table;
         let player_state = &mut ctx.accounts.player_state;
+        let session = authorize_player(
+            &player_state.player,
+            &ctx.accounts.player.key(),
+            ctx.accounts.session_token.as_mut(),
+        )?;
 
+        require!(
+            table.status == TableStatus::Waiting || table.status == TableStatus::Finished,
+            ShadowPokerError::HandInProgress
+        );
         let blind_amount = match blind_type {
-            BlindType::Small => table.small_blind,
-            BlindType::Big => table.big_blind,
+            BlindType::Big => {
+                require!(
+                    player_state.needs_big_blind,
+                    ShadowPokerError::BlindNotRequired
+                );
+                table.big_blind
+            }
+            BlindType::Straddle => {
+                require!(table.options.straddle, ShadowPokerError::StraddleNotAllowed);
+                require!(
+                    !player_state.needs_big_blind
+                        && player_state.posted_blind == 0
+                        && player_state.straddle == 0,
+                    ShadowPokerError::StraddleNotAllowed
+                );
+                table.big_blind * STRADDLE_BBS
+            }
+            BlindType::Small => return Err(ShadowPokerError::BlindPostedAutomatically.into()),
         };
-
         require!(
             player_state.stack >= blind_amount,
             ShadowPokerError::InsufficientStack
         );
 
         player_state.stack -= blind_amount;
-        player_state.current_bet = blind_amount;
-        table.pot += blind_amount;
+        let session_key = session.as_ref().map(|session| session.key());
+        if let Some(session) = session {
+            session.spend(blind_amount)?;
+        }
+        if blind_type == BlindType::Straddle {
+            player_state.straddle = blind_amount;
+            player_state.straddle_session = session_key;
+        } else {
+            player_state.posted_blind = blind_amount;
+            player_state.needs_big_blind = false;
+        }
 
         msg!(
             "Player {} posted {:?} blind of {} lamports",
@@ -205,6 +605,9 @@ pub mod shadow_poker {
     }
 
     /// Player action: Check, Call, Bet, Raise, Fold
+    ///
+    /// May be signed by a session key; the chips put in count against its
+    /// spend cap.
     pub fn player_action(
         ctx: Context<PlayerAction>,
         action: PlayerActionType,
@@ -212,14 +615,34 @@ pub mod shadow_poker {
     ) -> Result<()> {
         let table = &mut ctx.accounts.table;
         let player_state = &mut ctx.accounts.player_state;
+        let session = authorize_player(
+            &player_state.player,
+            &ctx.accounts.player.key(),
+            ctx.accounts.session_token.as_mut(),
+        )?;
+
+        require!(
+            table.status == TableStatus::Dealing || table.status == TableStatus::Betting,
+            ShadowPokerError::InvalidGameState
+        );
 
         // Verify it's player's turn
-        let current_player = table.players[table.current_player_index as usize];
+        let seat = table.current_player_index;
+        let current_player = *table
+            .players
+            .get(seat as usize)
+            .ok_or(ShadowPokerError::NotYourTurn)?;
         require!(
             current_player == player_state.player,
             ShadowPokerError::NotYourTurn
         );
+        require!(
+            player_state.is_active && !player_state.is_all_in,
+            ShadowPokerError::PlayerNotInHand
+        );
+        player_state.sync_round(table);
 
+        let stack_before = player_state.stack;
         match action {
             PlayerActionType::Check => {
                 require!(
@@ -228,40 +651,104 @@ pub mod shadow_poker {
                 );
             }
             PlayerActionType::Call => {
-                let call_amount = table.current_bet - player_state.current_bet;
-                require!(
-                    player_state.stack >= call_amount,
-                    ShadowPokerError::InsufficientStack
-                );
+                // A short stack calls all-in for whatever it has left
+                let call_amount = table
+                    .current_bet
+                    .saturating_sub(player_state.current_bet)
+                    .min(player_state.stack);
                 player_state.stack -= call_amount;
-                player_state.current_bet = table.current_bet;
+                player_state.current_bet += call_amount;
+                player_state.hand_contribution += call_amount;
                 table.pot += call_amount;
             }
             PlayerActionType::Bet | PlayerActionType::Raise => {
                 let bet_amount = amount.ok_or(ShadowPokerError::InvalidBetAmount)?;
-                let total_bet = player_state.current_bet + bet_amount;
-                require!(
-                    total_bet > table.current_bet,
-                    ShadowPokerError::BetTooSmall
-                );
                 require!(
                     player_state.stack >= bet_amount,
                     ShadowPokerError::InsufficientStack
                 );
+                let total_bet = player_state.current_bet + bet_amount;
+                let (min_total, max_total) = table
+                    .raise_bounds(player_state.current_bet, player_state.stack)
+                    .ok_or(ShadowPokerError::RaiseNotAllowed)?;
+                require!(total_bet >= min_total, ShadowPokerError::BetTooSmall);
+                require!(total_bet <= max_total, ShadowPokerError::BetTooLarge);
+
+                // Only a full raise counts towards the fixed-limit raise cap
+                let raise = total_bet - table.current_bet;
+                if table.current_bet > 0 && raise >= table.min_raise {
+                    table.raises += 1;
+                }
+                // A short all-in raise doesn't change the minimum raise
+                table.min_raise = table.min_raise.max(raise);
+                table.aggressor_seat = seat;
+                if !table.community_cards.is_empty() {
+                    table.postflop_aggression = true;
+                }
                 player_state.stack -= bet_amount;
                 player_state.current_bet = total_bet;
+                player_state.hand_contribution += bet_amount;
                 table.current_bet = total_bet;
                 table.pot += bet_amount;
             }
             PlayerActionType::Fold => {
                 player_state.is_active = false;
+                table.active_mask &= !(1 << seat);
+
+                // Folding postflop to a small bet is a soft-play signal
+                let facing = table.current_bet.saturating_sub(player_state.current_bet);
+                let aggressor = table.aggressor_seat;
+                if !table.community_cards.is_empty()
+                    && facing > 0
+                    && facing * SMALL_BET_POT_DIVISOR <= table.pot
+                    && aggressor != seat
+                {
+                    let opponent = table.players[aggressor as usize];
+                    let player = player_state.player;
+                    let stats = player_state.opponent_mut(aggressor, opponent);
+                    stats.small_bet_folds += 1;
+                    emit!(CollusionSignal {
+                        table: table.key(),
+                        hand_number: table.hand_number,
+                        kind: SignalKind::SmallBetFold,
+                        player,
+                        opponent,
+                        amount: facing,
+                        stats: *stats,
+                    });
+                }
             }
         }
 
+        if player_state.is_active && player_state.stack == 0 {
+            player_state.is_all_in = true;
+            table.all_in_mask |= 1 << seat;
+        }
         player_state.has_acted = true;
+        player_state.timeout_strikes = 0;
+
+        let moved = stack_before - player_state.stack;
+        if let Some(session) = session {
+            session.spend(moved)?;
+        }
+
+        let event = ActionTaken {
+            table: table.key(),
+            hand_number: table.hand_number,
+            seq: table.event_seq,
+            seat,
+            action,
+            amount: moved,
+            timed_out: false,
+            protected: false,
+        };
+        record_event(table, event);
 
-        // Move to next player
-        table.current_player_index = (table.current_player_index + 1) % table.players.len() as u8;
+        // Move to the next player who can still act
+        table.current_player_index = table
+            .next_seat(seat, table.actionable_mask())
+            .unwrap_or(seat);
+        table.action_deadline_slot = Clock::get()?.slot + TIMEOUT_SLOTS;
 
         msg!(
             "Player {} performed {:?}",
@@ -271,33 +758,123 @@ pub mod shadow_poker {
         Ok(())
     }
 
+    /// View for clients (call through simulation): what a player may do on
+    /// their turn. Bet and raise amounts are in `player_action` terms, i.e.
+    /// chips added on top of what the player already has in this street.
+    pub fn legal_actions(ctx: Context<LegalActions>) -> Result<ActionOptions> {
+        let table = &ctx.accounts.table;
+        let player_state = &ctx.accounts.player_state;
+
+        let in_betting = table.status == TableStatus::Dealing || table.status == TableStatus::Betting;
+        let is_turn = in_betting
+            && player_state.is_active
+            && !player_state.is_all_in
+            && table.players.get(table.current_player_index as usize) == Some(&player_state.player);
+        if !is_turn {
+            return Ok(ActionOptions::default());
+        }
+
+        let bet = player_state.street_bet(table);
+        let raise = table.raise_bounds(bet, player_state.stack);
+        Ok(ActionOptions {
+            is_turn,
+            can_check: bet >= table.current_bet,
+            call_amount: table.current_bet.saturating_sub(bet).min(player_state.stack),
+            can_raise: raise.is_some(),
+            min_raise_amount: raise.map_or(0, |(min, _)| min - bet),
+            max_raise_amount: raise.map_or(0, |(_, max)| max - bet),
+        })
+    }
+
     /// Reveal community cards (flop, turn, river)
+    ///
+    /// On mental poker tables anyone can reveal cards once every player's
+    /// decryption share for them is in; the cards must match the deck.
     pub fn reveal_cards(
         ctx: Context<RevealCards>,
         cards: Vec<Card>,
     ) -> Result<()> {
         let table = &mut ctx.accounts.table;
 
+        if table.dealing_mode == DealingMode::Dealer {
+            require_table_operator(
+                table,
+                &ctx.accounts.house,
+                &ctx.accounts.authority.key(),
+                ctx.accounts.dealer_approval.as_ref(),
+            )?;
+        }
+
         require!(
             table.status == TableStatus::Dealing || table.status == TableStatus::Betting,
             ShadowPokerError::InvalidGameState
         );
 
+        // Once nobody can act any more, the rest of the board is run twice
+        // on tables that offer it
+        let board_len = table.community_cards.len();
+        if table.options.run_it_twice
+            && table.run_twice_from.is_none()
+            && board_len < 5
+            && table.active_mask.count_ones() > 1
+            && table.actionable_mask().count_ones() <= 1
+        {
+            table.run_twice_from = Some(board_len as u8);
+        }
+
         // Validate correct number of cards for each street
-        match table.community_cards.len() {
-            0 => require!(cards.len() == 3, ShadowPokerError::InvalidCardCount), // Flop
-            3 => require!(cards.len() == 1, ShadowPokerError::InvalidCardCount), // Turn
-            4 => require!(cards.len() == 1, ShadowPokerError::InvalidCardCount), // River
+        let expected = match (board_len, table.run_twice_from) {
+            (0, _) => 3,     // Flop
+            (3, _) => 1,     // Turn
+            (4, _) => 1,     // River
+            (5, Some(shared)) if table.second_board.is_empty() => 5 - shared as usize, // Second run
             _ => return Err(ShadowPokerError::InvalidGameState.into()),
+        };
+        require!(cards.len() == expected, ShadowPokerError::InvalidCardCount);
+        require_valid_cards(table.variant, &cards, &table.community_cards)?;
+        if table.dealing_mode == DealingMode::MentalPoker {
+            let deck = ctx.accounts.deck.as_ref().ok_or(ShadowPokerError::MissingDeck)?;
+            let first = deck.board_position(table.community_cards.len());
+            for (i, card) in cards.iter().enumerate() {
+                require!(
+                    deck.decrypted_card(first + i) == Some(*card),
+                    ShadowPokerError::CardMismatch
+                );
+            }
         }
 
         let cards_len = cards.len();
+        let second_run = board_len == 5;
+        let event = BoardDealt {
+            table: table.key(),
+            hand_number: table.hand_number,
+            seq: table.event_seq,
+            cards: cards.clone(),
+            second_run,
+        };
+        record_event(table, event);
+        if second_run {
+            let shared = 5 - cards_len;
+            table.second_board = table.community_cards[..shared].to_vec();
+            table.second_board.extend(cards);
+            msg!("Second board dealt: {} cards", cards_len);
+            return Ok(());
+        }
         table.community_cards.extend(cards);
         table.status = TableStatus::Betting;
+
+        // Start a new betting round; player bets reset lazily on their next action
+        table.betting_round += 1;
         table.current_bet = 0;
+        table.min_raise = table.big_blind;
+        table.raises = 0;
 
-        // Reset player bets for new betting round
-        // (Would need to iterate through all player states)
+        // Post-flop action starts left of the button
+        let button = table.dealer_index;
+        table.current_player_index = table
+            .next_seat(button, table.actionable_mask())
+            .unwrap_or(button);
+        table.action_deadline_slot = Clock::get()?.slot + TIMEOUT_SLOTS;
 
         msg!(
             "Revealed {} cards. Community cards: {}",
@@ -307,139 +884,3818 @@ pub mod shadow_poker {
         Ok(())
     }
 
-    /// Resolve Hand (Temporary until Arcium MPC)
-    pub fn resolve_hand(ctx: Context<Showdown>) -> Result<()> {
+    /// Show hole cards at showdown so they are part of the hand history
+    pub fn show_cards(ctx: Context<ShowCards>, cards: Vec<Card>) -> Result<()> {
         let table = &mut ctx.accounts.table;
-        let house = &mut ctx.accounts.house;
-        let winner_state = &mut ctx.accounts.winner_state;
-        
-        require!(table.status == TableStatus::Betting || table.status == TableStatus::Dealing || table.status == TableStatus::Finished, ShadowPokerError::InvalidGameState);
-
-        let house_fee = (table.pot * HOUSE_FEE_BPS as u64) / 10000;
-        let winner_payout = table.pot.checked_sub(house_fee).unwrap_or(0);
-
-        table.house_fee += house_fee;
-        house.treasury += house_fee;
-        house.total_volume += table.pot;
+        let player_state = &ctx.accounts.player_state;
 
-        // Route house fee to treasury
-        **table.to_account_info().try_borrow_mut_lamports()? -= house_fee;
-        **house.to_account_info().try_borrow_mut_lamports()? += house_fee;
+        require!(
+            table.status == TableStatus::Dealing || table.status == TableStatus::Betting,
+            ShadowPokerError::InvalidGameState
+        );
+        require!(player_state.is_active, ShadowPokerError::PlayerNotInHand);
+        require!(
+            cards.len() == table.variant.hole_cards() as usize,
+            ShadowPokerError::InvalidCardCount
+        );
+        require_valid_cards(table.variant, &cards, &table.community_cards)?;
+        if table.dealing_mode == DealingMode::MentalPoker {
+            // The player's own decryption shares must already be in
+            let deck = ctx.accounts.deck.as_mut().ok_or(ShadowPokerError::MissingDeck)?;
+            let positions = deck
+                .hole_positions(player_state.seat_index)
+                .ok_or(ShadowPokerError::PlayerNotInHand)?;
+            for (position, card) in positions.zip(cards.iter()) {
+                require!(
+                    deck.decrypted_card(position) == Some(*card),
+                    ShadowPokerError::CardMismatch
+                );
+            }
+            deck.shown_mask |= 1 << player_state.seat_index;
+        }
 
-        // Credit winner
-        winner_state.stack += winner_payout;
-        
-        table.pot = 0;
-        table.status = TableStatus::Finished;
+        let event = CardsShown {
+            table: table.key(),
+            hand_number: table.hand_number,
+            seq: table.event_seq,
+            seat: player_state.seat_index,
+            cards,
+        };
+        record_event(table, event);
 
-        msg!("Hand resolved. Winner payout: {}", winner_payout);
+        msg!("Player {} showed their cards", player_state.player);
         Ok(())
     }
 
-    /// Leave table and withdraw remaining stack
-    pub fn leave_table(ctx: Context<LeaveTable>) -> Result<()> {
+    /// Permissionless crank: once the acting player's deadline has passed,
+    /// check for them if they can, otherwise fold them. Players who time
+    /// out `MAX_TIMEOUT_STRIKES` times in a row are sat out.
+    ///
+    /// A player facing a bet with disconnect protection left is instead
+    /// kept in the hand as all-in for what they have already put in, using
+    /// up one of their `DISCONNECT_PROTECTIONS` for the session. Like any
+    /// all-in player they can then only win the pots they matched.
+    pub fn enforce_timeout(ctx: Context<EnforceTimeout>) -> Result<()> {
         let table = &mut ctx.accounts.table;
-        let player_state = &ctx.accounts.player_state;
-        let player = &ctx.accounts.player;
+        let player_state = &mut ctx.accounts.player_state;
+        let clock = Clock::get()?;
 
-        // Verify player is at table
-        let player_index = table.players
-            .iter()
-            .position(|&p| p == player.key())
-            .ok_or(ShadowPokerError::NotAtTable)?;
+        require!(
+            table.status == TableStatus::Dealing || table.status == TableStatus::Betting,
+            ShadowPokerError::InvalidGameState
+        );
+        require!(
+            clock.slot > table.action_deadline_slot,
+            ShadowPokerError::TimeoutNotReached
+        );
+
+        let seat = table.current_player_index;
+        require!(
+            table.players.get(seat as usize) == Some(&player_state.player),
+            ShadowPokerError::NotYourTurn
+        );
+        require!(
+            player_state.is_active && !player_state.is_all_in,
+            ShadowPokerError::PlayerNotInHand
+        );
 
-        // Remove player from table
-        table.players.remove(player_index);
+        player_state.sync_round(table);
+        let mut protected = false;
+        let action = if player_state.current_bet >= table.current_bet {
+            PlayerActionType::Check
+        } else if player_state.disconnect_protections > 0 {
+            player_state.disconnect_protections -= 1;
+            player_state.is_all_in = true;
+            table.all_in_mask |= 1 << seat;
+            protected = true;
+            PlayerActionType::Check
+        } else {
+            player_state.is_active = false;
+            table.active_mask &= !(1 << seat);
+            PlayerActionType::Fold
+        };
+        player_state.has_acted = true;
 
-        // Return remaining stack
-        let return_amount = player_state.stack;
-        if return_amount > 0 {
-            let table_key = table.key();
-            let seeds = &[
-                b"table",
-                table_key.as_ref(),
-                &[table.bump],
-            ];
-            let signer = &[&seeds[..]];
+        let event = ActionTaken {
+            table: table.key(),
+            hand_number: table.hand_number,
+            seq: table.event_seq,
+            seat,
+            action,
+            amount: 0,
+            timed_out: true,
+            protected,
+        };
+        record_event(table, event);
 
-            anchor_lang::system_program::transfer(
-                CpiContext::new_with_signer(
-                    ctx.accounts.system_program.to_account_info(),
-                    anchor_lang::system_program::Transfer {
-                        from: table.to_account_info(),
-                        to: player.to_account_info(),
-                    },
-                    signer,
-                ),
-                return_amount,
-            )?;
+        player_state.timeout_strikes = player_state.timeout_strikes.saturating_add(1);
+        if player_state.timeout_strikes >= MAX_TIMEOUT_STRIKES {
+            player_state.is_sitting_out = true;
         }
 
+        table.current_player_index = table
+            .next_seat(seat, table.actionable_mask())
+            .unwrap_or(seat);
+        table.action_deadline_slot = clock.slot + TIMEOUT_SLOTS;
+
         msg!(
-            "Player {} left table {} with {} lamports returned",
-            player.key(),
-            table.key(),
-            return_amount
+            "Player {} timed out ({:?}{}), strike {}/{}",
+            player_state.player,
+            action,
+            if protected { ", disconnect protected" } else { "" },
+            player_state.timeout_strikes,
+            MAX_TIMEOUT_STRIKES
         );
         Ok(())
     }
 
-    /// Withdraw treasury (admin only)
-    pub fn withdraw_treasury(ctx: Context<WithdrawTreasury>, amount: u64) -> Result<()> {
+    /// Draw on the time bank to extend the acting player's deadline by up
+    /// to `TIME_BANK_CHUNK_SLOTS`. Can be called again while time remains;
+    /// time drawn and not used is lost. May be signed by a session key.
+    pub fn use_time_bank(ctx: Context<UseTimeBank>) -> Result<()> {
+        let table = &mut ctx.accounts.table;
+        let player_state = &mut ctx.accounts.player_state;
+        let clock = Clock::get()?;
+        authorize_player(
+            &player_state.player,
+            &ctx.accounts.player.key(),
+            ctx.accounts.session_token.as_mut(),
+        )?;
+
+        require!(
+            table.status == TableStatus::Dealing || table.status == TableStatus::Betting,
+            ShadowPokerError::InvalidGameState
+        );
+        require!(
+            table.players.get(table.current_player_index as usize) == Some(&player_state.player),
+            ShadowPokerError::NotYourTurn
+        );
+        require!(
+            player_state.is_active && !player_state.is_all_in,
+            ShadowPokerError::PlayerNotInHand
+        );
+        require!(
+            clock.slot <= table.action_deadline_slot,
+            ShadowPokerError::TurnTimedOut
+        );
+        require!(player_state.time_bank_slots > 0, ShadowPokerError::TimeBankEmpty);
+
+        let slots = player_state.time_bank_slots.min(TIME_BANK_CHUNK_SLOTS);
+        player_state.time_bank_slots -= slots;
+        table.action_deadline_slot += slots;
+
+        emit!(TimeBankUsed {
+            table: table.key(),
+            hand_number: table.hand_number,
+            seat: player_state.seat_index,
+            slots,
+            remaining: player_state.time_bank_slots,
+        });
+        Ok(())
+    }
+
+    /// Offer a player all-in insurance at a house-set price (house authority
+    /// or approved dealer). The price comes from the player's equity, which
+    /// only the dealer knows; the offer lapses when the next card is dealt.
+    pub fn offer_insurance(ctx: Context<OfferInsurance>, premium: u64, coverage: u64) -> Result<()> {
+        let table = &ctx.accounts.table;
         let house = &ctx.accounts.house;
-        
+        let player_state = &mut ctx.accounts.player_state;
+        let authority = ctx.accounts.authority.key();
+
+        let is_dealer = ctx
+            .accounts
+            .dealer_approval
+            .as_ref()
+            .is_some_and(|approval| approval.dealer == authority);
         require!(
-            house.authority == ctx.accounts.authority.key(),
+            authority == house.authority || is_dealer,
             ShadowPokerError::UnauthorizedHouse
         );
-        
+        require!(table.options.insurance, ShadowPokerError::InsuranceNotAllowed);
         require!(
-            amount <= house.treasury,
-            ShadowPokerError::InsufficientTreasury
+            table.hand_running()
+                && table.community_cards.len() < 5
+                && table.active_mask.count_ones() > 1
+                && table.actionable_mask().count_ones() <= 1,
+            ShadowPokerError::InsuranceNotAllowed
+        );
+        require!(player_state.is_active, ShadowPokerError::PlayerNotInHand);
+        require!(
+            !player_state.insurance.is_some_and(|i| i.accepted),
+            ShadowPokerError::InsuranceNotAllowed
+        );
+        require!(
+            premium > 0 && premium < coverage && coverage <= table.pot,
+            ShadowPokerError::InvalidInsurance
         );
 
-        **ctx.accounts.house.to_account_info().try_borrow_mut_lamports()? -= amount;
-        **ctx.accounts.authority.to_account_info().try_borrow_mut_lamports()? += amount;
+        player_state.insurance = Some(InsuranceOffer {
+            premium,
+            coverage,
+            board_len: table.community_cards.len() as u8,
+            accepted: false,
+        });
 
-        msg!("Treasury withdrawal: {} lamports", amount);
+        emit!(InsuranceOffered {
+            table: table.key(),
+            hand_number: table.hand_number,
+            seat: player_state.seat_index,
+            premium,
+            coverage,
+        });
         Ok(())
     }
-}
 
-// Account Structures
+    /// Buy the insurance on offer, paying the premium to the house. The
+    /// house sets the coverage aside from its treasury until the hand is
+    /// resolved.
+    pub fn accept_insurance(ctx: Context<AcceptInsurance>) -> Result<()> {
+        let table = &ctx.accounts.table;
+        let house = &mut ctx.accounts.house;
+        let player_state = &mut ctx.accounts.player_state;
 
-#[derive(Accounts)]
-pub struct InitializeHouse<'info> {
-    #[account(
-        init,
-        payer = authority,
-        space = 8 + ShadowPokerHouse::SIZE,
-        seeds = [b"shadow_poker_house"],
-        bump
-    )]
-    pub house: Account<'info, ShadowPokerHouse>,
-    
-    #[account(mut)]
-    pub authority: Signer<'info>,
-    
-    pub system_program: Program<'info, System>,
-}
+        require!(table.hand_running(), ShadowPokerError::InvalidGameState);
+        let mut insurance = player_state
+            .insurance
+            .filter(|i| !i.accepted)
+            .ok_or(ShadowPokerError::InvalidInsurance)?;
+        require!(
+            insurance.board_len as usize == table.community_cards.len(),
+            ShadowPokerError::InsuranceOfferExpired
+        );
 
-#[derive(Accounts)]
-#[instruction(min_buy_in: u64, max_buy_in: u64, small_blind: u64, big_blind: u64, max_players: u8)]
-pub struct CreateTable<'info> {
-    #[account(
-        init,
-        payer = creator,
-        space = 8 + Table::SIZE,
+        anchor_lang::system_program::transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                anchor_lang::system_program::Transfer {
+                    from: ctx.accounts.player.to_account_info(),
+                    to: house.to_account_info(),
+                },
+            ),
+            insurance.premium,
+        )?;
+        house.treasury += insurance.premium;
+        require!(
+            house.treasury >= insurance.coverage,
+            ShadowPokerError::InsufficientTreasury
+        );
+        house.treasury -= insurance.coverage;
+
+        insurance.accepted = true;
+        player_state.insurance = Some(insurance);
+
+        msg!(
+            "Player {} insured for {} at a premium of {}",
+            player_state.player,
+            insurance.coverage,
+            insurance.premium
+        );
+        Ok(())
+    }
+
+    /// Resolve Hand (Temporary until Arcium MPC)
+    ///
+    /// The pot is split into a main pot and side pots by what each player
+    /// put in (see `pots`). `ranking` lists seats still in the hand, best
+    /// hand first, and each pot goes to the first of them who can win it.
+    /// A hand that was run twice needs `second_ranking` for the second
+    /// board, and each contested pot is split between the two boards.
+    ///
+    /// Every seated player's `PlayerState` must be passed writable in
+    /// `remaining_accounts`, in seat order, so the pots can be built.
+    pub fn resolve_hand<'info>(
+        ctx: Context<'_, '_, 'info, 'info, Showdown<'info>>,
+        ranking: Vec<u8>,
+        second_ranking: Option<Vec<u8>>,
+    ) -> Result<()> {
+        let table = &mut ctx.accounts.table;
+        let house = &mut ctx.accounts.house;
+        
+        require!(table.status == TableStatus::Betting || table.status == TableStatus::Dealing, ShadowPokerError::InvalidGameState);
+
+        let mut states = load_player_states(table, ctx.remaining_accounts)?;
+
+        // Ranked seats must still be in the hand
+        for ranked in [Some(&ranking), second_ranking.as_ref()].into_iter().flatten() {
+            require!(
+                ranked.iter().all(|&seat| seat < MAX_PLAYERS && table.active_mask & (1 << seat) != 0),
+                ShadowPokerError::InvalidWinner
+            );
+        }
+        let contested = table.active_mask.count_ones() > 1;
+        let run_twice = contested && table.run_twice_from.is_some();
+        require!(
+            second_ranking.is_some() == run_twice,
+            ShadowPokerError::InvalidWinner
+        );
+        if run_twice {
+            require!(table.second_board.len() == 5, ShadowPokerError::SecondBoardPending);
+        }
+
+        let contributions: Vec<(u8, u64)> = states
+            .iter()
+            .map(|s| (s.seat_index, s.hand_contribution))
+            .collect();
+        require!(
+            contributions.iter().map(|c| c.1).sum::<u64>() == table.pot,
+            ShadowPokerError::InvalidGameState
+        );
+        let pots = pots::build_pots(&contributions, table.active_mask);
+        let rake = table.rake_for_current_hand(pots::uncalled(&contributions, table.active_mask));
+        let shares = pots::shares(&pots, rake, run_twice);
+
+        // A pot only one player can win goes to them. When more can, anyone
+        // can award the pot; otherwise a table operator must, except on
+        // mental poker tables where the program judges the showdown.
+        if pots.iter().any(|pot| pot.contested()) {
+            match table.dealing_mode {
+                DealingMode::Dealer => require_table_operator(
+                    table,
+                    house,
+                    &ctx.accounts.authority.key(),
+                    ctx.accounts.dealer_approval.as_ref(),
+                )?,
+                DealingMode::MentalPoker => {
+                    let deck = ctx.accounts.deck.as_ref().ok_or(ShadowPokerError::MissingDeck)?;
+                    require!(
+                        table.community_cards.len() == 5 && table.round_complete(&states),
+                        ShadowPokerError::RoundNotComplete
+                    );
+                    require!(
+                        deck.verified_mask == deck.shuffler_mask(),
+                        ShadowPokerError::ShufflesUnverified
+                    );
+                    // Players who haven't shown by the deadline muck
+                    require!(
+                        deck.shown_mask & table.active_mask == table.active_mask
+                            || Clock::get()?.slot > table.action_deadline_slot,
+                        ShadowPokerError::TimeoutNotReached
+                    );
+                }
+            }
+        }
+        let mut awards = Vec::with_capacity(shares.len());
+        for share in shares.iter() {
+            let pot = &pots[share.pot as usize];
+            let seat = match pot.contested() {
+                false => pot.eligible.trailing_zeros() as u8,
+                true => {
+                    let (ranked, board) = match share.second_run {
+                        false => (&ranking, &table.community_cards),
+                        true => (second_ranking.as_ref().unwrap_or(&ranking), &table.second_board),
+                    };
+                    let seat = ranked
+                        .iter()
+                        .copied()
+                        .find(|&seat| pot.eligible & (1 << seat) != 0)
+                        .ok_or(ShadowPokerError::InvalidWinner)?;
+                    if let (DealingMode::MentalPoker, Some(deck)) =
+                        (table.dealing_mode, ctx.accounts.deck.as_ref())
+                    {
+                        require!(
+                            deck.showdown_winners(table, board, pot.eligible) & (1 << seat) != 0,
+                            ShadowPokerError::InvalidWinner
+                        );
+                    }
+                    seat
+                }
+            };
+            awards.push((seat, *share));
+        }
+        let winner_payout = table.pot - rake;
+
+        if table.tournament.is_none() && table.chip_mint.is_none() {
+            house.total_volume += table.pot;
+        }
+        if rake > 0 {
+            // Attribute the rake to players by what they put in the pot
+            let total_contributed: u64 = states.iter().map(|s| s.hand_contribution).sum();
+            let mut rakeback_total: u64 = 0;
+            for state in states.iter_mut() {
+                let share = (rake as u128 * state.hand_contribution as u128)
+                    .checked_div(total_contributed as u128)
+                    .unwrap_or(0) as u64;
+                let rakeback = share * table.rakeback_bps as u64 / 10000;
+                state.rake_paid += share;
+                state.rakeback_owed += rakeback;
+                rakeback_total += rakeback;
+            }
+
+            table.house_fee += rake;
+            if table.chip_mint.is_some() {
+                // Token rake and rake-back stay in the vault until collected
+                table.rake_held += rake - rakeback_total;
+            } else {
+                // Rake-back stays in the house account but out of the withdrawable treasury
+                house.treasury += rake - rakeback_total;
+
+                **table.to_account_info().try_borrow_mut_lamports()? -= rake;
+                **house.to_account_info().try_borrow_mut_lamports()? += rake;
+            }
+        }
+
+        // Credit each pot's winners
+        for &(seat, share) in awards.iter() {
+            let winner_state = states
+                .iter_mut()
+                .find(|s| s.seat_index == seat)
+                .ok_or(ShadowPokerError::InvalidWinner)?;
+            winner_state.stack += share.amount;
+
+            // The pot takes its layer of every contribution, and each board
+            // half of it when the pot was split
+            let pot = pots[share.pot as usize];
+            let split = run_twice && pot.contested();
+            let layer = move |contribution: u64| {
+                let chips = pot.share_of(contribution);
+                match (split, share.second_run) {
+                    (false, _) => chips,
+                    (true, false) => chips - chips / 2,
+                    (true, true) => chips / 2,
+                }
+            };
+            track_chip_flow(table.key(), table.hand_number, &mut states, seat, layer);
+        }
+
+        // Two players checking a hand down with no bet after the flop
+        if table.active_mask.count_ones() == 2
+            && table.community_cards.len() == 5
+            && !table.postflop_aggression
+        {
+            let pair: Vec<(u8, Pubkey)> = states
+                .iter()
+                .filter(|s| table.active_mask & (1 << s.seat_index) != 0)
+                .map(|s| (s.seat_index, s.player))
+                .collect();
+            for state in states.iter_mut() {
+                let (seat, opponent) = match pair.iter().position(|p| p.0 == state.seat_index) {
+                    Some(i) => pair[1 - i],
+                    None => continue,
+                };
+                let player = state.player;
+                let stats = state.opponent_mut(seat, opponent);
+                stats.soft_play_hands += 1;
+                emit!(CollusionSignal {
+                    table: table.key(),
+                    hand_number: table.hand_number,
+                    kind: SignalKind::SoftPlay,
+                    player,
+                    opponent,
+                    amount: table.pot,
+                    stats: *stats,
+                });
+            }
+        }
+
+        // Settle insurance: the house pays out for each board an insured
+        // player still in the hand won no contested pot on, and keeps the
+        // rest of its reserve
+        let runs: u64 = if run_twice { 2 } else { 1 };
+        for state in states.iter_mut() {
+            let Some(insurance) = state.insurance.take().filter(|i| i.accepted) else {
+                continue;
+            };
+            let won_on = |second_run: bool| {
+                awards.iter().any(|&(seat, share)| {
+                    seat == state.seat_index
+                        && share.second_run == second_run
+                        && pots[share.pot as usize].contested()
+                })
+            };
+            let boards_lost = [false, true][..runs as usize]
+                .iter()
+                .filter(|&&second_run| !won_on(second_run))
+                .count() as u64;
+            let paid = match state.is_active {
+                true => insurance.coverage * boards_lost / runs,
+                false => 0,
+            };
+            house.treasury += insurance.coverage - paid;
+            if paid > 0 {
+                **house.to_account_info().try_borrow_mut_lamports()? -= paid;
+                **table.to_account_info().try_borrow_mut_lamports()? += paid;
+                state.stack += paid;
+            }
+            emit!(InsuranceSettled {
+                table: table.key(),
+                hand_number: table.hand_number,
+                seat: state.seat_index,
+                premium: insurance.premium,
+                coverage: insurance.coverage,
+                paid,
+            });
+        }
+
+        let table_key = table.key();
+        for &(seat, share) in awards.iter() {
+            let event = PotAwarded {
+                table: table_key,
+                hand_number: table.hand_number,
+                seq: table.event_seq,
+                winner_seat: seat,
+                pot: share.pot,
+                amount: share.amount,
+                rake: share.rake,
+                second_run: share.second_run,
+            };
+            record_event(table, event);
+        }
+
+        // Commit the hand history so replays can be checked against it
+        table.last_hand_hash = table.history_hash;
+        emit!(HandCompleted {
+            table: table_key,
+            hand_number: table.hand_number,
+            events: table.event_seq,
+            history_hash: table.history_hash,
+        });
+        
+        table.pot = 0;
+        table.status = TableStatus::Finished;
+
+        for state in states.iter() {
+            state.exit(&crate::ID)?;
+        }
+
+        msg!("Hand resolved. Winner payout: {}, rake: {}", winner_payout, rake);
+        Ok(())
+    }
+
+    /// Leave the table and withdraw the remaining stack.
+    ///
+    /// A player still in a running hand folds (forfeiting their bet) and is
+    /// marked to leave, as is one who already folded chips into it, since
+    /// the pots are built from every contribution. They call `leave_table`
+    /// again once the hand is over to cash out, or anyone can with
+    /// `cash_out_player`. Cashing out frees the seat and closes `PlayerState`.
+    pub fn leave_table(ctx: Context<LeaveTable>) -> Result<()> {
+        let table = &mut ctx.accounts.table;
+        let player_state = &mut ctx.accounts.player_state;
+        let player = &ctx.accounts.player;
+
+        require!(table.tournament.is_none(), ShadowPokerError::TournamentTable);
+
+        let seat = player_state.seat_index;
+        require!(
+            table.players.get(seat as usize) == Some(&player.key()),
+            ShadowPokerError::NotAtTable
+        );
+
+        if table.hand_running() && (player_state.is_active || player_state.hand_contribution > 0) {
+            player_state.is_sitting_out = true;
+            player_state.leave_pending = true;
+            if player_state.is_active {
+                player_state.is_active = false;
+                table.active_mask &= !(1 << seat);
+
+                let event = ActionTaken {
+                    table: table.key(),
+                    hand_number: table.hand_number,
+                    seq: table.event_seq,
+                    seat,
+                    action: PlayerActionType::Fold,
+                    amount: 0,
+                    timed_out: false,
+                    protected: false,
+                };
+                record_event(table, event);
+
+                if table.current_player_index == seat {
+                    table.current_player_index = table
+                        .next_seat(seat, table.actionable_mask())
+                        .unwrap_or(seat);
+                    table.action_deadline_slot = Clock::get()?.slot + TIMEOUT_SLOTS;
+                }
+            }
+
+            msg!(
+                "Player {} folded and will leave table {} after the hand",
+                player.key(),
+                table.key()
+            );
+            return Ok(());
+        }
+
+        let chips = table_chips(
+            table,
+            &player.key(),
+            ctx.accounts.chip_mint.as_ref(),
+            ctx.accounts.player_tokens.as_ref(),
+            ctx.accounts.vault.as_ref(),
+            ctx.accounts.token_program.as_ref(),
+        )?;
+        let sessions: Vec<AccountInfo> =
+            ctx.accounts.session_token.iter().map(|token| token.to_account_info()).collect();
+        let return_amount = cash_out(
+            table,
+            &ctx.accounts.house,
+            player_state,
+            &player.to_account_info(),
+            chips,
+            &sessions,
+        )?;
+
+        msg!(
+            "Player {} left table {} with {} chips returned",
+            player.key(),
+            table.key(),
+            return_amount
+        );
+        Ok(())
+    }
+
+    /// Permissionless crank: cash out a player who left in the middle of a
+    /// hand once it is over, so their chips don't wait at the table for
+    /// them to come back and call `leave_table` again.
+    pub fn cash_out_player(ctx: Context<CashOutPlayer>) -> Result<()> {
+        let table = &mut ctx.accounts.table;
+        let player_state = &mut ctx.accounts.player_state;
+        let player = &ctx.accounts.player;
+
+        require!(player_state.leave_pending, ShadowPokerError::NoLeavePending);
+        require!(!table.hand_running(), ShadowPokerError::HandInProgress);
+
+        let chips = table_chips(
+            table,
+            &player.key(),
+            ctx.accounts.chip_mint.as_ref(),
+            ctx.accounts.player_tokens.as_ref(),
+            ctx.accounts.vault.as_ref(),
+            ctx.accounts.token_program.as_ref(),
+        )?;
+        let sessions: Vec<AccountInfo> =
+            ctx.accounts.session_token.iter().map(|token| token.to_account_info()).collect();
+        let return_amount = cash_out(
+            table,
+            &ctx.accounts.house,
+            player_state,
+            &player.to_account_info(),
+            chips,
+            &sessions,
+        )?;
+
+        msg!(
+            "Player {} cashed out of table {} after the hand with {} chips returned",
+            player.key(),
+            table.key(),
+            return_amount
+        );
+        Ok(())
+    }
+
+    /// Authorize `session_key` to post blinds, act and use the time bank for
+    /// the player until `expires_at` (unix time), putting at most
+    /// `spend_cap` chips in across all tables. Session keys can never sign
+    /// `leave_table` or anything else that moves funds out.
+    pub fn create_session(
+        ctx: Context<CreateSession>,
+        session_key: Pubkey,
+        expires_at: i64,
+        spend_cap: u64,
+    ) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        require!(
+            expires_at > now && expires_at - now <= MAX_SESSION_SECONDS,
+            ShadowPokerError::InvalidSession
+        );
+        require!(session_key != ctx.accounts.authority.key(), ShadowPokerError::InvalidSession);
+
+        let session = &mut ctx.accounts.session_token;
+        session.authority = ctx.accounts.authority.key();
+        session.session_key = session_key;
+        session.expires_at = expires_at;
+        session.spend_cap = spend_cap;
+        session.spent = 0;
+        session.bump = ctx.bumps.session_token;
+
+        msg!(
+            "Player {} authorized session key {} until {} with a cap of {}",
+            session.authority,
+            session_key,
+            expires_at,
+            spend_cap
+        );
+        Ok(())
+    }
+
+    /// End a session early and reclaim its rent (player only).
+    pub fn revoke_session(ctx: Context<RevokeSession>) -> Result<()> {
+        msg!(
+            "Player {} revoked session key {}",
+            ctx.accounts.authority.key(),
+            ctx.accounts.session_token.session_key
+        );
+        Ok(())
+    }
+
+    /// Sit out from the next hand onwards while keeping the seat.
+    pub fn sit_out(ctx: Context<SitOutOrIn>) -> Result<()> {
+        let player_state = &mut ctx.accounts.player_state;
+        player_state.is_sitting_out = true;
+
+        msg!("Player {} is sitting out", player_state.player);
+        Ok(())
+    }
+
+    /// Come back from sitting out; the player is dealt into the next hand.
+    pub fn sit_in(ctx: Context<SitOutOrIn>) -> Result<()> {
+        let player_state = &mut ctx.accounts.player_state;
+
+        require!(!player_state.leave_pending, ShadowPokerError::LeavePending);
+        require!(player_state.stack > 0, ShadowPokerError::InsufficientStack);
+
+        player_state.is_sitting_out = false;
+        player_state.timeout_strikes = 0;
+
+        msg!("Player {} is back in", player_state.player);
+        Ok(())
+    }
+
+    /// Rebuy or top up between hands. The resulting stack must stay within
+    /// the table's buy-in limits.
+    pub fn top_up(ctx: Context<TopUp>, amount: u64) -> Result<()> {
+        let table = &ctx.accounts.table;
+        let player_state = &mut ctx.accounts.player_state;
+        let player = &ctx.accounts.player;
+
+        require!(table.tournament.is_none(), ShadowPokerError::TournamentTable);
+
+        require!(
+            !(table.hand_running() && player_state.is_active),
+            ShadowPokerError::HandInProgress
+        );
+        require!(!player_state.leave_pending, ShadowPokerError::LeavePending);
+
+        let new_stack = player_state
+            .stack
+            .checked_add(amount)
+            .ok_or(ShadowPokerError::InvalidBuyIn)?;
+        require!(
+            amount > 0 && new_stack >= table.min_buy_in && new_stack <= table.max_buy_in,
+            ShadowPokerError::InvalidBuyIn
+        );
+
+        let chips = table_chips(
+            table,
+            &player.key(),
+            ctx.accounts.chip_mint.as_ref(),
+            ctx.accounts.player_tokens.as_ref(),
+            ctx.accounts.vault.as_ref(),
+            ctx.accounts.token_program.as_ref(),
+        )?;
+        if let Some(chips) = chips {
+            chips.deposit(player, amount)?;
+        } else {
+            anchor_lang::system_program::transfer(
+                CpiContext::new(
+                    ctx.accounts.system_program.to_account_info(),
+                    anchor_lang::system_program::Transfer {
+                        from: player.to_account_info(),
+                        to: table.to_account_info(),
+                    },
+                ),
+                amount,
+            )?;
+        }
+
+        player_state.stack = new_stack;
+
+        msg!(
+            "Player {} topped up {} chips, stack now {}",
+            player.key(),
+            amount,
+            new_stack
+        );
+        Ok(())
+    }
+
+    /// Restrict who can join a cash table (creator only, between hands).
+    /// Every configured check must pass: the password whose hash was
+    /// committed, membership of the allowlist, and holding the invite token.
+    /// Passing all `None`/empty opens the table to everyone again.
+    pub fn set_table_access(
+        ctx: Context<SetTableAccess>,
+        password_hash: Option<[u8; 32]>,
+        allowlist: Vec<Pubkey>,
+        invite_mint: Option<Pubkey>,
+    ) -> Result<()> {
+        let table = &mut ctx.accounts.table;
+        let access = &mut ctx.accounts.table_access;
+
+        require!(table.tournament.is_none(), ShadowPokerError::TournamentTable);
+        require!(
+            table.status == TableStatus::Waiting || table.status == TableStatus::Finished,
+            ShadowPokerError::HandInProgress
+        );
+        require!(
+            allowlist.len() <= MAX_ALLOWLIST,
+            ShadowPokerError::AllowlistTooLong
+        );
+
+        access.table = table.key();
+        access.password_hash = password_hash;
+        access.allowlist = allowlist;
+        access.invite_mint = invite_mint;
+        access.bump = ctx.bumps.table_access;
+
+        table.access_restricted = access.password_hash.is_some()
+            || !access.allowlist.is_empty()
+            || access.invite_mint.is_some();
+
+        msg!(
+            "Table {} access updated (restricted: {})",
+            table.key(),
+            table.access_restricted
+        );
+        Ok(())
+    }
+
+    /// Turn straddles, run it twice and all-in insurance on or off for a
+    /// cash table (creator only, between hands). Straddles aren't offered
+    /// under fixed limit, and insurance is paid in SOL so needs a SOL table.
+    pub fn set_table_options(ctx: Context<SetTableOptions>, options: TableOptions) -> Result<()> {
+        let table = &mut ctx.accounts.table;
+
+        require!(table.tournament.is_none(), ShadowPokerError::TournamentTable);
+        require!(
+            table.status == TableStatus::Waiting || table.status == TableStatus::Finished,
+            ShadowPokerError::HandInProgress
+        );
+        require!(
+            !options.straddle || !matches!(table.betting, BettingStructure::FixedLimit { .. }),
+            ShadowPokerError::StraddleNotAllowed
+        );
+        require!(
+            !options.insurance || table.chip_mint.is_none(),
+            ShadowPokerError::InsuranceNotAllowed
+        );
+
+        table.options = options;
+
+        msg!("Table {} options: {:?}", table.key(), options);
+        Ok(())
+    }
+
+    /// Remove a player before the first hand and refund their buy-in (creator only)
+    pub fn kick_player(ctx: Context<KickPlayer>) -> Result<()> {
+        let table = &mut ctx.accounts.table;
+        let player_state = &mut ctx.accounts.player_state;
+        let player = &ctx.accounts.player;
+
+        require!(table.tournament.is_none(), ShadowPokerError::TournamentTable);
+        require!(table.hand_number == 0, ShadowPokerError::GameAlreadyStarted);
+        require!(
+            table.status == TableStatus::Waiting,
+            ShadowPokerError::HandInProgress
+        );
+
+        let chips = table_chips(
+            table,
+            &player.key(),
+            ctx.accounts.chip_mint.as_ref(),
+            ctx.accounts.player_tokens.as_ref(),
+            ctx.accounts.vault.as_ref(),
+            ctx.accounts.token_program.as_ref(),
+        )?;
+        let sessions: Vec<AccountInfo> =
+            ctx.accounts.session_token.iter().map(|token| token.to_account_info()).collect();
+        let refund = cash_out(
+            table,
+            &ctx.accounts.house,
+            player_state,
+            &player.to_account_info(),
+            chips,
+            &sessions,
+        )?;
+
+        msg!(
+            "Player {} kicked from table {} with {} chips refunded",
+            player.key(),
+            table.key(),
+            refund
+        );
+        Ok(())
+    }
+
+    /// Withdraw treasury (admin only)
+    pub fn withdraw_treasury(ctx: Context<WithdrawTreasury>, amount: u64) -> Result<()> {
+        let house = &ctx.accounts.house;
+        
+        require!(
+            house.authority == ctx.accounts.authority.key(),
+            ShadowPokerError::UnauthorizedHouse
+        );
+        
+        require!(
+            amount <= house.treasury,
+            ShadowPokerError::InsufficientTreasury
+        );
+
+        **ctx.accounts.house.to_account_info().try_borrow_mut_lamports()? -= amount;
+        **ctx.accounts.authority.to_account_info().try_borrow_mut_lamports()? += amount;
+
+        msg!("Treasury withdrawal: {} lamports", amount);
+        Ok(())
+    }
+
+    /// Approve a dealer key that may run hands on any table (house authority only)
+    pub fn approve_dealer(ctx: Context<ApproveDealer>, dealer: Pubkey) -> Result<()> {
+        let approval = &mut ctx.accounts.dealer_approval;
+        approval.dealer = dealer;
+        approval.bump = ctx.bumps.dealer_approval;
+
+        msg!("Dealer approved: {}", dealer);
+        Ok(())
+    }
+
+    /// Revoke a dealer key (house authority only)
+    pub fn revoke_dealer(ctx: Context<RevokeDealer>) -> Result<()> {
+        msg!("Dealer revoked: {}", ctx.accounts.dealer_approval.dealer);
+        Ok(())
+    }
+
+    /// Configure a table's rake percentage, per-hand cap and rake-back (house authority only)
+    pub fn set_table_rake(
+        ctx: Context<SetTableRake>,
+        rake_bps: u16,
+        rake_cap: u64,
+        rakeback_bps: u16,
+    ) -> Result<()> {
+        require!(rake_bps <= MAX_RAKE_BPS, ShadowPokerError::InvalidRake);
+        require!(rakeback_bps <= 10000, ShadowPokerError::InvalidRake);
+
+        let table = &mut ctx.accounts.table;
+        table.rake_bps = rake_bps;
+        table.rake_cap = rake_cap;
+        table.rakeback_bps = rakeback_bps;
+
+        msg!(
+            "Table {} rake set to {} bps (cap {}), rake-back {} bps",
+            table.key(),
+            rake_bps,
+            rake_cap,
+            rakeback_bps
+        );
+        Ok(())
+    }
+
+    /// Claim accrued rake-back to the player's wallet
+    pub fn claim_rakeback(ctx: Context<ClaimRakeback>) -> Result<()> {
+        let player_state = &mut ctx.accounts.player_state;
+        let amount = player_state.rakeback_owed;
+        require!(amount > 0, ShadowPokerError::NothingToClaim);
+
+        player_state.rakeback_owed = 0;
+        let chips = table_chips(
+            &ctx.accounts.table,
+            &ctx.accounts.player.key(),
+            ctx.accounts.chip_mint.as_ref(),
+            ctx.accounts.player_tokens.as_ref(),
+            ctx.accounts.vault.as_ref(),
+            ctx.accounts.token_program.as_ref(),
+        )?;
+        if let Some(chips) = chips {
+            chips.withdraw(&ctx.accounts.table, &ctx.accounts.house, amount)?;
+        } else {
+            pay_rakeback(&ctx.accounts.house, &ctx.accounts.player, amount)?;
+        }
+
+        msg!("Player {} claimed {} of rake-back", player_state.player, amount);
+        Ok(())
+    }
+
+    /// Create the escrow vault of an SPL token table. Anyone can pay for
+    /// it; players can join once it exists.
+    pub fn create_table_vault(ctx: Context<CreateTableVault>) -> Result<()> {
+        let table = &mut ctx.accounts.table;
+
+        require!(
+            table.chip_mint == Some(ctx.accounts.chip_mint.key()) && !table.play_money,
+            ShadowPokerError::NotTokenTable
+        );
+        require_supported_mint(&ctx.accounts.chip_mint)?;
+        table.vault_bump = ctx.bumps.vault;
+
+        msg!("Vault {} created for table {}", ctx.accounts.vault.key(), table.key());
+        Ok(())
+    }
+
+    /// Move the rake held in a token table's vault to the house (admin only)
+    pub fn collect_table_rake(ctx: Context<CollectTableRake>) -> Result<()> {
+        let table = &mut ctx.accounts.table;
+        let amount = table.rake_held;
+
+        require!(
+            table.chip_mint == Some(ctx.accounts.chip_mint.key()) && !table.play_money,
+            ShadowPokerError::NotTokenTable
+        );
+        require!(amount > 0, ShadowPokerError::NothingToClaim);
+        table.rake_held = 0;
+
+        let table_key = table.key();
+        let seeds: &[&[u8]] = &[b"vault", table_key.as_ref(), &[table.vault_bump]];
+        token_interface::transfer_checked(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                token_interface::TransferChecked {
+                    from: ctx.accounts.vault.to_account_info(),
+                    mint: ctx.accounts.chip_mint.to_account_info(),
+                    to: ctx.accounts.destination.to_account_info(),
+                    authority: ctx.accounts.vault.to_account_info(),
+                },
+                &[seeds],
+            ),
+            amount,
+            ctx.accounts.chip_mint.decimals,
+        )?;
+
+        msg!("Collected {} in rake from table {}", amount, table_key);
+        Ok(())
+    }
+
+    /// Create the house's non-transferable play chip mint (admin only).
+    /// Play chips are burned on buy-in and minted back on cash-out, so they
+    /// never leave the player's token account.
+    pub fn init_play_chips(ctx: Context<InitPlayChips>) -> Result<()> {
+        let mint = &ctx.accounts.play_chip_mint;
+        let token_program = &ctx.accounts.token_program;
+        let space = token_interface::find_mint_account_size(Some(&vec![
+            token_interface::spl_token_2022::extension::ExtensionType::NonTransferable,
+        ]))?;
+
+        let seeds: &[&[u8]] = &[b"play_chips", &[ctx.bumps.play_chip_mint]];
+        anchor_lang::system_program::create_account(
+            CpiContext::new_with_signer(
+                ctx.accounts.system_program.to_account_info(),
+                anchor_lang::system_program::CreateAccount {
+                    from: ctx.accounts.authority.to_account_info(),
+                    to: mint.to_account_info(),
+                },
+                &[seeds],
+            ),
+            Rent::get()?.minimum_balance(space),
+            space as u64,
+            &token_program.key(),
+        )?;
+        token_interface::non_transferable_mint_initialize(CpiContext::new(
+            token_program.to_account_info(),
+            token_interface::NonTransferableMintInitialize {
+                token_program_id: token_program.to_account_info(),
+                mint: mint.to_account_info(),
+            },
+        ))?;
+        token_interface::initialize_mint2(
+            CpiContext::new(
+                token_program.to_account_info(),
+                token_interface::InitializeMint2 {
+                    mint: mint.to_account_info(),
+                },
+            ),
+            PLAY_CHIP_DECIMALS,
+            &ctx.accounts.house.key(),
+            None,
+        )?;
+
+        msg!("Play chip mint created: {}", mint.key());
+        Ok(())
+    }
+
+    /// Mint play chips to a player, e.g. for onboarding or freeroll
+    /// prizes (admin only)
+    pub fn grant_play_chips(ctx: Context<GrantPlayChips>, amount: u64) -> Result<()> {
+        require!(amount > 0, ShadowPokerError::InvalidBuyIn);
+
+        let house = &ctx.accounts.house;
+        let seeds: &[&[u8]] = &[b"shadow_poker_house", &[house.bump]];
+        token_interface::mint_to(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                token_interface::MintTo {
+                    mint: ctx.accounts.play_chip_mint.to_account_info(),
+                    to: ctx.accounts.player_tokens.to_account_info(),
+                    authority: house.to_account_info(),
+                },
+                &[seeds],
+            ),
+            amount,
+        )?;
+
+        msg!(
+            "Granted {} play chips to {}",
+            amount,
+            ctx.accounts.player_tokens.owner
+        );
+        Ok(())
+    }
+
+    /// Create the deck account of a mental poker table (creator only).
+    /// The card points are uploaded next with `load_card_points`.
+    pub fn init_deck(ctx: Context<InitDeck>) -> Result<()> {
+        let table = &ctx.accounts.table;
+        require!(
+            table.dealing_mode == DealingMode::MentalPoker,
+            ShadowPokerError::WrongDealingMode
+        );
+
+        let deck = &mut ctx.accounts.deck;
+        deck.table = table.key();
+        deck.variant = table.variant;
+        deck.ready = false;
+        deck.card_points = vec![];
+        deck.reset(0, vec![], 0);
+        deck.bump = ctx.bumps.deck;
+
+        msg!("Deck created for mental poker table {}", table.key());
+        Ok(())
+    }
+
+    /// Upload the unencrypted card points (`mental_poker::card_points`) in
+    /// chunks. Once all 52 are in they are checked against
+    /// `CARD_POINTS_HASH` and trimmed to the cards of the table's variant.
+    pub fn load_card_points(ctx: Context<LoadCardPoints>, points: Vec<[u8; 32]>) -> Result<()> {
+        let deck = &mut ctx.accounts.deck;
+
+        require!(!deck.ready, ShadowPokerError::DeckAlreadyLoaded);
+        require!(
+            deck.card_points.len() + points.len() <= mental_poker::FULL_DECK,
+            ShadowPokerError::InvalidCardCount
+        );
+        deck.card_points.extend(points);
+
+        if deck.card_points.len() == mental_poker::FULL_DECK {
+            let mut hasher = blake3::Hasher::new();
+            for point in deck.card_points.iter() {
+                hasher.update(point);
+            }
+            require!(
+                *hasher.finalize().as_bytes() == mental_poker::CARD_POINTS_HASH,
+                ShadowPokerError::InvalidCardPoints
+            );
+
+            let variant = deck.variant;
+            let mut index = 0;
+            deck.card_points.retain(|_| {
+                index += 1;
+                mental_poker::card_at(index - 1).is_in_deck(variant)
+            });
+            deck.ready = true;
+            msg!("Deck for table {} is ready", deck.table);
+        }
+        Ok(())
+    }
+
+    /// Submit this player's turn of the shuffle: the previous deck
+    /// encrypted with their key and permuted, in chunks. The public key
+    /// and shuffle commitment must be the same on every chunk.
+    pub fn submit_shuffle(
+        ctx: Context<SubmitShuffle>,
+        public_key: [u8; 32],
+        commitment: [u8; 32],
+        points: Vec<[u8; 32]>,
+    ) -> Result<()> {
+        let table = &ctx.accounts.table;
+        let deck = &mut ctx.accounts.deck;
+        let seat = ctx.accounts.player_state.seat_index;
+
+        require!(
+            table.status == TableStatus::Shuffling,
+            ShadowPokerError::InvalidGameState
+        );
+        let index = deck.stages.len();
+        require!(
+            deck.shufflers.get(index) == Some(&seat),
+            ShadowPokerError::NotYourShuffle
+        );
+
+        if deck.public_keys.len() == index {
+            require!(
+                mental_poker::is_valid_point(&public_key),
+                ShadowPokerError::InvalidShuffle
+            );
+            deck.public_keys.push(public_key);
+            deck.commitments.push(commitment);
+        }
+        require!(
+            deck.public_keys[index] == public_key && deck.commitments[index] == commitment,
+            ShadowPokerError::InvalidShuffle
+        );
+        require!(
+            deck.pending.len() + points.len() <= deck.card_points.len(),
+            ShadowPokerError::InvalidShuffle
+        );
+        for point in points {
+            require!(
+                mental_poker::is_valid_point(&point),
+                ShadowPokerError::InvalidShuffle
+            );
+            deck.pending.push(point);
+        }
+
+        if deck.pending.len() == deck.card_points.len() {
+            let stage = std::mem::take(&mut deck.pending);
+            deck.stages.push(stage);
+            deck.deadline_slot = Clock::get()?.slot + TIMEOUT_SLOTS;
+
+            // The last shuffle opens the hole cards for decryption
+            if deck.stages.len() == deck.shufflers.len() {
+                deck.cards = deck.stages[index].clone();
+                deck.share_masks = vec![0; deck.cards.len()];
+                deck.requested = deck.hole_card_count() as u8;
+            }
+            msg!("Seat {} finished shuffling ({}/{})", seat, deck.stages.len(), deck.shufflers.len());
+        }
+        Ok(())
+    }
+
+    /// Submit decryption shares for cards open for decryption: every hole
+    /// card except the player's own, and board cards once requested.
+    /// Betting starts when all hole-card shares are in.
+    pub fn submit_shares(ctx: Context<SubmitShares>, shares: Vec<DecryptionShare>) -> Result<()> {
+        let table = &mut ctx.accounts.table;
+        let deck = &mut ctx.accounts.deck;
+        let seat = ctx.accounts.player_state.seat_index;
+
+        require!(table.hand_running(), ShadowPokerError::InvalidGameState);
+        require!(
+            deck.stages.len() == deck.shufflers.len(),
+            ShadowPokerError::DeckNotReady
+        );
+        let index = deck.shuffler_index(seat).ok_or(ShadowPokerError::PlayerNotInHand)?;
+        let public_key = deck.public_keys[index];
+
+        for share in shares.iter() {
+            let position = share.position as usize;
+            require!(
+                position < deck.requested as usize,
+                ShadowPokerError::ShareNotRequested
+            );
+            require!(
+                deck.share_masks[position] & (1 << seat) == 0,
+                ShadowPokerError::InvalidShare
+            );
+            require!(
+                mental_poker::verify_share(
+                    &public_key,
+                    &deck.cards[position],
+                    &share.point,
+                    &share.challenge,
+                    &share.response,
+                ),
+                ShadowPokerError::InvalidShare
+            );
+            deck.cards[position] = share.point;
+            deck.share_masks[position] |= 1 << seat;
+        }
+
+        if table.status == TableStatus::Shuffling && deck.withholding() == 0 {
+            table.status = TableStatus::Dealing;
+            table.action_deadline_slot = Clock::get()?.slot + TIMEOUT_SLOTS;
+            msg!("Hole cards dealt on table {}", table.key());
+        }
+        Ok(())
+    }
+
+    /// Permissionless crank: open the next board cards for decryption once
+    /// the betting round is over.
+    ///
+    /// Every seated player's `PlayerState` must be passed writable in
+    /// `remaining_accounts`, in seat order.
+    pub fn request_board<'info>(ctx: Context<'_, '_, 'info, 'info, RequestBoard<'info>>) -> Result<()> {
+        let table = &ctx.accounts.table;
+        let deck = &mut ctx.accounts.deck;
+
+        require!(
+            (table.status == TableStatus::Dealing || table.status == TableStatus::Betting)
+                && table.active_mask.count_ones() > 1,
+            ShadowPokerError::InvalidGameState
+        );
+        let board_len = table.community_cards.len();
+        let count = match (board_len, table.run_twice_from) {
+            (0, _) => 3,
+            (3 | 4, _) => 1,
+            (5, Some(shared)) if table.second_board.is_empty() => 5 - shared,
+            _ => return Err(ShadowPokerError::InvalidGameState.into()),
+        };
+        require!(
+            deck.requested as usize == deck.board_position(board_len),
+            ShadowPokerError::InvalidGameState
+        );
+
+        let states = load_player_states(table, ctx.remaining_accounts)?;
+        require!(table.round_complete(&states), ShadowPokerError::RoundNotComplete);
+
+        deck.requested += count;
+        deck.deadline_slot = Clock::get()?.slot + TIMEOUT_SLOTS;
+
+        msg!("Table {}: {} board cards open for decryption", table.key(), count);
+        Ok(())
+    }
+
+    /// Open this player's shuffle commitment so the shuffle can be checked.
+    /// Every shuffle must be opened before a showdown is settled; a shuffle
+    /// that doesn't check out marks the player as a cheat (see `abort_hand`).
+    pub fn verify_shuffle(
+        ctx: Context<VerifyShuffle>,
+        key: [u8; 32],
+        permutation: Vec<u8>,
+    ) -> Result<()> {
+        let table = &ctx.accounts.table;
+        let deck = &mut ctx.accounts.deck;
+        let seat = ctx.accounts.player_state.seat_index;
+
+        require!(table.hand_running(), ShadowPokerError::InvalidGameState);
+        let index = deck.shuffler_index(seat).ok_or(ShadowPokerError::PlayerNotInHand)?;
+        require!(index < deck.stages.len(), ShadowPokerError::DeckNotReady);
+        require!(
+            (deck.verified_mask | deck.cheated_mask) & (1 << seat) == 0,
+            ShadowPokerError::InvalidShuffleOpening
+        );
+        require!(
+            mental_poker::shuffle_commitment(&key, &permutation) == deck.commitments[index]
+                && mental_poker::multiply(&key, &mental_poker::BASEPOINT)
+                    == Some(deck.public_keys[index]),
+            ShadowPokerError::InvalidShuffleOpening
+        );
+
+        let input = match index {
+            0 => &deck.card_points,
+            _ => &deck.stages[index - 1],
+        };
+        if mental_poker::verify_shuffle(&key, &permutation, input, &deck.stages[index]) {
+            deck.verified_mask |= 1 << seat;
+            msg!("Seat {} shuffle verified", seat);
+        } else {
+            deck.cheated_mask |= 1 << seat;
+            msg!("Seat {} shuffle failed verification", seat);
+        }
+        Ok(())
+    }
+
+    /// Permissionless crank: call off a mental poker hand that can't be
+    /// finished because a player cheated their shuffle, or let the deadline
+    /// pass without shuffling, submitting a decryption share, or opening
+    /// their shuffle at showdown. Everyone gets back what they put in the
+    /// pot, and each offender pays `MENTAL_POKER_PENALTY_BBS` big blinds
+    /// (or their stack) to the other players and is sat out.
+    ///
+    /// Every seated player's `PlayerState` must be passed writable in
+    /// `remaining_accounts`, in seat order.
+    pub fn abort_hand<'info>(ctx: Context<'_, '_, 'info, 'info, AbortHand<'info>>) -> Result<()> {
+        let table = &mut ctx.accounts.table;
+        let deck = &ctx.accounts.deck;
+        let slot = Clock::get()?.slot;
+
+        require!(table.hand_running(), ShadowPokerError::InvalidGameState);
+        let mut states = load_player_states(table, ctx.remaining_accounts)?;
+
+        let mut offenders = deck.cheated_mask;
+        if offenders == 0 && slot > deck.deadline_slot {
+            offenders = deck.withholding();
+        }
+        let at_showdown = table.active_mask.count_ones() > 1
+            && table.community_cards.len() == 5
+            && table.round_complete(&states);
+        if offenders == 0 && at_showdown && slot > table.action_deadline_slot {
+            offenders = deck.shuffler_mask() & !deck.verified_mask;
+        }
+        require!(offenders != 0, ShadowPokerError::NoKeyWithheld);
+
+        // Call the hand off: every contribution and insurance premium goes back
+        let house = &mut ctx.accounts.house;
+        for state in states.iter_mut() {
+            state.stack += state.hand_contribution;
+            state.hand_contribution = 0;
+            state.is_active = false;
+
+            if let Some(insurance) = state.insurance.take().filter(|i| i.accepted) {
+                house.treasury += insurance.coverage - insurance.premium;
+                **house.to_account_info().try_borrow_mut_lamports()? -= insurance.premium;
+                **table.to_account_info().try_borrow_mut_lamports()? += insurance.premium;
+                state.stack += insurance.premium;
+            }
+        }
+
+        // Offenders compensate everyone else who was dealt in
+        let receivers = deck.shuffler_mask() & !offenders;
+        let mut penalties: u64 = 0;
+        if receivers != 0 {
+            for state in states.iter_mut().filter(|s| offenders & (1 << s.seat_index) != 0) {
+                let penalty = state.stack.min(table.big_blind * MENTAL_POKER_PENALTY_BBS);
+                state.stack -= penalty;
+                penalties += penalty;
+            }
+            let each = penalties / receivers.count_ones() as u64;
+            let mut remainder = penalties % receivers.count_ones() as u64;
+            for state in states.iter_mut().filter(|s| receivers & (1 << s.seat_index) != 0) {
+                state.stack += each + remainder;
+                remainder = 0;
+            }
+        }
+        for state in states.iter_mut().filter(|s| offenders & (1 << s.seat_index) != 0) {
+            state.is_sitting_out = true;
+        }
+
+        let table_key = table.key();
+        let event = HandAborted {
+            table: table_key,
+            hand_number: table.hand_number,
+            seq: table.event_seq,
+            offenders,
+            penalties,
+        };
+        record_event(table, event);
+        table.last_hand_hash = table.history_hash;
+        emit!(HandCompleted {
+            table: table_key,
+            hand_number: table.hand_number,
+            events: table.event_seq,
+            history_hash: table.history_hash,
+        });
+
+        table.pot = 0;
+        table.active_mask = 0;
+        table.all_in_mask = 0;
+        table.status = TableStatus::Finished;
+
+        for state in states.iter() {
+            state.exit(&crate::ID)?;
+        }
+
+        msg!(
+            "Hand {} on table {} aborted; offenders {:#b} paid {} in penalties",
+            table.hand_number,
+            table_key,
+            offenders,
+            penalties
+        );
+        Ok(())
+    }
+
+    /// Move a running hand to a MagicBlock ephemeral rollup (table
+    /// operator only): the table and every seated player's `PlayerState`
+    /// are delegated, and the rollup commits them back every
+    /// `commit_frequency_ms`. Betting then runs on the rollup until
+    /// `undelegate_table`; anything that moves funds waits for the base layer.
+    ///
+    /// Cash tables dealt by an operator only. `table_index` is the
+    /// `total_tables` count the table was created with. `remaining_accounts`
+    /// holds every seated player's `PlayerState` in seat order, then the
+    /// buffer, delegation record and delegation metadata for each in turn.
+    pub fn delegate_table<'info>(
+        ctx: Context<'_, '_, 'info, 'info, DelegateTable<'info>>,
+        table_index: u64,
+        commit_frequency_ms: u32,
+    ) -> Result<()> {
+        let table = &ctx.accounts.table;
+
+        require_table_operator(
+            table,
+            &ctx.accounts.house,
+            &ctx.accounts.authority.key(),
+            ctx.accounts.dealer_approval.as_ref(),
+        )?;
+        require!(
+            table.tournament.is_none() && table.dealing_mode == DealingMode::Dealer,
+            ShadowPokerError::DelegationNotAllowed
+        );
+        require!(
+            table.status == TableStatus::Dealing || table.status == TableStatus::Betting,
+            ShadowPokerError::InvalidGameState
+        );
+        require!(commit_frequency_ms > 0, ShadowPokerError::DelegationNotAllowed);
+
+        let index = table_index.to_le_bytes();
+        let table_seeds: [&[u8]; 2] = [b"table", &index];
+        require_keys_eq!(
+            Pubkey::create_program_address(&[b"table", &index, &[table.bump]], &crate::ID)
+                .map_err(|_| error!(ShadowPokerError::WrongTableSeeds))?,
+            table.key(),
+            ShadowPokerError::WrongTableSeeds
+        );
+
+        let seated = table.seated_count();
+        require!(
+            ctx.remaining_accounts.len() == seated * 4,
+            ShadowPokerError::PlayerStatesMismatch
+        );
+        let (state_infos, records) = ctx.remaining_accounts.split_at(seated);
+        let states = load_player_states(table, state_infos)?;
+
+        let delegation = Delegation {
+            payer: &ctx.accounts.authority.to_account_info(),
+            owner_program: &ctx.accounts.owner_program.to_account_info(),
+            delegation_program: &ctx.accounts.delegation_program.to_account_info(),
+            system_program: &ctx.accounts.system_program.to_account_info(),
+            commit_frequency_ms,
+        };
+        delegation.delegate(
+            &table.to_account_info(),
+            &table_seeds,
+            table.bump,
+            DelegationRecord {
+                buffer: &ctx.accounts.buffer.to_account_info(),
+                record: &ctx.accounts.delegation_record.to_account_info(),
+                metadata: &ctx.accounts.delegation_metadata.to_account_info(),
+            },
+        )?;
+        let table_key = table.key();
+        for (state, record) in states.iter().zip(records.chunks(3)) {
+            delegation.delegate(
+                &state.to_account_info(),
+                &[b"player_state", table_key.as_ref(), state.player.as_ref()],
+                state.bump,
+                DelegationRecord {
+                    buffer: &record[0],
+                    record: &record[1],
+                    metadata: &record[2],
+                },
+            )?;
+        }
+
+        msg!(
+            "Table {} hand {} delegated with {} players, committing every {}ms",
+            table_key,
+            table.hand_number,
+            seated,
+            commit_frequency_ms
+        );
+        Ok(())
+    }
+
+    /// Permissionless, on the rollup: commit the table and every seated
+    /// player's `PlayerState` (in `remaining_accounts`, seat order) to the
+    /// base layer without ending the delegation.
+    pub fn commit_table<'info>(ctx: Context<'_, '_, 'info, 'info, CommitTable<'info>>) -> Result<()> {
+        let table = &ctx.accounts.table;
+        load_player_states(table, ctx.remaining_accounts)?;
+
+        let mut accounts = vec![table.to_account_info()];
+        accounts.extend(ctx.remaining_accounts.iter().cloned());
+        ephemeral_rollup::schedule_commit(
+            &ctx.accounts.payer.to_account_info(),
+            &ctx.accounts.magic_context.to_account_info(),
+            &ctx.accounts.magic_program.to_account_info(),
+            &accounts,
+            false,
+        )?;
+
+        msg!("Table {} committed at hand {}", table.key(), table.hand_number);
+        Ok(())
+    }
+
+    /// On the rollup: commit the table and its players' states and return
+    /// them to the base layer. Table operators can do this at any time;
+    /// anyone can once betting is over or the acting player's deadline has
+    /// passed, so a hand can always be settled.
+    ///
+    /// Every seated player's `PlayerState` must be passed in
+    /// `remaining_accounts`, in seat order.
+    pub fn undelegate_table<'info>(
+        ctx: Context<'_, '_, 'info, 'info, UndelegateTable<'info>>,
+    ) -> Result<()> {
+        let table = &ctx.accounts.table;
+        let states = load_player_states(table, ctx.remaining_accounts)?;
+
+        let betting_over = !table.hand_running()
+            || table.active_mask.count_ones() <= 1
+            || (table.community_cards.len() == 5 && table.round_complete(&states));
+        let timed_out = Clock::get()?.slot > table.action_deadline_slot;
+        if !betting_over && !timed_out {
+            require_table_operator(
+                table,
+                &ctx.accounts.house,
+                &ctx.accounts.payer.key(),
+                ctx.accounts.dealer_approval.as_ref(),
+            )?;
+        }
+
+        let mut accounts = vec![table.to_account_info()];
+        accounts.extend(ctx.remaining_accounts.iter().cloned());
+        ephemeral_rollup::schedule_commit(
+            &ctx.accounts.payer.to_account_info(),
+            &ctx.accounts.magic_context.to_account_info(),
+            &ctx.accounts.magic_program.to_account_info(),
+            &accounts,
+            true,
+        )?;
+
+        msg!("Table {} undelegated at hand {}", table.key(), table.hand_number);
+        Ok(())
+    }
+
+    /// Called by the delegation program to hand an undelegated `Table` or
+    /// `PlayerState` back, recreating it from the committed state.
+    #[instruction(discriminator = &UNDELEGATE_DISCRIMINATOR)]
+    pub fn process_undelegation(
+        ctx: Context<ProcessUndelegation>,
+        account_seeds: Vec<Vec<u8>>,
+    ) -> Result<()> {
+        let buffer = ctx.accounts.buffer.to_account_info();
+        {
+            let data = buffer.try_borrow_data()?;
+            require!(
+                data.starts_with(Table::DISCRIMINATOR) || data.starts_with(PlayerState::DISCRIMINATOR),
+                ErrorCode::AccountDiscriminatorMismatch
+            );
+        }
+        ephemeral_rollup::restore_undelegated(
+            &ctx.accounts.base_account.to_account_info(),
+            &buffer,
+            &ctx.accounts.payer.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+            &account_seeds,
+        )
+    }
+
+    /// Create a tournament with a fixed entry fee, blind schedule and payout structure
+    #[allow(clippy::too_many_arguments)]
+    pub fn create_tournament(
+        ctx: Context<CreateTournament>,
+        tournament_id: u64,
+        entry_fee: u64,
+        starting_stack: u64,
+        max_entrants: u16,
+        level_duration_slots: u64,
+        blind_levels: Vec<BlindLevel>,
+        payout_bps: Vec<u16>,
+        variant: GameVariant,
+        betting: BettingStructure,
+    ) -> Result<()> {
+        require_valid_game(variant, betting)?;
+        require!(entry_fee >= MIN_BUY_IN, ShadowPokerError::BuyInTooSmall);
+        require!(entry_fee <= MAX_BUY_IN, ShadowPokerError::BuyInTooLarge);
+        require!(
+            max_entrants >= MIN_PLAYERS as u16
+                && max_entrants <= MAX_TOURNAMENT_TABLES as u16 * MAX_PLAYERS as u16,
+            ShadowPokerError::InvalidPlayerCount
+        );
+        require!(level_duration_slots > 0, ShadowPokerError::InvalidBlindSchedule);
+        require!(
+            !blind_levels.is_empty() && blind_levels.len() <= MAX_BLIND_LEVELS,
+            ShadowPokerError::InvalidBlindSchedule
+        );
+        for level in blind_levels.iter() {
+            require!(
+                level.small_blind > 0
+                    && level.small_blind <= level.big_blind
+                    && level.ante <= level.small_blind,
+                ShadowPokerError::InvalidBlinds
+            );
+        }
+        require!(
+            blind_levels.windows(2).all(|w| w[0].big_blind <= w[1].big_blind),
+            ShadowPokerError::InvalidBlindSchedule
+        );
+        require!(
+            starting_stack >= blind_levels[0].big_blind,
+            ShadowPokerError::InvalidBlindSchedule
+        );
+        require!(
+            !payout_bps.is_empty()
+                && payout_bps.len() <= MAX_PAYOUT_PLACES
+                && payout_bps.len() <= max_entrants as usize,
+            ShadowPokerError::InvalidPayouts
+        );
+        require!(
+            payout_bps.iter().map(|bps| *bps as u32).sum::<u32>() == 10_000,
+            ShadowPokerError::InvalidPayouts
+        );
+
+        let tournament = &mut ctx.accounts.tournament;
+        tournament.creator = ctx.accounts.creator.key();
+        tournament.tournament_id = tournament_id;
+        tournament.entry_fee = entry_fee;
+        tournament.starting_stack = starting_stack;
+        tournament.max_entrants = max_entrants;
+        tournament.variant = variant;
+        tournament.betting = betting;
+        tournament.entrants = 0;
+        tournament.players_remaining = 0;
+        tournament.prize_pool = 0;
+        tournament.house_fee = 0;
+        tournament.status = TournamentStatus::Registering;
+        tournament.level_duration_slots = level_duration_slots;
+        tournament.blind_levels = blind_levels;
+        tournament.current_level = 0;
+        tournament.started_at_slot = 0;
+        tournament.payout_bps = payout_bps;
+        tournament.tables = vec![];
+        tournament.table_size = 0;
+        tournament.bump = ctx.bumps.tournament;
+
+        msg!(
+            "Tournament {} created: {} entry, up to {} entrants",
+            tournament.key(),
+            entry_fee,
+            max_entrants
+        );
+        Ok(())
+    }
+
+    /// Register for a tournament by paying the entry fee into its escrow
+    pub fn register_tournament(ctx: Context<RegisterTournament>) -> Result<()> {
+        let tournament = &mut ctx.accounts.tournament;
+        let entry = &mut ctx.accounts.entry;
+        let player = &ctx.accounts.player;
+
+        require!(
+            tournament.status == TournamentStatus::Registering,
+            ShadowPokerError::RegistrationClosed
+        );
+        require!(
+            tournament.entrants < tournament.max_entrants,
+            ShadowPokerError::TournamentFull
+        );
+
+        entry.tournament = tournament.key();
+        entry.player = player.key();
+        entry.entry_index = tournament.entrants;
+        entry.table = Pubkey::default();
+        entry.finish_position = 0;
+        entry.claimed = false;
+        entry.bump = ctx.bumps.entry;
+
+        tournament.entrants += 1;
+
+        anchor_lang::system_program::transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                anchor_lang::system_program::Transfer {
+                    from: player.to_account_info(),
+                    to: tournament.to_account_info(),
+                },
+            ),
+            tournament.entry_fee,
+        )?;
+
+        msg!(
+            "Player {} registered for tournament {} ({}/{})",
+            player.key(),
+            tournament.key(),
+            tournament.entrants,
+            tournament.max_entrants
+        );
+        Ok(())
+    }
+
+    /// Close registration and fix the prize pool. The creator can start at
+    /// any time; anyone can start a sit-and-go once it is full.
+    pub fn start_tournament(ctx: Context<StartTournament>) -> Result<()> {
+        let tournament = &mut ctx.accounts.tournament;
+        let house = &mut ctx.accounts.house;
+        let clock = Clock::get()?;
+
+        require!(
+            tournament.status == TournamentStatus::Registering,
+            ShadowPokerError::RegistrationClosed
+        );
+        require!(
+            ctx.accounts.authority.key() == tournament.creator
+                || tournament.entrants == tournament.max_entrants,
+            ShadowPokerError::NotTournamentCreator
+        );
+        require!(
+            tournament.entrants >= MIN_PLAYERS as u16,
+            ShadowPokerError::NotEnoughPlayers
+        );
+        require!(
+            tournament.payout_bps.len() <= tournament.entrants as usize,
+            ShadowPokerError::InvalidPayouts
+        );
+        require!(!tournament.tables.is_empty(), ShadowPokerError::NoTournamentTables);
+        require!(
+            tournament.tables.len() * tournament.table_size as usize >= tournament.entrants as usize,
+            ShadowPokerError::NoTournamentTables
+        );
+
+        let total_entries = tournament.entry_fee * tournament.entrants as u64;
+        let house_fee = (total_entries * HOUSE_FEE_BPS as u64) / 10000;
+
+        tournament.house_fee = house_fee;
+        tournament.prize_pool = total_entries - house_fee;
+        tournament.players_remaining = tournament.entrants;
+        tournament.status = TournamentStatus::Running;
+        tournament.started_at_slot = clock.slot;
+        tournament.current_level = 0;
+
+        house.treasury += house_fee;
+        house.total_volume += total_entries;
+
+        **tournament.to_account_info().try_borrow_mut_lamports()? -= house_fee;
+        **house.to_account_info().try_borrow_mut_lamports()? += house_fee;
+
+        msg!(
+            "Tournament {} started with {} entrants, prize pool {}",
+            tournament.key(),
+            tournament.entrants,
+            tournament.prize_pool
+        );
+        Ok(())
+    }
+
+    /// Add a table to a tournament (creator only, before it starts)
+    pub fn create_tournament_table(ctx: Context<CreateTournamentTable>, max_players: u8) -> Result<()> {
+        require!(
+            (MIN_PLAYERS..=MAX_PLAYERS).contains(&max_players),
+            ShadowPokerError::InvalidPlayerCount
+        );
+
+        let tournament = &mut ctx.accounts.tournament;
+        let table = &mut ctx.accounts.table;
+        let clock = Clock::get()?;
+
+        require!(
+            tournament.status == TournamentStatus::Registering,
+            ShadowPokerError::RegistrationClosed
+        );
+        require!(
+            tournament.tables.len() < MAX_TOURNAMENT_TABLES,
+            ShadowPokerError::TooManyTables
+        );
+        // Players are dealt round-robin, so every table has the same seats
+        require!(
+            tournament.table_size == 0 || tournament.table_size == max_players,
+            ShadowPokerError::InvalidPlayerCount
+        );
+        tournament.table_size = max_players;
+
+        let level = tournament.blind_levels[0];
+        table.open(tournament.creator, max_players, clock.slot, ctx.bumps.table);
+        table.min_buy_in = tournament.starting_stack;
+        table.max_buy_in = tournament.starting_stack;
+        table.small_blind = level.small_blind;
+        table.big_blind = level.big_blind;
+        table.ante = level.ante;
+        table.variant = tournament.variant;
+        table.betting = tournament.betting;
+        table.dealing_mode = DealingMode::Dealer;
+        table.tournament = Some(tournament.key());
+        table.access_restricted = false;
+
+        tournament.tables.push(table.key());
+
+        msg!(
+            "Tournament {} table {} created with {} seats",
+            tournament.key(),
+            table.key(),
+            max_players
+        );
+        Ok(())
+    }
+
+    /// Permissionless crank: seat a registered player with the starting
+    /// stack. Entrants are spread round-robin across the tournament tables.
+    pub fn seat_tournament_player(ctx: Context<SeatTournamentPlayer>) -> Result<()> {
+        let tournament = &ctx.accounts.tournament;
+        let entry = &mut ctx.accounts.entry;
+        let table = &mut ctx.accounts.table;
+        let player_state = &mut ctx.accounts.player_state;
+
+        require!(
+            tournament.status == TournamentStatus::Running,
+            ShadowPokerError::TournamentNotRunning
+        );
+        require!(
+            entry.table == Pubkey::default() && entry.finish_position == 0,
+            ShadowPokerError::AlreadyAtTable
+        );
+        let assigned = tournament.tables[entry.entry_index as usize % tournament.tables.len()];
+        require_keys_eq!(table.key(), assigned, ShadowPokerError::WrongTournamentTable);
+
+        let seat = table
+            .players
+            .iter()
+            .position(|p| *p == Pubkey::default())
+            .ok_or(ShadowPokerError::TableFull)?;
+        table.players[seat] = entry.player;
+
+        seat_player(player_state, entry.player, table.key(), seat as u8, tournament.starting_stack);
+        player_state.bump = ctx.bumps.player_state;
+        entry.table = table.key();
+
+        msg!(
+            "Player {} seated at tournament table {} seat {}",
+            entry.player,
+            table.key(),
+            seat
+        );
+        Ok(())
+    }
+
+    /// Permissionless crank: bring a tournament table's blinds up to the
+    /// level scheduled for the current slot. Applies between hands only.
+    pub fn sync_blind_level(ctx: Context<SyncBlindLevel>) -> Result<()> {
+        let tournament = &mut ctx.accounts.tournament;
+        let table = &mut ctx.accounts.table;
+        let clock = Clock::get()?;
+
+        require!(
+            tournament.status == TournamentStatus::Running,
+            ShadowPokerError::TournamentNotRunning
+        );
+        require!(
+            table.status == TableStatus::Waiting || table.status == TableStatus::Finished,
+            ShadowPokerError::HandInProgress
+        );
+
+        let elapsed = clock.slot.saturating_sub(tournament.started_at_slot);
+        let level_index = ((elapsed / tournament.level_duration_slots) as usize)
+            .min(tournament.blind_levels.len() - 1);
+        let level = tournament.blind_levels[level_index];
+
+        tournament.current_level = level_index as u8;
+        table.small_blind = level.small_blind;
+        table.big_blind = level.big_blind;
+        table.ante = level.ante;
+
+        msg!(
+            "Table {} at blind level {}: {}/{} ante {}",
+            table.key(),
+            level_index + 1,
+            level.small_blind,
+            level.big_blind,
+            level.ante
+        );
+        Ok(())
+    }
+
+    /// Permissionless crank: move a player between two tournament tables
+    /// to even them out, or to break a table into another one.
+    ///
+    /// Tables that fit on one table together are only ever merged, from
+    /// the smaller into the larger, so players never move back and forth.
+    /// Other tables are only evened out when two or more players apart.
+    pub fn balance_tables(ctx: Context<BalanceTables>) -> Result<()> {
+        let from_table = &mut ctx.accounts.from_table;
+        let to_table = &mut ctx.accounts.to_table;
+        let from_state = &ctx.accounts.from_state;
+        let to_state = &mut ctx.accounts.to_state;
+        let entry = &mut ctx.accounts.entry;
+
+        require!(
+            ctx.accounts.tournament.status == TournamentStatus::Running,
+            ShadowPokerError::TournamentNotRunning
+        );
+        require_keys_neq!(from_table.key(), to_table.key(), ShadowPokerError::WrongTournamentTable);
+        for table in [&from_table, &to_table] {
+            require!(
+                table.status == TableStatus::Waiting || table.status == TableStatus::Finished,
+                ShadowPokerError::HandInProgress
+            );
+        }
+
+        let from_count = from_table.seated_count();
+        let to_count = to_table.seated_count();
+        let mergeable = from_count + to_count <= to_table.max_players as usize;
+        require!(
+            match mergeable {
+                true => from_count <= to_count,
+                false => from_count >= to_count + 2,
+            },
+            ShadowPokerError::TablesBalanced
+        );
+
+        let seat = to_table
+            .players
+            .iter()
+            .position(|p| *p == Pubkey::default())
+            .ok_or(ShadowPokerError::TableFull)?;
+
+        from_table.players[from_state.seat_index as usize] = Pubkey::default();
+        to_table.players[seat] = from_state.player;
+
+        seat_player(to_state, from_state.player, to_table.key(), seat as u8, from_state.stack);
+        to_state.needs_big_blind = from_state.needs_big_blind;
+        to_state.bump = ctx.bumps.to_state;
+        entry.table = to_table.key();
+
+        msg!(
+            "Player {} moved from table {} to table {} seat {}",
+            from_state.player,
+            from_table.key(),
+            to_table.key(),
+            seat
+        );
+        Ok(())
+    }
+
+    /// Permissionless crank: knock out a busted player, recording their
+    /// finishing position and freeing the seat.
+    ///
+    /// Players busted in the same hand finish in order of the stacks they
+    /// started it with, so the one who started with least must go first.
+    /// Every seated player's `PlayerState` must be passed in
+    /// `remaining_accounts`, in seat order.
+    pub fn eliminate_player<'info>(
+        ctx: Context<'_, '_, 'info, 'info, EliminatePlayer<'info>>,
+    ) -> Result<()> {
+        let tournament = &mut ctx.accounts.tournament;
+        let table = &mut ctx.accounts.table;
+        let player_state = &ctx.accounts.player_state;
+        let entry = &mut ctx.accounts.entry;
+
+        require!(
+            tournament.status == TournamentStatus::Running,
+            ShadowPokerError::TournamentNotRunning
+        );
+        require!(
+            table.status == TableStatus::Waiting || table.status == TableStatus::Finished,
+            ShadowPokerError::HandInProgress
+        );
+        require!(
+            player_state.stack == 0 && player_state.posted_blind == 0,
+            ShadowPokerError::PlayerNotBusted
+        );
+
+        // A busted player's contribution is what they started their last
+        // hand with; anyone who busted in an earlier hand has had theirs
+        // reset to zero by the hands since
+        let states = load_player_states(table, ctx.remaining_accounts)?;
+        require!(
+            states
+                .iter()
+                .filter(|s| s.stack == 0 && s.posted_blind == 0)
+                .all(|s| s.hand_contribution >= player_state.hand_contribution),
+            ShadowPokerError::EliminationOutOfOrder
+        );
+
+        table.players[player_state.seat_index as usize] = Pubkey::default();
+
+        entry.finish_position = tournament.players_remaining;
+        entry.table = Pubkey::default();
+        tournament.players_remaining -= 1;
+
+        if tournament.players_remaining == 1 {
+            tournament.status = TournamentStatus::Finished;
+        }
+
+        msg!(
+            "Player {} eliminated from tournament {} in position {}",
+            entry.player,
+            tournament.key(),
+            entry.finish_position
+        );
+        Ok(())
+    }
+
+    /// Claim a tournament prize once it has finished. The winner receives
+    /// any rounding remainder of the prize pool.
+    pub fn claim_tournament_prize(ctx: Context<ClaimTournamentPrize>) -> Result<()> {
+        let tournament = &ctx.accounts.tournament;
+        let entry = &mut ctx.accounts.entry;
+        let player = &ctx.accounts.player;
+
+        require!(
+            tournament.status == TournamentStatus::Finished,
+            ShadowPokerError::TournamentNotFinished
+        );
+        require!(!entry.claimed, ShadowPokerError::PrizeAlreadyClaimed);
+
+        // The last player standing was never eliminated
+        let position = if entry.finish_position == 0 { 1 } else { entry.finish_position };
+        let prize = tournament.prize_for(position);
+        require!(prize > 0, ShadowPokerError::NoPrize);
+
+        entry.finish_position = position;
+        entry.claimed = true;
+
+        **tournament.to_account_info().try_borrow_mut_lamports()? -= prize;
+        **player.to_account_info().try_borrow_mut_lamports()? += prize;
+
+        msg!(
+            "Player {} claimed {} lamports for finishing {} in tournament {}",
+            player.key(),
+            prize,
+            position,
+            tournament.key()
+        );
+        Ok(())
+    }
+
+    /// Open a waitlist for one stake level (house only). Players queue with
+    /// their buy-in escrowed and are seated at the waitlist's tables, which
+    /// are created from `template` once `spawn_threshold` players wait.
+    pub fn create_waitlist(
+        ctx: Context<CreateWaitlist>,
+        waitlist_id: u64,
+        template: TableTemplate,
+        spawn_threshold: u8,
+    ) -> Result<()> {
+        template.validate()?;
+        require!(
+            spawn_threshold > 0 && spawn_threshold as usize <= MAX_WAITLIST,
+            ShadowPokerError::InvalidPlayerCount
+        );
+
+        let waitlist = &mut ctx.accounts.waitlist;
+        waitlist.authority = ctx.accounts.authority.key();
+        waitlist.waitlist_id = waitlist_id;
+        waitlist.template = template;
+        waitlist.spawn_threshold = spawn_threshold;
+        waitlist.queue = vec![];
+        waitlist.tables = vec![];
+        waitlist.bump = ctx.bumps.waitlist;
+
+        msg!(
+            "Waitlist {} created for {}/{} {:?}",
+            waitlist.key(),
+            template.small_blind,
+            template.big_blind,
+            template.variant
+        );
+        Ok(())
+    }
+
+    /// Queue for a seat, escrowing the buy-in until seated or leaving.
+    pub fn join_waitlist(ctx: Context<JoinWaitlist>, buy_in: u64) -> Result<()> {
+        let waitlist = &mut ctx.accounts.waitlist;
+        let entry = &mut ctx.accounts.entry;
+        let player = &ctx.accounts.player;
+
+        require!(
+            buy_in >= waitlist.template.min_buy_in && buy_in <= waitlist.template.max_buy_in,
+            ShadowPokerError::InvalidBuyIn
+        );
+        require!(waitlist.queue.len() < MAX_WAITLIST, ShadowPokerError::WaitlistFull);
+
+        entry.waitlist = waitlist.key();
+        entry.player = player.key();
+        entry.buy_in = buy_in;
+        entry.bump = ctx.bumps.entry;
+        waitlist.queue.push(player.key());
+
+        anchor_lang::system_program::transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                anchor_lang::system_program::Transfer {
+                    from: player.to_account_info(),
+                    to: entry.to_account_info(),
+                },
+            ),
+            buy_in,
+        )?;
+
+        msg!(
+            "Player {} joined waitlist {} at position {}",
+            player.key(),
+            waitlist.key(),
+            waitlist.queue.len()
+        );
+        Ok(())
+    }
+
+    /// Leave the waitlist; the escrowed buy-in is returned.
+    pub fn leave_waitlist(ctx: Context<LeaveWaitlist>) -> Result<()> {
+        let waitlist = &mut ctx.accounts.waitlist;
+        let player = ctx.accounts.player.key();
+
+        waitlist.queue.retain(|p| *p != player);
+
+        msg!("Player {} left waitlist {}", player, waitlist.key());
+        Ok(())
+    }
+
+    /// Permissionless crank: create a table from the waitlist's template
+    /// once enough players are waiting. The house authority is its creator.
+    ///
+    /// The waitlist's tables must all be full, so only players who can't be
+    /// seated anywhere count towards opening another. Every one of them must
+    /// be passed in `remaining_accounts`, in the waitlist's order.
+    pub fn spawn_waitlist_table<'info>(
+        ctx: Context<'_, '_, 'info, 'info, SpawnWaitlistTable<'info>>,
+    ) -> Result<()> {
+        let waitlist = &mut ctx.accounts.waitlist;
+        let table = &mut ctx.accounts.table;
+        let house = &mut ctx.accounts.house;
+        let clock = Clock::get()?;
+
+        require!(
+            ctx.remaining_accounts.len() == waitlist.tables.len(),
+            ShadowPokerError::WrongWaitlistTable
+        );
+        for (info, key) in ctx.remaining_accounts.iter().zip(&waitlist.tables) {
+            require_keys_eq!(info.key(), *key, ShadowPokerError::WrongWaitlistTable);
+            let existing: Account<Table> = Account::try_from(info)?;
+            require!(
+                existing.seated_count() == existing.players.len(),
+                ShadowPokerError::WaitlistSeatsOpen
+            );
+        }
+        require!(
+            waitlist.queue.len() >= waitlist.spawn_threshold as usize,
+            ShadowPokerError::WaitlistTooShort
+        );
+        require!(
+            waitlist.tables.len() < MAX_WAITLIST_TABLES,
+            ShadowPokerError::TooManyTables
+        );
+
+        let template = waitlist.template;
+        table.open(house.authority, template.max_players, clock.slot, ctx.bumps.table);
+        table.min_buy_in = template.min_buy_in;
+        table.max_buy_in = template.max_buy_in;
+        table.small_blind = template.small_blind;
+        table.big_blind = template.big_blind;
+        table.ante = template.ante;
+        table.variant = template.variant;
+        table.betting = template.betting;
+        table.dealing_mode = DealingMode::Dealer;
+        table.chip_mint = None;
+        table.play_money = false;
+        table.tournament = None;
+        table.access_restricted = false;
+
+        house.total_tables += 1;
+        waitlist.tables.push(table.key());
+
+        msg!(
+            "Waitlist {} opened table {} with {} seats",
+            waitlist.key(),
+            table.key(),
+            template.max_players
+        );
+        Ok(())
+    }
+
+    /// Permissionless crank: seat the first player on the waitlist who isn't
+    /// already at the table at one of its tables with a free seat, moving
+    /// their escrowed buy-in to the table. Players ahead of them who already
+    /// sit there keep their place for the next table.
+    pub fn seat_from_waitlist(ctx: Context<SeatFromWaitlist>) -> Result<()> {
+        let waitlist = &mut ctx.accounts.waitlist;
+        let entry = &ctx.accounts.entry;
+        let table = &mut ctx.accounts.table;
+        let player_state = &mut ctx.accounts.player_state;
+
+        require!(
+            table.status == TableStatus::Waiting || table.status == TableStatus::Finished,
+            ShadowPokerError::TableNotJoinable
+        );
+        let position = waitlist
+            .queue
+            .iter()
+            .position(|player| !table.players.contains(player))
+            .filter(|&position| waitlist.queue[position] == entry.player)
+            .ok_or(ShadowPokerError::NotNextOnWaitlist)?;
+        let seat = table
+            .players
+            .iter()
+            .position(|p| *p == Pubkey::default())
+            .ok_or(ShadowPokerError::TableFull)?;
+        table.players[seat] = entry.player;
+
+        seat_player(player_state, entry.player, table.key(), seat as u8, entry.buy_in);
+        player_state.needs_big_blind = table.hand_number > 0;
+        player_state.bump = ctx.bumps.player_state;
+        waitlist.queue.remove(position);
+
+        // The entry's rent goes back to the player when it closes
+        **entry.to_account_info().try_borrow_mut_lamports()? -= entry.buy_in;
+        **table.to_account_info().try_borrow_mut_lamports()? += entry.buy_in;
+
+        msg!(
+            "Player {} seated from waitlist {} at table {} seat {} with {} chips",
+            entry.player,
+            waitlist.key(),
+            table.key(),
+            seat,
+            entry.buy_in
+        );
+        Ok(())
+    }
+}
+
+// Helpers
+
+/// Table operators are the table creator, the house authority and
+/// house-approved dealers.
+fn require_table_operator(
+    table: &Table,
+    house: &ShadowPokerHouse,
+    authority: &Pubkey,
+    dealer_approval: Option<&Account<HouseDealer>>,
+) -> Result<()> {
+    let is_dealer = dealer_approval.is_some_and(|approval| approval.dealer == *authority);
+    require!(
+        *authority == table.creator || *authority == house.authority || is_dealer,
+        ShadowPokerError::UnauthorizedTableAction
+    );
+    Ok(())
+}
+
+/// Check that `signer` is `player` or one of their live session keys.
+/// Returns the session token when acting through one, so spending can be
+/// charged to it.
+fn authorize_player<'a, 'info>(
+    player: &Pubkey,
+    signer: &Pubkey,
+    session: Option<&'a mut Account<'info, SessionToken>>,
+) -> Result<Option<&'a mut Account<'info, SessionToken>>> {
+    if signer == player {
+        return Ok(None);
+    }
+    let session = session.ok_or(ShadowPokerError::InvalidSession)?;
+    require!(
+        session.authority == *player && session.session_key == *signer,
+        ShadowPokerError::InvalidSession
+    );
+    require!(
+        Clock::get()?.unix_timestamp < session.expires_at,
+        ShadowPokerError::SessionExpired
+    );
+    Ok(Some(session))
+}
+
+/// Take a refunded straddle off the spending of the session token it was
+/// charged to, found among `accounts`. A token revoked since has nothing
+/// left to refund.
+fn refund_session_spend(accounts: &[AccountInfo], session_key: &Pubkey, amount: u64) -> Result<()> {
+    let info = accounts
+        .iter()
+        .find(|info| info.key == session_key)
+        .ok_or(ShadowPokerError::MissingSessionToken)?;
+    if info.owner != &crate::ID {
+        return Ok(());
+    }
+    require!(info.is_writable, ShadowPokerError::MissingSessionToken);
+    let mut session = SessionToken::try_deserialize(&mut &info.try_borrow_data()?[..])?;
+    session.spent = session.spent.saturating_sub(amount);
+    session.try_serialize(&mut &mut info.try_borrow_mut_data()?[..])
+}
+
+/// Load the `PlayerState` of every occupied seat from `remaining_accounts`,
+/// which must be passed in seat order.
+fn load_player_states<'info>(
+    table: &Account<'info, Table>,
+    accounts: &'info [AccountInfo<'info>],
+) -> Result<Vec<Account<'info, PlayerState>>> {
+    require!(
+        accounts.len() == table.seated_count(),
+        ShadowPokerError::PlayerStatesMismatch
+    );
+
+    let table_key = table.key();
+    let mut states = Vec::with_capacity(accounts.len());
+    let seated = table.players.iter().enumerate().filter(|(_, p)| **p != Pubkey::default());
+    for (info, (seat, player)) in accounts.iter().zip(seated) {
+        require!(info.is_writable, ShadowPokerError::PlayerStatesMismatch);
+        let state: Account<'info, PlayerState> = Account::try_from(info)?;
+        let expected = Pubkey::create_program_address(
+            &[b"player_state", table_key.as_ref(), player.as_ref(), &[state.bump]],
+            &crate::ID,
+        )
+        .map_err(|_| error!(ShadowPokerError::PlayerStatesMismatch))?;
+        require_keys_eq!(info.key(), expected, ShadowPokerError::PlayerStatesMismatch);
+        require!(state.seat_index as usize == seat, ShadowPokerError::PlayerStatesMismatch);
+        states.push(state);
+    }
+    Ok(states)
+}
+
+/// Next seat after `from` (wrapping around `seat_count` seats) whose bit is set in `mask`.
+pub fn next_seat_in(seat_count: u8, from: u8, mask: u16) -> Option<u8> {
+    (1..=seat_count)
+        .map(|offset| (from % seat_count.max(1) + offset) % seat_count)
+        .find(|seat| mask & (1 << seat) != 0)
+}
+
+/// Fold an event into a running hand-history hash. Off-chain replays
+/// recompute this over the emitted event data to check the committed hash.
+pub fn chain_history(prev: &[u8; 32], event_data: &[u8]) -> [u8; 32] {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(prev);
+    hasher.update(event_data);
+    *hasher.finalize().as_bytes()
+}
+
+/// Check that a variant and betting structure can be played together.
+fn require_valid_game(variant: GameVariant, betting: BettingStructure) -> Result<()> {
+    require!(
+        variant != GameVariant::PotLimitOmaha || betting == BettingStructure::PotLimit,
+        ShadowPokerError::InvalidBettingStructure
+    );
+    if let BettingStructure::FixedLimit { max_raises } = betting {
+        require!(
+            (1..=MAX_FIXED_LIMIT_RAISES).contains(&max_raises),
+            ShadowPokerError::InvalidBettingStructure
+        );
+    }
+    Ok(())
+}
+
+/// Check that `cards` exist in the variant's deck and don't repeat each
+/// other or any card already dealt.
+fn require_valid_cards(variant: GameVariant, cards: &[Card], dealt: &[Card]) -> Result<()> {
+    for (i, card) in cards.iter().enumerate() {
+        require!(card.is_in_deck(variant), ShadowPokerError::InvalidCard);
+        require!(
+            !cards[..i].contains(card) && !dealt.contains(card),
+            ShadowPokerError::DuplicateCard
+        );
+    }
+    Ok(())
+}
+
+/// Emit a hand-history event and chain it into the table's history hash.
+fn record_event<E: anchor_lang::Event>(table: &mut Table, event: E) {
+    table.history_hash = chain_history(&table.history_hash, &event.data());
+    table.event_seq += 1;
+    emit!(event);
+}
+
+/// Pay rake-back held in the house account out to a player.
+fn pay_rakeback<'info>(
+    house: &Account<'info, ShadowPokerHouse>,
+    player: &AccountInfo<'info>,
+    amount: u64,
+) -> Result<()> {
+    **house.to_account_info().try_borrow_mut_lamports()? -= amount;
+    **player.try_borrow_mut_lamports()? += amount;
+    Ok(())
+}
+
+/// Address of the house play chip mint.
+pub fn play_chip_mint_address() -> Pubkey {
+    Pubkey::find_program_address(&[b"play_chips"], &crate::ID).0
+}
+
+/// Token accounts that chips move through at a token or play-money table.
+struct TokenChips<'a, 'info> {
+    mint: &'a InterfaceAccount<'info, Mint>,
+    wallet: &'a InterfaceAccount<'info, TokenAccount>, // The player's token account
+    vault: Option<&'a InterfaceAccount<'info, TokenAccount>>, // None for play money
+    token_program: &'a Interface<'info, TokenInterface>,
+}
+
+impl<'info> TokenChips<'_, 'info> {
+    /// Take `amount` from the player: into the vault, or burned for play money.
+    fn deposit(&self, player: &Signer<'info>, amount: u64) -> Result<()> {
+        let program = self.token_program.to_account_info();
+        match self.vault {
+            Some(vault) => token_interface::transfer_checked(
+                CpiContext::new(
+                    program,
+                    token_interface::TransferChecked {
+                        from: self.wallet.to_account_info(),
+                        mint: self.mint.to_account_info(),
+                        to: vault.to_account_info(),
+                        authority: player.to_account_info(),
+                    },
+                ),
+                amount,
+                self.mint.decimals,
+            ),
+            None => token_interface::burn(
+                CpiContext::new(
+                    program,
+                    token_interface::Burn {
+                        mint: self.mint.to_account_info(),
+                        from: self.wallet.to_account_info(),
+                        authority: player.to_account_info(),
+                    },
+                ),
+                amount,
+            ),
+        }
+    }
+
+    /// Pay `amount` to the player: out of the vault, or minted by the house
+    /// for play money.
+    fn withdraw(
+        &self,
+        table: &Account<'info, Table>,
+        house: &Account<'info, ShadowPokerHouse>,
+        amount: u64,
+    ) -> Result<()> {
+        if amount == 0 {
+            return Ok(());
+        }
+        let program = self.token_program.to_account_info();
+        match self.vault {
+            Some(vault) => {
+                let table_key = table.key();
+                let seeds: &[&[u8]] = &[b"vault", table_key.as_ref(), &[table.vault_bump]];
+                token_interface::transfer_checked(
+                    CpiContext::new_with_signer(
+                        program,
+                        token_interface::TransferChecked {
+                            from: vault.to_account_info(),
+                            mint: self.mint.to_account_info(),
+                            to: self.wallet.to_account_info(),
+                            authority: vault.to_account_info(),
+                        },
+                        &[seeds],
+                    ),
+                    amount,
+                    self.mint.decimals,
+                )
+            }
+            None => {
+                let seeds: &[&[u8]] = &[b"shadow_poker_house", &[house.bump]];
+                token_interface::mint_to(
+                    CpiContext::new_with_signer(
+                        program,
+                        token_interface::MintTo {
+                            mint: self.mint.to_account_info(),
+                            to: self.wallet.to_account_info(),
+                            authority: house.to_account_info(),
+                        },
+                        &[seeds],
+                    ),
+                    amount,
+                )
+            }
+        }
+    }
+}
+
+/// Free a player's seat without shifting anyone else's and pay out their
+/// remaining stack (including any blind or straddle posted for the next
+/// hand) plus any rake-back, closing their `PlayerState` to them. A
+/// straddle charged to a session key comes off its spending again, like in
+/// `start_hand`; its token must be among `sessions`. Returns the chips
+/// returned.
+fn cash_out<'info>(
+    table: &mut Account<'info, Table>,
+    house: &Account<'info, ShadowPokerHouse>,
+    player_state: &mut Account<'info, PlayerState>,
+    player: &AccountInfo<'info>,
+    chips: Option<TokenChips<'_, 'info>>,
+    sessions: &[AccountInfo<'info>],
+) -> Result<u64> {
+    table.players[player_state.seat_index as usize] = Pubkey::default();
+    if let Some(session_key) = player_state.straddle_session.filter(|_| player_state.straddle > 0) {
+        refund_session_spend(sessions, &session_key, player_state.straddle)?;
+    }
+
+    let return_amount = player_state.stack + player_state.posted_blind + player_state.straddle;
+    let rakeback = player_state.rakeback_owed;
+    player_state.stack = 0;
+    player_state.posted_blind = 0;
+    player_state.straddle = 0;
+    player_state.rakeback_owed = 0;
+
+    if let Some(chips) = chips {
+        chips.withdraw(table, house, return_amount + rakeback)?;
+    } else {
+        if return_amount > 0 {
+            **table.to_account_info().try_borrow_mut_lamports()? -= return_amount;
+            **player.try_borrow_mut_lamports()? += return_amount;
+        }
+        if rakeback > 0 {
+            pay_rakeback(house, player, rakeback)?;
+        }
+    }
+
+    // Refund the PlayerState rent to the player
+    player_state.close(player.clone())?;
+    Ok(return_amount)
+}
+
+/// Token-2022 extensions that let a mint move chips without the table
+/// knowing: a transfer fee shorts every deposit, and a transfer hook or
+/// permanent delegate can take tokens out of the vault.
+const UNSUPPORTED_MINT_EXTENSIONS: [ExtensionType; 3] = [
+    ExtensionType::TransferFeeConfig,
+    ExtensionType::TransferHook,
+    ExtensionType::PermanentDelegate,
+];
+
+/// Check that a table can hold its chips in `mint`, so the vault always
+/// has what the stacks add up to.
+fn require_supported_mint(mint: &InterfaceAccount<Mint>) -> Result<()> {
+    let info = mint.to_account_info();
+    if *info.owner != Token2022::id() {
+        return Ok(());
+    }
+    let data = info.try_borrow_data()?;
+    let mint = StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&data)?;
+    require!(
+        !mint
+            .get_extension_types()?
+            .iter()
+            .any(|extension| UNSUPPORTED_MINT_EXTENSIONS.contains(extension)),
+        ShadowPokerError::UnsupportedMintExtension
+    );
+    Ok(())
+}
+
+/// Check the optional token accounts an instruction was given against the
+/// table's currency. Returns `None` for SOL tables.
+fn table_chips<'a, 'info>(
+    table: &Table,
+    player: &Pubkey,
+    mint: Option<&'a InterfaceAccount<'info, Mint>>,
+    wallet: Option<&'a InterfaceAccount<'info, TokenAccount>>,
+    vault: Option<&'a InterfaceAccount<'info, TokenAccount>>,
+    token_program: Option<&'a Interface<'info, TokenInterface>>,
+) -> Result<Option<TokenChips<'a, 'info>>> {
+    let Some(chip_mint) = table.chip_mint else {
+        return Ok(None);
+    };
+    let (Some(mint), Some(wallet), Some(token_program)) = (mint, wallet, token_program) else {
+        return Err(ShadowPokerError::MissingChipAccounts.into());
+    };
+    require!(
+        mint.key() == chip_mint
+            && *mint.to_account_info().owner == token_program.key()
+            && wallet.mint == chip_mint
+            && wallet.owner == *player,
+        ShadowPokerError::WrongChipMint
+    );
+    let vault = match table.play_money {
+        true => None,
+        false => Some(vault.ok_or(ShadowPokerError::MissingChipAccounts)?),
+    };
+    Ok(Some(TokenChips {
+        mint,
+        wallet,
+        vault,
+        token_program,
+    }))
+}
+
+/// Record chips moving to `winner_seat` from every other player who put
+/// chips in the pot, taking `share` of each contribution.
+fn track_chip_flow(
+    table: Pubkey,
+    hand_number: u64,
+    states: &mut [Account<PlayerState>],
+    winner_seat: u8,
+    share: impl Fn(u64) -> u64,
+) {
+    let Some(winner) = states.iter().find(|s| s.seat_index == winner_seat).map(|s| s.player) else {
+        return;
+    };
+    let losers: Vec<(u8, Pubkey, u64)> = states
+        .iter()
+        .filter(|s| s.seat_index != winner_seat && share(s.hand_contribution) > 0)
+        .map(|s| (s.seat_index, s.player, share(s.hand_contribution)))
+        .collect();
+    for state in states.iter_mut() {
+        if state.seat_index == winner_seat {
+            for &(seat, player, amount) in losers.iter() {
+                let stats = state.opponent_mut(seat, player);
+                stats.chips_won += amount;
+                emit!(CollusionSignal {
+                    table,
+                    hand_number,
+                    kind: SignalKind::ChipsWon,
+                    player: winner,
+                    opponent: player,
+                    amount,
+                    stats: *stats,
+                });
+            }
+        } else if let Some(&(_, _, amount)) =
+            losers.iter().find(|(seat, _, _)| *seat == state.seat_index)
+        {
+            state.opponent_mut(winner_seat, winner).chips_lost += amount;
+        }
+    }
+}
+
+/// A player's authorization for a session key to play for them.
+#[account]
+pub struct SessionToken {
+    pub authority: Pubkey,   // The player's wallet
+    pub session_key: Pubkey, // Ephemeral key allowed to sign in-hand actions
+    pub expires_at: i64,     // Unix time
+    pub spend_cap: u64,      // Most chips the key may put in, across all tables
+    pub spent: u64,
+    pub bump: u8,
+}
+
+impl SessionToken {
+    pub const SIZE: usize = 32 + 32 + 8 + 8 + 8 + 1;
+
+    /// Charge `amount` chips put in by the session key against its cap.
+    pub fn spend(&mut self, amount: u64) -> Result<()> {
+        self.spent = self
+            .spent
+            .checked_add(amount)
+            .filter(|spent| *spent <= self.spend_cap)
+            .ok_or(ShadowPokerError::SessionSpendCapExceeded)?;
+        Ok(())
+    }
+}
+
+/// Initialize the `PlayerState` of a player sitting down with `stack`.
+fn seat_player(state: &mut PlayerState, player: Pubkey, table: Pubkey, seat: u8, stack: u64) {
+    state.player = player;
+    state.table = table;
+    state.seat_index = seat;
+    state.stack = stack;
+    state.current_bet = 0;
+    state.bet_round = 0;
+    state.is_active = true;
+    state.has_acted = false;
+    state.is_all_in = false;
+    state.posted_blind = 0;
+    state.hand_contribution = 0;
+    state.rake_paid = 0;
+    state.rakeback_owed = 0;
+    state.needs_big_blind = false;
+    state.timeout_strikes = 0;
+    state.time_bank_slots = TIME_BANK_START_SLOTS;
+    state.disconnect_protections = DISCONNECT_PROTECTIONS;
+    state.is_sitting_out = false;
+    state.leave_pending = false;
+    state.straddle = 0;
+    state.straddle_session = None;
+    state.insurance = None;
+    state.opponents = vec![OpponentStats::default(); MAX_PLAYERS as usize];
+}
+
+/// Take a forced bet (ante or blind) from a player, going all-in if the
+/// stack can't cover it. Returns the amount actually posted.
+fn post_forced_bet(state: &mut PlayerState, amount: u64) -> u64 {
+    let posted = amount.min(state.stack);
+    state.stack -= posted;
+    state.current_bet += posted;
+    state.hand_contribution += posted;
+    if state.stack == 0 {
+        state.is_all_in = true;
+    }
+    posted
+}
+
+// Account Structures
+
+#[derive(Accounts)]
+pub struct InitializeHouse<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + ShadowPokerHouse::SIZE,
+        seeds = [b"shadow_poker_house"],
+        bump
+    )]
+    pub house: Account<'info, ShadowPokerHouse>,
+    
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(min_buy_in: u64, max_buy_in: u64, small_blind: u64, big_blind: u64, max_players: u8, ante: u64)]
+pub struct CreateTable<'info> {
+    #[account(
+        init,
+        payer = creator,
+        space = 8 + Table::space(max_players),
         seeds = [b"table".as_ref(), house.total_tables.to_le_bytes().as_ref()],
         bump
     )]
     pub table: Account<'info, Table>,
     
+    #[account(
+        mut,
+        seeds = [b"shadow_poker_house"],
+        bump = house.bump,
+    )]
+    pub house: Account<'info, ShadowPokerHouse>,
+    
+    /// SPL token and play-money tables only
+    pub chip_mint: Option<InterfaceAccount<'info, Mint>>,
+    
+    #[account(mut)]
+    pub creator: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(buy_in: u64)]
+pub struct JoinTable<'info> {
+    #[account(mut)]
+    pub table: Account<'info, Table>,
+    
+    #[account(
+        seeds = [b"table_access", table.key().as_ref()],
+        bump = table_access.bump,
+    )]
+    pub table_access: Option<Account<'info, TableAccess>>,
+    
+    pub invite_token: Option<InterfaceAccount<'info, TokenAccount>>,
+    
+    #[account(
+        init,
+        payer = player,
+        space = 8 + PlayerState::SIZE,
+        seeds = [b"player_state".as_ref(), table.key().as_ref(), player.key().as_ref()],
+        bump
+    )]
+    pub player_state: Account<'info, PlayerState>,
+    
+    /// Token and play-money tables only
+    #[account(mut)]
+    pub chip_mint: Option<InterfaceAccount<'info, Mint>>,
+    
+    #[account(mut)]
+    pub player_tokens: Option<InterfaceAccount<'info, TokenAccount>>,
+    
+    /// Token tables only
+    #[account(
+        mut,
+        seeds = [b"vault", table.key().as_ref()],
+        bump = table.vault_bump,
+    )]
+    pub vault: Option<InterfaceAccount<'info, TokenAccount>>,
+    
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+    
+    #[account(mut)]
+    pub player: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct StartHand<'info> {
+    #[account(mut)]
+    pub table: Account<'info, Table>,
+    
+    #[account(
+        seeds = [b"shadow_poker_house"],
+        bump = house.bump,
+    )]
+    pub house: Account<'info, ShadowPokerHouse>,
+    
+    /// Present when `authority` is a house-approved dealer
+    #[account(
+        seeds = [b"dealer", authority.key().as_ref()],
+        bump = dealer_approval.bump,
+    )]
+    pub dealer_approval: Option<Account<'info, HouseDealer>>,
+    
+    /// Mental poker tables only
+    #[account(
+        mut,
+        seeds = [b"deck", table.key().as_ref()],
+        bump = deck.bump,
+    )]
+    pub deck: Option<Account<'info, Deck>>,
+    
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct PostBlind<'info> {
+    pub table: Account<'info, Table>,
+    
+    #[account(
+        mut,
+        seeds = [b"player_state", table.key().as_ref(), player_state.player.as_ref()],
+        bump = player_state.bump,
+    )]
+    pub player_state: Account<'info, PlayerState>,
+    
+    /// Present when `player` is a session key
+    #[account(
+        mut,
+        seeds = [b"session", player_state.player.as_ref(), player.key().as_ref()],
+        bump = session_token.bump,
+    )]
+    pub session_token: Option<Account<'info, SessionToken>>,
+    
+    /// The player's wallet or a session key acting for them
+    pub player: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct PlayerAction<'info> {
+    #[account(mut)]
+    pub table: Account<'info, Table>,
+    
+    #[account(
+        mut,
+        seeds = [b"player_state", table.key().as_ref(), player_state.player.as_ref()],
+        bump = player_state.bump,
+    )]
+    pub player_state: Account<'info, PlayerState>,
+    
+    /// Present when `player` is a session key
+    #[account(
+        mut,
+        seeds = [b"session", player_state.player.as_ref(), player.key().as_ref()],
+        bump = session_token.bump,
+    )]
+    pub session_token: Option<Account<'info, SessionToken>>,
+    
+    /// The player's wallet or a session key acting for them
+    pub player: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct LegalActions<'info> {
+    pub table: Account<'info, Table>,
+
+    #[account(
+        seeds = [b"player_state", table.key().as_ref(), player_state.player.as_ref()],
+        bump = player_state.bump,
+    )]
+    pub player_state: Account<'info, PlayerState>,
+}
+
+#[derive(Accounts)]
+pub struct RevealCards<'info> {
+    #[account(mut)]
+    pub table: Account<'info, Table>,
+    
+    #[account(
+        seeds = [b"shadow_poker_house"],
+        bump = house.bump,
+    )]
+    pub house: Account<'info, ShadowPokerHouse>,
+    
+    /// Present when `authority` is a house-approved dealer
+    #[account(
+        seeds = [b"dealer", authority.key().as_ref()],
+        bump = dealer_approval.bump,
+    )]
+    pub dealer_approval: Option<Account<'info, HouseDealer>>,
+    
+    /// Mental poker tables only
+    #[account(
+        seeds = [b"deck", table.key().as_ref()],
+        bump = deck.bump,
+    )]
+    pub deck: Option<Account<'info, Deck>>,
+    
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ShowCards<'info> {
+    #[account(mut)]
+    pub table: Account<'info, Table>,
+    
+    #[account(
+        seeds = [b"player_state", table.key().as_ref(), player.key().as_ref()],
+        bump = player_state.bump,
+    )]
+    pub player_state: Account<'info, PlayerState>,
+    
+    /// Mental poker tables only
+    #[account(
+        mut,
+        seeds = [b"deck", table.key().as_ref()],
+        bump = deck.bump,
+    )]
+    pub deck: Option<Account<'info, Deck>>,
+    
+    pub player: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct EnforceTimeout<'info> {
+    #[account(mut)]
+    pub table: Account<'info, Table>,
+    
+    #[account(
+        mut,
+        seeds = [b"player_state", table.key().as_ref(), player_state.player.as_ref()],
+        bump = player_state.bump,
+    )]
+    pub player_state: Account<'info, PlayerState>,
+}
+
+/*
+// #[init_computation_definition_accounts("poker", payer)]
+#[derive(Accounts)]
+pub struct InitPokerCompDef<'info> {
+...
+}
+*/
+
+#[derive(Accounts)]
+pub struct Showdown<'info> {
+    #[account(mut)]
+    pub table: Account<'info, Table>,
+    
+    #[account(
+        mut,
+        seeds = [b"shadow_poker_house"],
+        bump = house.bump,
+    )]
+    pub house: Account<'info, ShadowPokerHouse>,
+    
+    /// Present when `authority` is a house-approved dealer
+    #[account(
+        seeds = [b"dealer", authority.key().as_ref()],
+        bump = dealer_approval.bump,
+    )]
+    pub dealer_approval: Option<Account<'info, HouseDealer>>,
+    
+    /// Mental poker tables only
+    #[account(
+        seeds = [b"deck", table.key().as_ref()],
+        bump = deck.bump,
+    )]
+    pub deck: Option<Account<'info, Deck>>,
+    
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct InitDeck<'info> {
+    #[account(has_one = creator @ ShadowPokerError::UnauthorizedTableAction)]
+    pub table: Account<'info, Table>,
+    
+    #[account(
+        init,
+        payer = creator,
+        space = MAX_PERMITTED_DATA_INCREASE,
+        seeds = [b"deck", table.key().as_ref()],
+        bump
+    )]
+    pub deck: Account<'info, Deck>,
+    
+    #[account(mut)]
+    pub creator: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct LoadCardPoints<'info> {
+    #[account(has_one = creator @ ShadowPokerError::UnauthorizedTableAction)]
+    pub table: Account<'info, Table>,
+    
+    #[account(
+        mut,
+        seeds = [b"deck", table.key().as_ref()],
+        bump = deck.bump,
+    )]
+    pub deck: Account<'info, Deck>,
+    
+    pub creator: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SubmitShuffle<'info> {
+    pub table: Account<'info, Table>,
+    
+    // Grows to full size with the first shuffle; accounts can only be
+    // created with `MAX_PERMITTED_DATA_INCREASE` bytes
+    #[account(
+        mut,
+        seeds = [b"deck", table.key().as_ref()],
+        bump = deck.bump,
+        realloc = 8 + Deck::SIZE,
+        realloc::payer = player,
+        realloc::zero = false,
+    )]
+    pub deck: Account<'info, Deck>,
+    
+    #[account(
+        seeds = [b"player_state", table.key().as_ref(), player.key().as_ref()],
+        bump = player_state.bump,
+    )]
+    pub player_state: Account<'info, PlayerState>,
+    
+    #[account(mut)]
+    pub player: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SubmitShares<'info> {
+    #[account(mut)]
+    pub table: Account<'info, Table>,
+    
+    #[account(
+        mut,
+        seeds = [b"deck", table.key().as_ref()],
+        bump = deck.bump,
+    )]
+    pub deck: Account<'info, Deck>,
+    
+    #[account(
+        seeds = [b"player_state", table.key().as_ref(), player.key().as_ref()],
+        bump = player_state.bump,
+    )]
+    pub player_state: Account<'info, PlayerState>,
+    
+    pub player: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct RequestBoard<'info> {
+    pub table: Account<'info, Table>,
+    
+    #[account(
+        mut,
+        seeds = [b"deck", table.key().as_ref()],
+        bump = deck.bump,
+    )]
+    pub deck: Account<'info, Deck>,
+}
+
+#[derive(Accounts)]
+pub struct VerifyShuffle<'info> {
+    pub table: Account<'info, Table>,
+    
+    #[account(
+        mut,
+        seeds = [b"deck", table.key().as_ref()],
+        bump = deck.bump,
+    )]
+    pub deck: Account<'info, Deck>,
+    
+    #[account(
+        seeds = [b"player_state", table.key().as_ref(), player.key().as_ref()],
+        bump = player_state.bump,
+    )]
+    pub player_state: Account<'info, PlayerState>,
+    
+    pub player: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct AbortHand<'info> {
+    #[account(mut)]
+    pub table: Account<'info, Table>,
+    
+    /// Refunds insurance premiums
+    #[account(
+        mut,
+        seeds = [b"shadow_poker_house"],
+        bump = house.bump,
+    )]
+    pub house: Account<'info, ShadowPokerHouse>,
+    
+    #[account(
+        seeds = [b"deck", table.key().as_ref()],
+        bump = deck.bump,
+    )]
+    pub deck: Account<'info, Deck>,
+}
+
+#[derive(Accounts)]
+pub struct LeaveTable<'info> {
+    #[account(mut)]
+    pub table: Account<'info, Table>,
+    
+    #[account(
+        mut,
+        seeds = [b"shadow_poker_house"],
+        bump = house.bump,
+    )]
+    pub house: Account<'info, ShadowPokerHouse>,
+    
+    #[account(
+        mut,
+        seeds = [b"player_state", table.key().as_ref(), player.key().as_ref()],
+        bump = player_state.bump,
+    )]
+    pub player_state: Account<'info, PlayerState>,
+    
+    /// Token and play-money tables only
+    #[account(mut)]
+    pub chip_mint: Option<InterfaceAccount<'info, Mint>>,
+    
+    #[account(mut)]
+    pub player_tokens: Option<InterfaceAccount<'info, TokenAccount>>,
+    
+    /// Token tables only
+    #[account(
+        mut,
+        seeds = [b"vault", table.key().as_ref()],
+        bump = table.vault_bump,
+    )]
+    pub vault: Option<InterfaceAccount<'info, TokenAccount>>,
+    
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+    
+    /// CHECK: Session token a straddle posted for the next hand was charged
+    /// to, when there is one; checked against the `PlayerState` on use
+    #[account(mut)]
+    pub session_token: Option<UncheckedAccount<'info>>,
+    
+    #[account(mut)]
+    pub player: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CashOutPlayer<'info> {
+    #[account(mut)]
+    pub table: Account<'info, Table>,
+    
+    #[account(
+        mut,
+        seeds = [b"shadow_poker_house"],
+        bump = house.bump,
+    )]
+    pub house: Account<'info, ShadowPokerHouse>,
+    
+    #[account(
+        mut,
+        seeds = [b"player_state", table.key().as_ref(), player.key().as_ref()],
+        bump = player_state.bump,
+    )]
+    pub player_state: Account<'info, PlayerState>,
+    
+    /// Token and play-money tables only
+    #[account(mut)]
+    pub chip_mint: Option<InterfaceAccount<'info, Mint>>,
+    
+    #[account(mut)]
+    pub player_tokens: Option<InterfaceAccount<'info, TokenAccount>>,
+    
+    /// Token tables only
+    #[account(
+        mut,
+        seeds = [b"vault", table.key().as_ref()],
+        bump = table.vault_bump,
+    )]
+    pub vault: Option<InterfaceAccount<'info, TokenAccount>>,
+    
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+    
+    /// CHECK: Session token a straddle posted for the next hand was charged
+    /// to, when there is one; checked against the `PlayerState` on use
+    #[account(mut)]
+    pub session_token: Option<UncheckedAccount<'info>>,
+    
+    /// CHECK: Receives the cash-out; tied to `player_state` by its seeds
+    #[account(mut)]
+    pub player: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(session_key: Pubkey)]
+pub struct CreateSession<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + SessionToken::SIZE,
+        seeds = [b"session".as_ref(), authority.key().as_ref(), session_key.as_ref()],
+        bump
+    )]
+    pub session_token: Account<'info, SessionToken>,
+    
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RevokeSession<'info> {
+    #[account(
+        mut,
+        has_one = authority @ ShadowPokerError::InvalidSession,
+        close = authority,
+    )]
+    pub session_token: Account<'info, SessionToken>,
+    
+    #[account(mut)]
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SitOutOrIn<'info> {
+    pub table: Account<'info, Table>,
+    
+    #[account(
+        mut,
+        seeds = [b"player_state", table.key().as_ref(), player.key().as_ref()],
+        bump = player_state.bump,
+    )]
+    pub player_state: Account<'info, PlayerState>,
+    
+    pub player: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct TopUp<'info> {
+    #[account(mut)]
+    pub table: Account<'info, Table>,
+    
+    #[account(
+        mut,
+        seeds = [b"player_state", table.key().as_ref(), player.key().as_ref()],
+        bump = player_state.bump,
+    )]
+    pub player_state: Account<'info, PlayerState>,
+    
+    /// Token and play-money tables only
+    #[account(mut)]
+    pub chip_mint: Option<InterfaceAccount<'info, Mint>>,
+    
+    #[account(mut)]
+    pub player_tokens: Option<InterfaceAccount<'info, TokenAccount>>,
+    
+    /// Token tables only
+    #[account(
+        mut,
+        seeds = [b"vault", table.key().as_ref()],
+        bump = table.vault_bump,
+    )]
+    pub vault: Option<InterfaceAccount<'info, TokenAccount>>,
+    
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+    
+    #[account(mut)]
+    pub player: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct DelegateTable<'info> {
+    /// Passed writable but not `mut`: it belongs to the delegation program
+    /// by the end of the instruction, so Anchor must not write it back
+    pub table: Account<'info, Table>,
+    
+    #[account(
+        seeds = [b"shadow_poker_house"],
+        bump = house.bump,
+    )]
+    pub house: Account<'info, ShadowPokerHouse>,
+    
+    #[account(
+        seeds = [b"dealer", authority.key().as_ref()],
+        bump = dealer_approval.bump,
+    )]
+    pub dealer_approval: Option<Account<'info, HouseDealer>>,
+    
+    /// CHECK: holds the table's data during delegation, checked on use
+    #[account(mut)]
+    pub buffer: UncheckedAccount<'info>,
+    
+    /// CHECK: delegation program PDA, checked on use
+    #[account(mut)]
+    pub delegation_record: UncheckedAccount<'info>,
+    
+    /// CHECK: delegation program PDA, checked on use
+    #[account(mut)]
+    pub delegation_metadata: UncheckedAccount<'info>,
+    
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    /// CHECK: this program, which keeps ownership through the delegation
+    #[account(address = crate::ID)]
+    pub owner_program: UncheckedAccount<'info>,
+    
+    /// CHECK: MagicBlock delegation program
+    #[account(address = DELEGATION_PROGRAM_ID)]
+    pub delegation_program: UncheckedAccount<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CommitTable<'info> {
+    pub table: Account<'info, Table>,
+    
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    
+    /// CHECK: MagicBlock magic context
+    #[account(mut, address = MAGIC_CONTEXT_ID)]
+    pub magic_context: UncheckedAccount<'info>,
+    
+    /// CHECK: MagicBlock magic program
+    #[account(address = MAGIC_PROGRAM_ID)]
+    pub magic_program: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct UndelegateTable<'info> {
+    pub table: Account<'info, Table>,
+    
+    #[account(
+        seeds = [b"shadow_poker_house"],
+        bump = house.bump,
+    )]
+    pub house: Account<'info, ShadowPokerHouse>,
+    
+    #[account(
+        seeds = [b"dealer", payer.key().as_ref()],
+        bump = dealer_approval.bump,
+    )]
+    pub dealer_approval: Option<Account<'info, HouseDealer>>,
+    
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    
+    /// CHECK: MagicBlock magic context
+    #[account(mut, address = MAGIC_CONTEXT_ID)]
+    pub magic_context: UncheckedAccount<'info>,
+    
+    /// CHECK: MagicBlock magic program
+    #[account(address = MAGIC_PROGRAM_ID)]
+    pub magic_program: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ProcessUndelegation<'info> {
+    /// CHECK: recreated at the address derived from the passed seeds
+    #[account(mut)]
+    pub base_account: UncheckedAccount<'info>,
+    
+    /// CHECK: delegation program buffer, which must sign
+    pub buffer: UncheckedAccount<'info>,
+    
+    /// CHECK: pays the recreated account's rent
+    #[account(mut)]
+    pub payer: UncheckedAccount<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UseTimeBank<'info> {
+    #[account(mut)]
+    pub table: Account<'info, Table>,
+    
+    #[account(
+        mut,
+        seeds = [b"player_state", table.key().as_ref(), player_state.player.as_ref()],
+        bump = player_state.bump,
+    )]
+    pub player_state: Account<'info, PlayerState>,
+    
+    /// Present when `player` is a session key
+    #[account(
+        mut,
+        seeds = [b"session", player_state.player.as_ref(), player.key().as_ref()],
+        bump = session_token.bump,
+    )]
+    pub session_token: Option<Account<'info, SessionToken>>,
+    
+    /// The player's wallet or a session key acting for them
+    pub player: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetTableOptions<'info> {
+    #[account(mut, has_one = creator @ ShadowPokerError::UnauthorizedTableAction)]
+    pub table: Account<'info, Table>,
+    
+    pub creator: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct OfferInsurance<'info> {
+    pub table: Account<'info, Table>,
+    
+    #[account(
+        seeds = [b"shadow_poker_house"],
+        bump = house.bump,
+    )]
+    pub house: Account<'info, ShadowPokerHouse>,
+    
+    /// Present when `authority` is a house-approved dealer
+    #[account(
+        seeds = [b"dealer", authority.key().as_ref()],
+        bump = dealer_approval.bump,
+    )]
+    pub dealer_approval: Option<Account<'info, HouseDealer>>,
+    
+    #[account(
+        mut,
+        seeds = [b"player_state", table.key().as_ref(), player_state.player.as_ref()],
+        bump = player_state.bump,
+    )]
+    pub player_state: Account<'info, PlayerState>,
+    
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct AcceptInsurance<'info> {
+    pub table: Account<'info, Table>,
+    
+    #[account(
+        mut,
+        seeds = [b"shadow_poker_house"],
+        bump = house.bump,
+    )]
+    pub house: Account<'info, ShadowPokerHouse>,
+    
+    #[account(
+        mut,
+        seeds = [b"player_state", table.key().as_ref(), player.key().as_ref()],
+        bump = player_state.bump,
+    )]
+    pub player_state: Account<'info, PlayerState>,
+    
+    #[account(mut)]
+    pub player: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetTableAccess<'info> {
+    #[account(mut, has_one = creator @ ShadowPokerError::UnauthorizedTableAction)]
+    pub table: Account<'info, Table>,
+    
+    #[account(
+        init_if_needed,
+        payer = creator,
+        space = 8 + TableAccess::SIZE,
+        seeds = [b"table_access", table.key().as_ref()],
+        bump
+    )]
+    pub table_access: Account<'info, TableAccess>,
+    
+    #[account(mut)]
+    pub creator: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct KickPlayer<'info> {
+    #[account(mut, has_one = creator @ ShadowPokerError::UnauthorizedTableAction)]
+    pub table: Account<'info, Table>,
+    
+    #[account(
+        mut,
+        seeds = [b"player_state", table.key().as_ref(), player.key().as_ref()],
+        bump = player_state.bump,
+    )]
+    pub player_state: Account<'info, PlayerState>,
+    
+    /// Mints play chips back for play-money refunds
+    #[account(
+        seeds = [b"shadow_poker_house"],
+        bump = house.bump,
+    )]
+    pub house: Account<'info, ShadowPokerHouse>,
+    
+    /// Token and play-money tables only
+    #[account(mut)]
+    pub chip_mint: Option<InterfaceAccount<'info, Mint>>,
+    
+    #[account(mut)]
+    pub player_tokens: Option<InterfaceAccount<'info, TokenAccount>>,
+    
+    /// Token tables only
+    #[account(
+        mut,
+        seeds = [b"vault", table.key().as_ref()],
+        bump = table.vault_bump,
+    )]
+    pub vault: Option<InterfaceAccount<'info, TokenAccount>>,
+    
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+    
+    /// CHECK: Session token a straddle posted for the next hand was charged
+    /// to, when there is one; checked against the `PlayerState` on use
+    #[account(mut)]
+    pub session_token: Option<UncheckedAccount<'info>>,
+    
+    /// CHECK: Receives the refund; tied to `player_state` by its seeds
+    #[account(mut)]
+    pub player: UncheckedAccount<'info>,
+    
+    pub creator: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(dealer: Pubkey)]
+pub struct ApproveDealer<'info> {
+    #[account(
+        seeds = [b"shadow_poker_house"],
+        bump = house.bump,
+        has_one = authority @ ShadowPokerError::UnauthorizedHouse,
+    )]
+    pub house: Account<'info, ShadowPokerHouse>,
+    
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + HouseDealer::SIZE,
+        seeds = [b"dealer", dealer.as_ref()],
+        bump
+    )]
+    pub dealer_approval: Account<'info, HouseDealer>,
+    
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RevokeDealer<'info> {
+    #[account(
+        seeds = [b"shadow_poker_house"],
+        bump = house.bump,
+        has_one = authority @ ShadowPokerError::UnauthorizedHouse,
+    )]
+    pub house: Account<'info, ShadowPokerHouse>,
+    
+    #[account(
+        mut,
+        close = authority,
+        seeds = [b"dealer", dealer_approval.dealer.as_ref()],
+        bump = dealer_approval.bump,
+    )]
+    pub dealer_approval: Account<'info, HouseDealer>,
+    
+    #[account(mut)]
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetTableRake<'info> {
+    #[account(mut)]
+    pub table: Account<'info, Table>,
+    
+    #[account(
+        seeds = [b"shadow_poker_house"],
+        bump = house.bump,
+        has_one = authority @ ShadowPokerError::UnauthorizedHouse,
+    )]
+    pub house: Account<'info, ShadowPokerHouse>,
+    
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimRakeback<'info> {
+    #[account(
+        mut,
+        seeds = [b"shadow_poker_house"],
+        bump = house.bump,
+    )]
+    pub house: Account<'info, ShadowPokerHouse>,
+    
+    pub table: Account<'info, Table>,
+    
+    #[account(
+        mut,
+        seeds = [b"player_state", table.key().as_ref(), player.key().as_ref()],
+        bump = player_state.bump,
+    )]
+    pub player_state: Account<'info, PlayerState>,
+    
+    /// Token and play-money tables only
+    #[account(mut)]
+    pub chip_mint: Option<InterfaceAccount<'info, Mint>>,
+    
+    #[account(mut)]
+    pub player_tokens: Option<InterfaceAccount<'info, TokenAccount>>,
+    
+    /// Token tables only
+    #[account(
+        mut,
+        seeds = [b"vault", table.key().as_ref()],
+        bump = table.vault_bump,
+    )]
+    pub vault: Option<InterfaceAccount<'info, TokenAccount>>,
+    
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+    
+    #[account(mut)]
+    pub player: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CreateTableVault<'info> {
+    #[account(mut)]
+    pub table: Account<'info, Table>,
+    
+    pub chip_mint: InterfaceAccount<'info, Mint>,
+    
+    #[account(
+        init,
+        payer = payer,
+        token::mint = chip_mint,
+        token::authority = vault,
+        token::token_program = token_program,
+        seeds = [b"vault", table.key().as_ref()],
+        bump
+    )]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+    
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    
+    pub token_program: Interface<'info, TokenInterface>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CollectTableRake<'info> {
+    #[account(mut)]
+    pub table: Account<'info, Table>,
+    
+    #[account(
+        seeds = [b"shadow_poker_house"],
+        bump = house.bump,
+        has_one = authority @ ShadowPokerError::UnauthorizedHouse,
+    )]
+    pub house: Account<'info, ShadowPokerHouse>,
+    
+    pub chip_mint: InterfaceAccount<'info, Mint>,
+    
+    #[account(
+        mut,
+        seeds = [b"vault", table.key().as_ref()],
+        bump = table.vault_bump,
+    )]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+    
+    #[account(mut, token::mint = chip_mint)]
+    pub destination: InterfaceAccount<'info, TokenAccount>,
+    
+    pub authority: Signer<'info>,
+    
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct InitPlayChips<'info> {
+    #[account(
+        seeds = [b"shadow_poker_house"],
+        bump = house.bump,
+        has_one = authority @ ShadowPokerError::UnauthorizedHouse,
+    )]
+    pub house: Account<'info, ShadowPokerHouse>,
+    
+    /// CHECK: Created here as a Token-2022 mint with the non-transferable extension
+    #[account(
+        mut,
+        seeds = [b"play_chips"],
+        bump
+    )]
+    pub play_chip_mint: UncheckedAccount<'info>,
+    
     #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    pub token_program: Program<'info, Token2022>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct GrantPlayChips<'info> {
+    #[account(
+        seeds = [b"shadow_poker_house"],
+        bump = house.bump,
+        has_one = authority @ ShadowPokerError::UnauthorizedHouse,
+    )]
     pub house: Account<'info, ShadowPokerHouse>,
     
+    #[account(
+        mut,
+        seeds = [b"play_chips"],
+        bump
+    )]
+    pub play_chip_mint: InterfaceAccount<'info, Mint>,
+    
+    #[account(mut, token::mint = play_chip_mint)]
+    pub player_tokens: InterfaceAccount<'info, TokenAccount>,
+    
+    pub authority: Signer<'info>,
+    
+    pub token_program: Program<'info, Token2022>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawTreasury<'info> {
+    #[account(
+        mut,
+        seeds = [b"shadow_poker_house"],
+        bump = house.bump,
+    )]
+    pub house: Account<'info, ShadowPokerHouse>,
+    
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(tournament_id: u64)]
+pub struct CreateTournament<'info> {
+    #[account(
+        init,
+        payer = creator,
+        space = 8 + Tournament::SIZE,
+        seeds = [b"tournament".as_ref(), creator.key().as_ref(), tournament_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub tournament: Account<'info, Tournament>,
+    
     #[account(mut)]
     pub creator: Signer<'info>,
     
@@ -447,19 +4703,18 @@ pub struct CreateTable<'info> {
 }
 
 #[derive(Accounts)]
-#[instruction(buy_in: u64)]
-pub struct JoinTable<'info> {
+pub struct RegisterTournament<'info> {
     #[account(mut)]
-    pub table: Account<'info, Table>,
+    pub tournament: Account<'info, Tournament>,
     
     #[account(
         init,
         payer = player,
-        space = 8 + PlayerState::SIZE,
-        seeds = [b"player_state".as_ref(), table.key().as_ref(), player.key().as_ref()],
+        space = 8 + TournamentEntry::SIZE,
+        seeds = [b"tournament_entry".as_ref(), tournament.key().as_ref(), player.key().as_ref()],
         bump
     )]
-    pub player_state: Account<'info, PlayerState>,
+    pub entry: Account<'info, TournamentEntry>,
     
     #[account(mut)]
     pub player: Signer<'info>,
@@ -468,98 +4723,307 @@ pub struct JoinTable<'info> {
 }
 
 #[derive(Accounts)]
-pub struct StartHand<'info> {
+pub struct StartTournament<'info> {
     #[account(mut)]
-    pub table: Account<'info, Table>,
+    pub tournament: Account<'info, Tournament>,
+    
+    #[account(
+        mut,
+        seeds = [b"shadow_poker_house"],
+        bump = house.bump,
+    )]
+    pub house: Account<'info, ShadowPokerHouse>,
     
     pub authority: Signer<'info>,
 }
 
 #[derive(Accounts)]
-pub struct PostBlind<'info> {
-    #[account(mut)]
+#[instruction(max_players: u8)]
+pub struct CreateTournamentTable<'info> {
+    #[account(mut, has_one = creator @ ShadowPokerError::NotTournamentCreator)]
+    pub tournament: Account<'info, Tournament>,
+    
+    #[account(
+        init,
+        payer = creator,
+        space = 8 + Table::space(max_players),
+        seeds = [
+            b"tournament_table".as_ref(),
+            tournament.key().as_ref(),
+            (tournament.tables.len() as u8).to_le_bytes().as_ref(),
+        ],
+        bump
+    )]
     pub table: Account<'info, Table>,
     
     #[account(mut)]
+    pub creator: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SeatTournamentPlayer<'info> {
+    pub tournament: Account<'info, Tournament>,
+    
+    #[account(
+        mut,
+        seeds = [b"tournament_entry", tournament.key().as_ref(), entry.player.as_ref()],
+        bump = entry.bump,
+    )]
+    pub entry: Account<'info, TournamentEntry>,
+    
+    #[account(
+        mut,
+        constraint = table.tournament == Some(tournament.key()) @ ShadowPokerError::WrongTournamentTable,
+    )]
+    pub table: Account<'info, Table>,
+    
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + PlayerState::SIZE,
+        seeds = [b"player_state".as_ref(), table.key().as_ref(), entry.player.as_ref()],
+        bump
+    )]
     pub player_state: Account<'info, PlayerState>,
     
-    pub player: Signer<'info>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-pub struct PlayerAction<'info> {
+pub struct SyncBlindLevel<'info> {
     #[account(mut)]
+    pub tournament: Account<'info, Tournament>,
+    
+    #[account(
+        mut,
+        constraint = table.tournament == Some(tournament.key()) @ ShadowPokerError::WrongTournamentTable,
+    )]
     pub table: Account<'info, Table>,
+}
+
+#[derive(Accounts)]
+pub struct BalanceTables<'info> {
+    pub tournament: Account<'info, Tournament>,
+    
+    #[account(
+        mut,
+        constraint = from_table.tournament == Some(tournament.key()) @ ShadowPokerError::WrongTournamentTable,
+    )]
+    pub from_table: Account<'info, Table>,
+    
+    #[account(
+        mut,
+        constraint = to_table.tournament == Some(tournament.key()) @ ShadowPokerError::WrongTournamentTable,
+    )]
+    pub to_table: Account<'info, Table>,
+    
+    #[account(
+        mut,
+        close = payer,
+        seeds = [b"player_state", from_table.key().as_ref(), from_state.player.as_ref()],
+        bump = from_state.bump,
+    )]
+    pub from_state: Account<'info, PlayerState>,
+    
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + PlayerState::SIZE,
+        seeds = [b"player_state".as_ref(), to_table.key().as_ref(), from_state.player.as_ref()],
+        bump
+    )]
+    pub to_state: Account<'info, PlayerState>,
+    
+    #[account(
+        mut,
+        seeds = [b"tournament_entry", tournament.key().as_ref(), from_state.player.as_ref()],
+        bump = entry.bump,
+    )]
+    pub entry: Account<'info, TournamentEntry>,
     
     #[account(mut)]
-    pub player_state: Account<'info, PlayerState>,
+    pub payer: Signer<'info>,
     
-    pub player: Signer<'info>,
+    pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-pub struct RevealCards<'info> {
+pub struct EliminatePlayer<'info> {
     #[account(mut)]
+    pub tournament: Account<'info, Tournament>,
+    
+    #[account(
+        mut,
+        constraint = table.tournament == Some(tournament.key()) @ ShadowPokerError::WrongTournamentTable,
+    )]
     pub table: Account<'info, Table>,
     
-    pub authority: Signer<'info>,
+    #[account(
+        mut,
+        close = player,
+        seeds = [b"player_state", table.key().as_ref(), player.key().as_ref()],
+        bump = player_state.bump,
+    )]
+    pub player_state: Account<'info, PlayerState>,
+    
+    #[account(
+        mut,
+        seeds = [b"tournament_entry", tournament.key().as_ref(), player.key().as_ref()],
+        bump = entry.bump,
+    )]
+    pub entry: Account<'info, TournamentEntry>,
+    
+    /// CHECK: Receives the closed PlayerState rent; tied to the PDAs above by seeds
+    #[account(mut)]
+    pub player: UncheckedAccount<'info>,
 }
 
-/*
-// #[init_computation_definition_accounts("poker", payer)]
 #[derive(Accounts)]
-pub struct InitPokerCompDef<'info> {
-...
+pub struct ClaimTournamentPrize<'info> {
+    #[account(mut)]
+    pub tournament: Account<'info, Tournament>,
+    
+    #[account(
+        mut,
+        seeds = [b"tournament_entry", tournament.key().as_ref(), player.key().as_ref()],
+        bump = entry.bump,
+    )]
+    pub entry: Account<'info, TournamentEntry>,
+    
+    #[account(mut)]
+    pub player: Signer<'info>,
 }
-*/
 
 #[derive(Accounts)]
-pub struct Showdown<'info> {
+#[instruction(waitlist_id: u64)]
+pub struct CreateWaitlist<'info> {
+    #[account(
+        seeds = [b"shadow_poker_house"],
+        bump = house.bump,
+        has_one = authority @ ShadowPokerError::UnauthorizedHouse,
+    )]
+    pub house: Account<'info, ShadowPokerHouse>,
+    
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + Waitlist::SIZE,
+        seeds = [b"waitlist".as_ref(), waitlist_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub waitlist: Account<'info, Waitlist>,
+    
     #[account(mut)]
-    pub table: Account<'info, Table>,
+    pub authority: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct JoinWaitlist<'info> {
+    #[account(mut)]
+    pub waitlist: Account<'info, Waitlist>,
+    
+    #[account(
+        init,
+        payer = player,
+        space = 8 + WaitlistEntry::SIZE,
+        seeds = [b"waitlist_entry".as_ref(), waitlist.key().as_ref(), player.key().as_ref()],
+        bump
+    )]
+    pub entry: Account<'info, WaitlistEntry>,
     
     #[account(mut)]
-    pub house: Account<'info, ShadowPokerHouse>,
+    pub player: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
 
+#[derive(Accounts)]
+pub struct LeaveWaitlist<'info> {
+    #[account(mut)]
+    pub waitlist: Account<'info, Waitlist>,
+    
+    /// Closing the entry returns the escrowed buy-in with its rent
     #[account(
         mut,
-        seeds = [b"player_state", table.key().as_ref(), winner_state.player.as_ref()],
-        bump = winner_state.bump
+        seeds = [b"waitlist_entry", waitlist.key().as_ref(), player.key().as_ref()],
+        bump = entry.bump,
+        close = player,
     )]
-    pub winner_state: Account<'info, PlayerState>,
+    pub entry: Account<'info, WaitlistEntry>,
     
-    pub authority: Signer<'info>,
+    #[account(mut)]
+    pub player: Signer<'info>,
 }
 
 #[derive(Accounts)]
-pub struct LeaveTable<'info> {
+pub struct SpawnWaitlistTable<'info> {
     #[account(mut)]
+    pub waitlist: Account<'info, Waitlist>,
+    
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + Table::space(waitlist.template.max_players),
+        seeds = [b"table".as_ref(), house.total_tables.to_le_bytes().as_ref()],
+        bump
+    )]
     pub table: Account<'info, Table>,
     
     #[account(
         mut,
-        seeds = [b"player_state", table.key().as_ref(), player.key().as_ref()],
-        bump = player_state.bump,
+        seeds = [b"shadow_poker_house"],
+        bump = house.bump,
     )]
-    pub player_state: Account<'info, PlayerState>,
+    pub house: Account<'info, ShadowPokerHouse>,
     
     #[account(mut)]
-    pub player: Signer<'info>,
+    pub payer: Signer<'info>,
     
     pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-pub struct WithdrawTreasury<'info> {
+pub struct SeatFromWaitlist<'info> {
+    #[account(mut)]
+    pub waitlist: Account<'info, Waitlist>,
+    
+    /// Entry of the first player in the queue who isn't at `table`
     #[account(
         mut,
-        seeds = [b"shadow_poker_house"],
-        bump = house.bump,
+        seeds = [b"waitlist_entry", waitlist.key().as_ref(), entry.player.as_ref()],
+        bump = entry.bump,
+        close = player,
     )]
-    pub house: Account<'info, ShadowPokerHouse>,
+    pub entry: Account<'info, WaitlistEntry>,
+    
+    #[account(
+        mut,
+        constraint = waitlist.tables.contains(&table.key()) @ ShadowPokerError::WrongWaitlistTable,
+    )]
+    pub table: Account<'info, Table>,
+    
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + PlayerState::SIZE,
+        seeds = [b"player_state".as_ref(), table.key().as_ref(), entry.player.as_ref()],
+        bump
+    )]
+    pub player_state: Account<'info, PlayerState>,
+    
+    /// CHECK: the queued player, who gets the entry's rent back
+    #[account(mut, address = entry.player)]
+    pub player: UncheckedAccount<'info>,
     
     #[account(mut)]
-    pub authority: Signer<'info>,
+    pub payer: Signer<'info>,
     
     pub system_program: Program<'info, System>,
 }
@@ -579,12 +5043,97 @@ impl ShadowPokerHouse {
     pub const SIZE: usize = 32 + 8 + 8 + 8 + 1;
 }
 
-#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+#[account]
+pub struct HouseDealer {
+    pub dealer: Pubkey,
+    pub bump: u8,
+}
+
+impl HouseDealer {
+    pub const SIZE: usize = 32 + 1;
+}
+
+#[account]
+pub struct TableAccess {
+    pub table: Pubkey,
+    pub password_hash: Option<[u8; 32]>, // blake3(table || password)
+    pub allowlist: Vec<Pubkey>,
+    pub invite_mint: Option<Pubkey>,     // Joining players must hold a token of this mint
+    pub bump: u8,
+}
+
+impl TableAccess {
+    pub const SIZE: usize = 32 + (1 + 32) + (4 + MAX_ALLOWLIST * 32) + (1 + 32) + 1;
+
+    /// Check a joining player against every configured restriction.
+    ///
+    /// The password is revealed in the join transaction, so it keeps out
+    /// strangers but should be rotated if the table is reused.
+    pub fn check(
+        &self,
+        table: &Pubkey,
+        player: &Pubkey,
+        password: Option<&[u8]>,
+        invite_token: Option<&TokenAccount>,
+    ) -> Result<()> {
+        if let Some(expected) = self.password_hash {
+            let password = password.ok_or(ShadowPokerError::WrongPassword)?;
+            let mut hasher = blake3::Hasher::new();
+            hasher.update(table.as_ref());
+            hasher.update(password);
+            require!(
+                *hasher.finalize().as_bytes() == expected,
+                ShadowPokerError::WrongPassword
+            );
+        }
+        if !self.allowlist.is_empty() {
+            require!(
+                self.allowlist.contains(player),
+                ShadowPokerError::NotOnAllowlist
+            );
+        }
+        if let Some(mint) = self.invite_mint {
+            let token = invite_token.ok_or(ShadowPokerError::MissingInviteToken)?;
+            require!(
+                token.mint == mint && token.owner == *player && token.amount > 0,
+                ShadowPokerError::MissingInviteToken
+            );
+        }
+        Ok(())
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
 pub struct Card {
     pub suit: u8, // 0-3 (hearts, diamonds, clubs, spades)
     pub rank: u8, // 2-14 (2-10, J=11, Q=12, K=13, A=14)
 }
 
+impl Card {
+    pub fn is_in_deck(&self, variant: GameVariant) -> bool {
+        self.suit < 4 && (variant.lowest_rank()..=14).contains(&self.rank)
+    }
+}
+
+/// Optional game features a cash table can offer.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct TableOptions {
+    pub straddle: bool,     // The player after the big blind may post a live straddle
+    pub run_it_twice: bool, // All-in hands deal the rest of the board twice and split the pot
+    pub insurance: bool,    // The house may offer all-in players insurance
+}
+
+/// Returned by `legal_actions`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct ActionOptions {
+    pub is_turn: bool,
+    pub can_check: bool,
+    pub call_amount: u64,  // All-in for less when the stack is short
+    pub can_raise: bool,
+    pub min_raise_amount: u64,
+    pub max_raise_amount: u64,
+}
+
 #[account]
 pub struct Table {
     pub creator: Pubkey,
@@ -592,24 +5141,181 @@ pub struct Table {
     pub max_buy_in: u64,
     pub small_blind: u64,
     pub big_blind: u64,
+    pub ante: u64,
+    pub variant: GameVariant,
+    pub betting: BettingStructure,
+    pub dealing_mode: DealingMode,
+    pub options: TableOptions,
+    pub chip_mint: Option<Pubkey>,  // SPL mint the table plays in; lamports when None
+    pub play_money: bool,           // House play chips: burned on buy-in, minted on cash-out, never raked
+    pub vault_bump: u8,             // Token tables: bump of the `[b"vault", table]` escrow
+    pub rake_held: u64,             // Token tables: rake in the vault waiting for the house to collect
+    pub tournament: Option<Pubkey>, // Set for tournament tables, whose stacks are chips rather than lamports
+    pub access_restricted: bool,    // Joining is checked against the `TableAccess` account
     pub max_players: u8,
-    pub players: Vec<Pubkey>,
+    pub players: Vec<Pubkey>,  // One slot per seat, `Pubkey::default()` when empty
     pub status: TableStatus,
     pub pot: u64,
     pub current_bet: u64,
+    pub min_raise: u64,     // Smallest raise increment allowed this street
+    pub raises: u8,         // Raises made this street (capped under fixed limit)
+    pub betting_round: u32, // Increases every street; player bets from older rounds are stale
     pub community_cards: Vec<Card>,
+    pub run_twice_from: Option<u8>, // Board length when the hand went to be run twice
+    pub second_board: Vec<Card>,    // Full second board, sharing the cards dealt before the run
+    pub aggressor_seat: u8,         // Last seat to bet or raise this hand (the big blind preflop)
+    pub postflop_aggression: bool,  // Anyone has bet or raised since the flop
     pub dealer_index: u8,
     pub current_player_index: u8,
+    pub action_deadline_slot: u64, // Slot after which the current player can be timed out
+    pub small_blind_index: u8,
+    pub big_blind_index: u8,
+    pub hand_number: u64,
+    pub active_mask: u16,  // Seats still in the hand (bit per seat)
+    pub all_in_mask: u16,  // Seats that are all-in
     pub created_at_slot: u64,
     pub house_fee: u64,
+    pub rake_bps: u16,
+    pub rake_cap: u64,       // Maximum rake per hand, in the table's chips
+    pub rakeback_bps: u16,   // Share of each player's rake returned to them
+    pub event_seq: u32,             // Hand-history events emitted this hand
+    pub history_hash: [u8; 32],     // Running hash of this hand's events
+    pub last_hand_hash: [u8; 32],   // History hash of the last completed hand
     pub deck_commitment: [u8; 32],
     pub last_proof_timestamp: i64,
     pub bump: u8,
 }
 
 impl Table {
-    // Base size + space for up to 6 players + 5 community cards + Arcium fields (32 + 8)
-    pub const SIZE: usize = 32 + 8 + 8 + 8 + 8 + 1 + (4 + 6 * 32) + 1 + 8 + 8 + (4 + 5 * 2) + 1 + 1 + 8 + 8 + 1 + 32 + 8;
+    // Base size + options + chip mint + tournament link + empty seat vector + 2 boards of 5 cards + blind/seat tracking + Arcium fields (32 + 8)
+    pub const BASE_SIZE: usize = 32 + 8 + 8 + 8 + 8 + 8 + 1 + 2 + 1 + 3 + (1 + 32) + 1 + 1 + 8 + (1 + 32) + 1 + 1 + 4 + 1 + 8 + 8 + 8 + 1 + 4 + (4 + 5 * 2) + 2 + (4 + 5 * 2) + 1 + 1 + 1 + 1 + 8 + 1 + 1 + 8 + 2 + 2 + 8 + 8 + 2 + 8 + 2 + 4 + 32 + 32 + 1 + 32 + 8;
+
+    /// Account size for a table with `max_players` seats.
+    pub const fn space(max_players: u8) -> usize {
+        Self::BASE_SIZE + max_players as usize * 32
+    }
+
+    /// Set up an empty table with every seat free and no hand played yet.
+    pub fn open(&mut self, creator: Pubkey, max_players: u8, slot: u64, bump: u8) {
+        self.creator = creator;
+        self.max_players = max_players;
+        self.options = TableOptions::default();
+        self.chip_mint = None;
+        self.play_money = false;
+        self.vault_bump = 0;
+        self.rake_held = 0;
+        self.players = vec![Pubkey::default(); max_players as usize];
+        self.status = TableStatus::Waiting;
+        self.pot = 0;
+        self.current_bet = 0;
+        self.min_raise = 0;
+        self.raises = 0;
+        self.betting_round = 0;
+        self.community_cards = vec![];
+        self.run_twice_from = None;
+        self.second_board = vec![];
+        self.aggressor_seat = 0;
+        self.postflop_aggression = false;
+        self.dealer_index = 0;
+        self.current_player_index = 0;
+        self.action_deadline_slot = 0;
+        self.small_blind_index = 0;
+        self.big_blind_index = 0;
+        self.hand_number = 0;
+        self.active_mask = 0;
+        self.all_in_mask = 0;
+        self.created_at_slot = slot;
+        self.house_fee = 0;
+        self.rake_bps = HOUSE_FEE_BPS;
+        self.rake_cap = DEFAULT_RAKE_CAP;
+        self.rakeback_bps = 0;
+        self.event_seq = 0;
+        self.history_hash = [0u8; 32];
+        self.last_hand_hash = [0u8; 32];
+        self.bump = bump;
+    }
+
+    /// Rake for the pot being awarded, leaving out the `uncalled` bet that
+    /// goes back to its owner: capped per hand, nothing from tournament or
+    /// play chips, and no flop, no drop.
+    pub fn rake_for_current_hand(&self, uncalled: u64) -> u64 {
+        if self.tournament.is_some() || self.play_money || self.community_cards.is_empty() {
+            return 0;
+        }
+        let raked = self.pot.saturating_sub(uncalled);
+        ((raked as u128 * self.rake_bps as u128 / 10000) as u64).min(self.rake_cap)
+    }
+
+    /// Number of occupied seats.
+    pub fn seated_count(&self) -> usize {
+        self.players.iter().filter(|p| **p != Pubkey::default()).count()
+    }
+
+    /// Next seat after `from` (wrapping around the table) whose bit is set in `mask`.
+    pub fn next_seat(&self, from: u8, mask: u16) -> Option<u8> {
+        next_seat_in(self.players.len() as u8, from, mask)
+    }
+
+    /// Seats that are still in the hand and not all-in.
+    pub fn actionable_mask(&self) -> u16 {
+        self.active_mask & !self.all_in_mask
+    }
+
+    /// Whether a hand is underway, including a mental poker shuffle.
+    pub fn hand_running(&self) -> bool {
+        matches!(
+            self.status,
+            TableStatus::Shuffling | TableStatus::Dealing | TableStatus::Betting
+        )
+    }
+
+    /// Whether the betting round is over: everyone who can still act has
+    /// acted this street and matched the bet. `states` are the table's
+    /// player states.
+    pub fn round_complete(&self, states: &[Account<PlayerState>]) -> bool {
+        states
+            .iter()
+            .filter(|s| self.actionable_mask() & (1 << s.seat_index) != 0)
+            .all(|s| {
+                s.bet_round == self.betting_round
+                    && s.has_acted
+                    && s.current_bet == self.current_bet
+            })
+    }
+
+    /// Smallest and largest total a player with `bet` in front of them and
+    /// `stack` behind may bet or raise to this street, or `None` if they
+    /// can only call or fold. A player short of the minimum may still go
+    /// all-in.
+    pub fn raise_bounds(&self, bet: u64, stack: u64) -> Option<(u64, u64)> {
+        let all_in = bet + stack;
+        if all_in <= self.current_bet {
+            return None;
+        }
+        let (min, max) = match self.betting {
+            BettingStructure::NoLimit => (self.current_bet + self.min_raise, all_in),
+            BettingStructure::PotLimit => {
+                // Raise at most the size of the pot after calling
+                let to_call = self.current_bet - bet;
+                let pot_raise = self.current_bet + self.pot + to_call;
+                (self.current_bet + self.min_raise, pot_raise.min(all_in))
+            }
+            BettingStructure::FixedLimit { max_raises } => {
+                if self.current_bet > 0 && self.raises >= max_raises {
+                    return None;
+                }
+                // Small bets before the turn, big bets from the turn on
+                let size = if self.community_cards.len() >= 4 {
+                    self.big_blind * 2
+                } else {
+                    self.big_blind
+                };
+                let total = self.current_bet + size;
+                (total, total.min(all_in))
+            }
+        };
+        Some((min.min(max), max))
+    }
 }
 
 /*
@@ -634,15 +5340,379 @@ impl HasSize for ShowdownOutput {
 pub struct PlayerState {
     pub player: Pubkey,
     pub table: Pubkey,
+    pub seat_index: u8,
     pub stack: u64,
     pub current_bet: u64,
+    pub bet_round: u32,         // `Table::betting_round` that `current_bet` belongs to
     pub is_active: bool,
     pub has_acted: bool,
+    pub is_all_in: bool,
+    pub posted_blind: u64,      // Big blind posted out of position, played live next hand
+    pub straddle: u64,          // Straddle posted for the next hand
+    pub straddle_session: Option<Pubkey>, // Session token the straddle was charged to
+    pub needs_big_blind: bool,  // New player waiting for the big blind to reach them
+    pub timeout_strikes: u8,    // Consecutive turns lost to `enforce_timeout`
+    pub time_bank_slots: u64,   // Extra thinking time, topped up every hand
+    pub disconnect_protections: u8, // Timeouts left this session that go all-in instead of folding
+    pub is_sitting_out: bool,   // Dealt out of new hands
+    pub leave_pending: bool,    // Folded out of a running hand, cashes out once it ends
+    pub hand_contribution: u64, // Chips put into the current hand's pot
+    pub rake_paid: u64,         // Lifetime rake attributed to this player at this table
+    pub rakeback_owed: u64,     // Claimable rake-back, paid from the house account
+    pub opponents: Vec<OpponentStats>, // Per seat, this player's record against whoever sits there
+    pub insurance: Option<InsuranceOffer>, // All-in insurance offered or bought this hand
     pub bump: u8,
 }
 
 impl PlayerState {
-    pub const SIZE: usize = 32 + 32 + 8 + 8 + 1 + 1 + 1;
+    pub const SIZE: usize = 32 + 32 + 1 + 8 + 8 + 4 + 1 + 1 + 1 + 8 + 8 + (1 + 32)
+        + 1 + 1 + 8 + 1 + 1 + 1 + 8 + 8 + 8
+        + (4 + MAX_PLAYERS as usize * OpponentStats::SIZE) + (1 + InsuranceOffer::SIZE) + 1;
+
+    /// This player's stats against `opponent` in `seat`, started afresh
+    /// when someone new has taken the seat.
+    pub fn opponent_mut(&mut self, seat: u8, opponent: Pubkey) -> &mut OpponentStats {
+        let stats = &mut self.opponents[seat as usize];
+        if stats.opponent != opponent {
+            *stats = OpponentStats {
+                opponent,
+                ..Default::default()
+            };
+        }
+        stats
+    }
+
+    /// Chips this player has in front of them in the table's current
+    /// betting round.
+    pub fn street_bet(&self, table: &Table) -> u64 {
+        if self.bet_round == table.betting_round {
+            self.current_bet
+        } else {
+            0
+        }
+    }
+
+    /// Clear a bet and action left over from an earlier street.
+    pub fn sync_round(&mut self, table: &Table) {
+        if self.bet_round != table.betting_round {
+            self.current_bet = 0;
+            self.has_acted = false;
+            self.bet_round = table.betting_round;
+        }
+    }
+}
+
+/// Encrypted deck of a mental poker table, reused from hand to hand.
+///
+/// Deck positions are dealt in shuffle order: each shuffler's hole cards
+/// first, then the board.
+#[account]
+pub struct Deck {
+    pub table: Pubkey,
+    pub variant: GameVariant,
+    pub ready: bool,                 // Card points loaded and checked
+    pub card_points: Vec<[u8; 32]>,  // Unencrypted cards of the variant, in card order
+    pub hand_number: u64,
+    pub shufflers: Vec<u8>,          // Seats dealt in, in shuffle order (left of the button first)
+    pub public_keys: Vec<[u8; 32]>,  // Per shuffler, `k * G`
+    pub commitments: Vec<[u8; 32]>,  // Per shuffler, `blake3(k || permutation)`
+    pub stages: Vec<Vec<[u8; 32]>>,  // Deck after each shuffle
+    pub pending: Vec<[u8; 32]>,      // Shuffle being uploaded
+    pub cards: Vec<[u8; 32]>,        // Final deck with decryption shares applied
+    pub share_masks: Vec<u16>,       // Per position, seats whose share has been applied
+    pub requested: u8,               // Positions open for decryption
+    pub verified_mask: u16,          // Seats whose shuffle checked out
+    pub cheated_mask: u16,           // Seats whose shuffle failed verification
+    pub shown_mask: u16,             // Seats that showed down
+    pub deadline_slot: u64,          // When the pending shuffle or shares are due
+    pub bump: u8,
+}
+
+impl Deck {
+    const POINTS_SIZE: usize = 4 + mental_poker::FULL_DECK * 32;
+    // Sized for a full table of shufflers and a full deck
+    pub const SIZE: usize = 32 + 1 + 1 + Self::POINTS_SIZE + 8
+        + (4 + MAX_MENTAL_POKER_PLAYERS as usize)
+        + 2 * (4 + MAX_MENTAL_POKER_PLAYERS as usize * 32)
+        + (4 + MAX_MENTAL_POKER_PLAYERS as usize * Self::POINTS_SIZE)
+        + 2 * Self::POINTS_SIZE + (4 + mental_poker::FULL_DECK * 2) + 1 + 2 + 2 + 2 + 8 + 1;
+
+    /// Clear the previous hand and wait for `shufflers` to shuffle in turn.
+    pub fn reset(&mut self, hand_number: u64, shufflers: Vec<u8>, deadline_slot: u64) {
+        self.hand_number = hand_number;
+        self.shufflers = shufflers;
+        self.public_keys = vec![];
+        self.commitments = vec![];
+        self.stages = vec![];
+        self.pending = vec![];
+        self.cards = vec![];
+        self.share_masks = vec![];
+        self.requested = 0;
+        self.verified_mask = 0;
+        self.cheated_mask = 0;
+        self.shown_mask = 0;
+        self.deadline_slot = deadline_slot;
+    }
+
+    pub fn shuffler_index(&self, seat: u8) -> Option<usize> {
+        self.shufflers.iter().position(|s| *s == seat)
+    }
+
+    pub fn shuffler_mask(&self) -> u16 {
+        self.shufflers.iter().fold(0, |mask, seat| mask | (1 << seat))
+    }
+
+    pub fn hole_card_count(&self) -> usize {
+        self.shufflers.len() * self.variant.hole_cards() as usize
+    }
+
+    /// Deck positions of a seat's hole cards.
+    pub fn hole_positions(&self, seat: u8) -> Option<std::ops::Range<usize>> {
+        let per_player = self.variant.hole_cards() as usize;
+        let first = self.shuffler_index(seat)? * per_player;
+        Some(first..first + per_player)
+    }
+
+    /// Deck position of the next board card when `board_len` are out.
+    pub fn board_position(&self, board_len: usize) -> usize {
+        self.hole_card_count() + board_len
+    }
+
+    /// The card at `position`, once every layer of encryption is off.
+    pub fn decrypted_card(&self, position: usize) -> Option<Card> {
+        let point = self.cards.get(position)?;
+        let index = self.card_points.iter().position(|p| p == point)?;
+        mental_poker::deck_indices(self.variant)
+            .nth(index)
+            .map(mental_poker::card_at)
+    }
+
+    /// Seats holding up the hand: the shuffler whose turn it is, or anyone
+    /// missing a decryption share for an open position. Nobody owes a share
+    /// for their own hole cards.
+    pub fn withholding(&self) -> u16 {
+        if let Some(seat) = self.shufflers.get(self.stages.len()) {
+            return 1 << seat;
+        }
+        let per_player = self.variant.hole_cards() as usize;
+        let everyone = self.shuffler_mask();
+        (0..self.requested as usize).fold(0, |missing, position| {
+            let owner = match self.shufflers.get(position / per_player) {
+                Some(seat) if position < self.hole_card_count() => 1 << seat,
+                _ => 0,
+            };
+            missing | (everyone & !owner & !self.share_masks[position])
+        })
+    }
+
+    /// Seats holding the best hand on `board` among players in `eligible`
+    /// who are still in and showed. If none of them showed, the first of
+    /// them left of the button takes the pot.
+    pub fn showdown_winners(&self, table: &Table, board: &[Card], eligible: u16) -> u16 {
+        let contenders = table.active_mask & eligible;
+        let mut best = None;
+        let mut winners = 0u16;
+        for &seat in self.shufflers.iter() {
+            if (contenders & self.shown_mask) & (1 << seat) == 0 {
+                continue;
+            }
+            let hole: Option<Vec<Card>> = self
+                .hole_positions(seat)
+                .into_iter()
+                .flatten()
+                .map(|position| self.decrypted_card(position))
+                .collect();
+            let value = hole.and_then(|hole| {
+                hand_eval::best_hand(self.variant, &hole, board)
+            });
+            if value > best {
+                best = value;
+                winners = 1 << seat;
+            } else if value.is_some() && value == best {
+                winners |= 1 << seat;
+            }
+        }
+        if winners == 0 {
+            if let Some(seat) = table.next_seat(table.dealer_index, contenders) {
+                winners = 1 << seat;
+            }
+        }
+        winners
+    }
+}
+
+/// A player's decryption share for one deck position, with its
+/// Chaum-Pedersen proof (see `mental_poker::verify_share`).
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct DecryptionShare {
+    pub position: u8,
+    pub point: [u8; 32],
+    pub challenge: [u8; 32],
+    pub response: [u8; 32],
+}
+
+#[account]
+pub struct Tournament {
+    pub creator: Pubkey,
+    pub tournament_id: u64,
+    pub entry_fee: u64,
+    pub starting_stack: u64,      // Chips each entrant starts with
+    pub max_entrants: u16,
+    pub variant: GameVariant,
+    pub betting: BettingStructure,
+    pub entrants: u16,
+    pub players_remaining: u16,
+    pub prize_pool: u64,          // Entry fees net of the house fee, fixed at start
+    pub house_fee: u64,
+    pub status: TournamentStatus,
+    pub level_duration_slots: u64,
+    pub blind_levels: Vec<BlindLevel>,
+    pub current_level: u8,
+    pub started_at_slot: u64,
+    pub payout_bps: Vec<u16>,     // Share of the prize pool per finishing place
+    pub tables: Vec<Pubkey>,
+    pub table_size: u8,           // Seats at each tournament table, set by the first one
+    pub bump: u8,
+}
+
+impl Tournament {
+    // Base size + space for the maximum blind schedule, payout places and tables
+    pub const SIZE: usize = 32 + 8 + 8 + 8 + 2 + 1 + 2 + 2 + 2 + 8 + 8 + 1 + 8
+        + (4 + MAX_BLIND_LEVELS * BlindLevel::SIZE) + 1 + 8
+        + (4 + MAX_PAYOUT_PLACES * 2) + (4 + MAX_TOURNAMENT_TABLES * 32) + 1 + 1;
+
+    /// Prize for a finishing position (1 = winner). The winner also takes
+    /// whatever rounding leaves over from the other places.
+    pub fn prize_for(&self, position: u16) -> u64 {
+        let share = |bps: &u16| (self.prize_pool as u128 * *bps as u128 / 10_000) as u64;
+        match position {
+            0 => 0,
+            1 => self.prize_pool - self.payout_bps[1..].iter().map(share).sum::<u64>(),
+            _ => self.payout_bps.get(position as usize - 1).map(share).unwrap_or(0),
+        }
+    }
+}
+
+#[account]
+pub struct TournamentEntry {
+    pub tournament: Pubkey,
+    pub player: Pubkey,
+    pub entry_index: u16,         // Registration order, used for initial seating
+    pub table: Pubkey,            // Current table, `Pubkey::default()` when unseated
+    pub finish_position: u16,     // 0 while still in the tournament
+    pub claimed: bool,
+    pub bump: u8,
+}
+
+impl TournamentEntry {
+    pub const SIZE: usize = 32 + 32 + 2 + 32 + 2 + 1 + 1;
+}
+
+/// Settings a waitlist creates its tables with. Waitlist tables play in
+/// SOL with an operator dealer.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct TableTemplate {
+    pub min_buy_in: u64,
+    pub max_buy_in: u64,
+    pub small_blind: u64,
+    pub big_blind: u64,
+    pub ante: u64,
+    pub max_players: u8,
+    pub variant: GameVariant,
+    pub betting: BettingStructure,
+}
+
+impl TableTemplate {
+    pub const SIZE: usize = 8 + 8 + 8 + 8 + 8 + 1 + 1 + 2;
+
+    /// Same rules as `create_table` for a SOL table.
+    pub fn validate(&self) -> Result<()> {
+        require_valid_game(self.variant, self.betting)?;
+        require!(
+            (MIN_PLAYERS..=MAX_PLAYERS).contains(&self.max_players),
+            ShadowPokerError::InvalidPlayerCount
+        );
+        require!(
+            self.small_blind > 0 && self.small_blind <= self.big_blind && self.ante <= self.small_blind,
+            ShadowPokerError::InvalidBlinds
+        );
+        require!(self.min_buy_in >= MIN_BUY_IN, ShadowPokerError::BuyInTooSmall);
+        require!(self.max_buy_in <= MAX_BUY_IN, ShadowPokerError::BuyInTooLarge);
+        require!(self.min_buy_in < self.max_buy_in, ShadowPokerError::InvalidBuyInRange);
+        Ok(())
+    }
+}
+
+/// Queue of players waiting for a seat at one stake level.
+#[account]
+pub struct Waitlist {
+    pub authority: Pubkey,
+    pub waitlist_id: u64,
+    pub template: TableTemplate,
+    pub spawn_threshold: u8,  // Players waiting before a new table may be opened
+    pub queue: Vec<Pubkey>,   // Players in the order they are seated
+    pub tables: Vec<Pubkey>,  // Tables opened from the template
+    pub bump: u8,
+}
+
+impl Waitlist {
+    pub const SIZE: usize = 32 + 8 + TableTemplate::SIZE + 1
+        + (4 + MAX_WAITLIST * 32) + (4 + MAX_WAITLIST_TABLES * 32) + 1;
+}
+
+/// A queued player; the account holds their buy-in in escrow.
+#[account]
+pub struct WaitlistEntry {
+    pub waitlist: Pubkey,
+    pub player: Pubkey,
+    pub buy_in: u64,
+    pub bump: u8,
+}
+
+impl WaitlistEntry {
+    pub const SIZE: usize = 32 + 32 + 8 + 1;
+}
+
+/// House insurance on an all-in player's hand: the player pays `premium`
+/// and is paid `coverage` if they lose (half of it per board lost when the
+/// hand is run twice).
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct InsuranceOffer {
+    pub premium: u64,
+    pub coverage: u64,
+    pub board_len: u8, // Board the offer was priced on
+    pub accepted: bool,
+}
+
+impl InsuranceOffer {
+    pub const SIZE: usize = 8 + 8 + 1 + 1;
+}
+
+/// How one player has fared against another at the same table, for
+/// off-chain collusion and chip-dumping detection.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct OpponentStats {
+    pub opponent: Pubkey,
+    pub hands_together: u32,  // Hands both were dealt into
+    pub chips_won: u64,       // Chips the opponent put into pots this player won
+    pub chips_lost: u64,      // Chips this player put into pots the opponent won
+    pub small_bet_folds: u32, // Postflop folds to a small bet from the opponent
+    pub soft_play_hands: u32, // Hands checked down heads-up with no bet after the flop
+}
+
+impl OpponentStats {
+    pub const SIZE: usize = 32 + 4 + 8 + 8 + 4 + 4;
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy)]
+pub struct BlindLevel {
+    pub small_blind: u64,
+    pub big_blind: u64,
+    pub ante: u64,
+}
+
+impl BlindLevel {
+    pub const SIZE: usize = 8 + 8 + 8;
 }
 
 // Enums
@@ -653,12 +5723,64 @@ pub enum TableStatus {
     Dealing,
     Betting,
     Finished,
+    Shuffling, // Mental poker players are shuffling and dealing hole cards
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum DealingMode {
+    Dealer,      // Cards dealt by the table operator (Arcium MPC)
+    MentalPoker, // Players shuffle and deal with commutative encryption
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum GameVariant {
+    Holdem,
+    PotLimitOmaha, // Four hole cards, exactly two of them played
+    ShortDeck,     // 36-card deck with the twos through fives removed
+}
+
+impl GameVariant {
+    /// Hole cards dealt to each player.
+    pub fn hole_cards(&self) -> u8 {
+        match self {
+            GameVariant::PotLimitOmaha => 4,
+            GameVariant::Holdem | GameVariant::ShortDeck => 2,
+        }
+    }
+
+    /// Lowest card rank in the deck.
+    pub fn lowest_rank(&self) -> u8 {
+        match self {
+            GameVariant::ShortDeck => 6,
+            GameVariant::Holdem | GameVariant::PotLimitOmaha => 2,
+        }
+    }
+
+    /// Cards in the deck.
+    pub fn deck_size(&self) -> u8 {
+        4 * (15 - self.lowest_rank())
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BettingStructure {
+    NoLimit,
+    PotLimit,
+    FixedLimit { max_raises: u8 }, // Raises allowed per street after the opening bet
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum TournamentStatus {
+    Registering,
+    Running,
+    Finished,
 }
 
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
 pub enum BlindType {
     Small,
     Big,
+    Straddle,
 }
 
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
@@ -670,11 +5792,184 @@ pub enum PlayerActionType {
     Fold,
 }
 
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ForcedBetKind {
+    Ante,
+    SmallBlind,
+    BigBlind,
+    Posted, // Big blind posted out of position before the hand
+    Straddle,
+}
+
+// Events
+//
+// Every hand emits an ordered, hash-chained log of these events (see
+// `chain_history`); `HandCompleted` carries the final hash, which is also
+// stored on the table as `last_hand_hash`.
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct SeatSnapshot {
+    pub seat: u8,
+    pub player: Pubkey,
+    pub stack: u64, // Stack before antes and blinds, excluding a blind posted before the hand
+}
+
+#[event]
+#[derive(Clone, Debug)]
+pub struct HandStarted {
+    pub table: Pubkey,
+    pub hand_number: u64,
+    pub seq: u32,
+    pub max_players: u8,
+    pub variant: GameVariant,
+    pub betting: BettingStructure,
+    pub button: u8,
+    pub small_blind_seat: u8,
+    pub big_blind_seat: u8,
+    pub small_blind: u64,
+    pub big_blind: u64,
+    pub ante: u64,
+    pub rake_bps: u16,
+    pub rake_cap: u64,
+    pub is_tournament: bool,
+    pub play_money: bool,
+    pub seats: Vec<SeatSnapshot>,
+}
+
+#[event]
+#[derive(Clone, Debug)]
+pub struct ForcedBetPosted {
+    pub table: Pubkey,
+    pub hand_number: u64,
+    pub seq: u32,
+    pub seat: u8,
+    pub kind: ForcedBetKind,
+    pub amount: u64,
+}
+
+#[event]
+#[derive(Clone, Debug)]
+pub struct ActionTaken {
+    pub table: Pubkey,
+    pub hand_number: u64,
+    pub seq: u32,
+    pub seat: u8,
+    pub action: PlayerActionType,
+    pub amount: u64, // Chips moved from the stack into the pot
+    pub timed_out: bool,
+    pub protected: bool, // Timed out facing a bet and kept in as all-in by disconnect protection
+}
+
+#[event]
+#[derive(Clone, Debug)]
+pub struct BoardDealt {
+    pub table: Pubkey,
+    pub hand_number: u64,
+    pub seq: u32,
+    pub cards: Vec<Card>,
+    pub second_run: bool, // Cards of the second board when the hand is run twice
+}
+
+#[event]
+#[derive(Clone, Debug)]
+pub struct CardsShown {
+    pub table: Pubkey,
+    pub hand_number: u64,
+    pub seq: u32,
+    pub seat: u8,
+    pub cards: Vec<Card>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SignalKind {
+    ChipsWon,     // `player` won `amount` of the opponent's chips
+    SmallBetFold, // `player` folded to a bet of `amount` from the opponent
+    SoftPlay,     // The pair checked down a pot of `amount` heads-up
+}
+
+/// Pairwise play signal for the off-chain risk engine. Not part of the
+/// hand history; `stats` is the updated record of `player` against
+/// `opponent`.
+#[event]
+#[derive(Clone, Debug)]
+pub struct CollusionSignal {
+    pub table: Pubkey,
+    pub hand_number: u64,
+    pub kind: SignalKind,
+    pub player: Pubkey,
+    pub opponent: Pubkey,
+    pub amount: u64,
+    pub stats: OpponentStats,
+}
+
+#[event]
+#[derive(Clone, Debug)]
+pub struct PotAwarded {
+    pub table: Pubkey,
+    pub hand_number: u64,
+    pub seq: u32,
+    pub winner_seat: u8,
+    pub pot: u8, // 0 for the main pot, then each side pot in turn
+    pub amount: u64,
+    pub rake: u64,
+    pub second_run: bool, // Share of the pot won on the second board
+}
+
+#[event]
+#[derive(Clone, Debug)]
+pub struct TimeBankUsed {
+    pub table: Pubkey,
+    pub hand_number: u64,
+    pub seat: u8,
+    pub slots: u64,
+    pub remaining: u64,
+}
+
+#[event]
+#[derive(Clone, Debug)]
+pub struct InsuranceOffered {
+    pub table: Pubkey,
+    pub hand_number: u64,
+    pub seat: u8,
+    pub premium: u64,
+    pub coverage: u64,
+}
+
+#[event]
+#[derive(Clone, Debug)]
+pub struct InsuranceSettled {
+    pub table: Pubkey,
+    pub hand_number: u64,
+    pub seat: u8,
+    pub premium: u64,
+    pub coverage: u64,
+    pub paid: u64,
+}
+
+#[event]
+#[derive(Clone, Debug)]
+pub struct HandAborted {
+    pub table: Pubkey,
+    pub hand_number: u64,
+    pub seq: u32,
+    pub offenders: u16, // Seat bitmask
+    pub penalties: u64, // Total taken from offenders and shared among the others
+}
+
+#[event]
+#[derive(Clone, Debug)]
+pub struct HandCompleted {
+    pub table: Pubkey,
+    pub hand_number: u64,
+    pub events: u32,
+    pub history_hash: [u8; 32],
+}
+
 // Errors
 
 #[error_code]
 pub enum ShadowPokerError {
-    #[msg("Invalid player count (2-6 allowed)")]
+    #[msg("Invalid player count (2-10 allowed)")]
     InvalidPlayerCount,
     #[msg("Buy-in too small")]
     BuyInTooSmall,
@@ -722,4 +6017,152 @@ pub enum ShadowPokerError {
     InvalidShowdownProof,
     #[msg("Cluster not set for MXE")]
     ClusterNotSet,
+    #[msg("Invalid blind or ante amounts")]
+    InvalidBlinds,
+    #[msg("Blinds are posted automatically when the hand starts")]
+    BlindPostedAutomatically,
+    #[msg("Player does not need to post a blind")]
+    BlindNotRequired,
+    #[msg("Player states do not match the seated players")]
+    PlayerStatesMismatch,
+    #[msg("Player is not in the hand")]
+    PlayerNotInHand,
+    #[msg("Action timeout has not been reached")]
+    TimeoutNotReached,
+    #[msg("Player is leaving the table")]
+    LeavePending,
+    #[msg("Not allowed at a tournament table")]
+    TournamentTable,
+    #[msg("Invalid blind schedule")]
+    InvalidBlindSchedule,
+    #[msg("Invalid payout structure")]
+    InvalidPayouts,
+    #[msg("Tournament registration is closed")]
+    RegistrationClosed,
+    #[msg("Tournament is full")]
+    TournamentFull,
+    #[msg("Only the tournament creator can do this")]
+    NotTournamentCreator,
+    #[msg("Tournament is not running")]
+    TournamentNotRunning,
+    #[msg("Tournament has not finished")]
+    TournamentNotFinished,
+    #[msg("Tournament needs enough tables for every entrant")]
+    NoTournamentTables,
+    #[msg("Too many tournament tables")]
+    TooManyTables,
+    #[msg("Table does not belong to this tournament or seat assignment")]
+    WrongTournamentTable,
+    #[msg("Tables are already balanced")]
+    TablesBalanced,
+    #[msg("Player still has chips")]
+    PlayerNotBusted,
+    #[msg("Prize already claimed")]
+    PrizeAlreadyClaimed,
+    #[msg("No prize for this finishing position")]
+    NoPrize,
+    #[msg("Only the table creator can do this")]
+    UnauthorizedTableAction,
+    #[msg("Table is private")]
+    AccessDenied,
+    #[msg("Wrong table password")]
+    WrongPassword,
+    #[msg("Player is not on the table allowlist")]
+    NotOnAllowlist,
+    #[msg("Invite token required")]
+    MissingInviteToken,
+    #[msg("Allowlist too long")]
+    AllowlistTooLong,
+    #[msg("The table has already dealt its first hand")]
+    GameAlreadyStarted,
+    #[msg("Invalid rake configuration")]
+    InvalidRake,
+    #[msg("Nothing to claim")]
+    NothingToClaim,
+    #[msg("Card is not in this game's deck")]
+    InvalidCard,
+    #[msg("Card has already been dealt")]
+    DuplicateCard,
+    #[msg("Bet exceeds the table limit")]
+    BetTooLarge,
+    #[msg("No bet or raise is allowed")]
+    RaiseNotAllowed,
+    #[msg("Invalid betting structure")]
+    InvalidBettingStructure,
+    #[msg("Not available in this table's dealing mode")]
+    WrongDealingMode,
+    #[msg("Mental poker tables need the deck account")]
+    MissingDeck,
+    #[msg("The deck is not ready")]
+    DeckNotReady,
+    #[msg("Card points are already loaded")]
+    DeckAlreadyLoaded,
+    #[msg("Card points don't match the canonical deck")]
+    InvalidCardPoints,
+    #[msg("Not this player's turn to shuffle")]
+    NotYourShuffle,
+    #[msg("Invalid shuffle")]
+    InvalidShuffle,
+    #[msg("Invalid decryption share")]
+    InvalidShare,
+    #[msg("Card is not open for decryption")]
+    ShareNotRequested,
+    #[msg("Cards don't match the decrypted deck")]
+    CardMismatch,
+    #[msg("The betting round is not over")]
+    RoundNotComplete,
+    #[msg("Every shuffle must be verified before the showdown")]
+    ShufflesUnverified,
+    #[msg("Shuffle opening doesn't match the commitment")]
+    InvalidShuffleOpening,
+    #[msg("Nobody is holding up the hand")]
+    NoKeyWithheld,
+    #[msg("Token tables need the chip mint, token accounts and token program")]
+    MissingChipAccounts,
+    #[msg("Token account or mint doesn't match the table's chips")]
+    WrongChipMint,
+    #[msg("Table doesn't play in an SPL token")]
+    NotTokenTable,
+    #[msg("Straddling is not allowed")]
+    StraddleNotAllowed,
+    #[msg("The second board has not been dealt")]
+    SecondBoardPending,
+    #[msg("Insurance is not available")]
+    InsuranceNotAllowed,
+    #[msg("Invalid insurance offer")]
+    InvalidInsurance,
+    #[msg("Insurance offer expired when the next card was dealt")]
+    InsuranceOfferExpired,
+    #[msg("Turn has already timed out")]
+    TurnTimedOut,
+    #[msg("Time bank is empty")]
+    TimeBankEmpty,
+    #[msg("Waitlist is full")]
+    WaitlistFull,
+    #[msg("Not enough players waiting to open a table")]
+    WaitlistTooShort,
+    #[msg("Player is not next on the waitlist")]
+    NotNextOnWaitlist,
+    #[msg("Table does not belong to this waitlist")]
+    WrongWaitlistTable,
+    #[msg("Table can't be delegated to a rollup")]
+    DelegationNotAllowed,
+    #[msg("Table seeds don't match the table")]
+    WrongTableSeeds,
+    #[msg("Signer is neither the player nor an authorized session key")]
+    InvalidSession,
+    #[msg("Session key has expired")]
+    SessionExpired,
+    #[msg("Session key spend cap exceeded")]
+    SessionSpendCapExceeded,
+    #[msg("Another player busted with a smaller stack must be eliminated first")]
+    EliminationOutOfOrder,
+    #[msg("A waitlist table still has a free seat")]
+    WaitlistSeatsOpen,
+    #[msg("Player is not waiting to leave the table")]
+    NoLeavePending,
+    #[msg("Session token of a refunded straddle is missing")]
+    MissingSessionToken,
+    #[msg("Chip mint has a transfer fee, transfer hook or permanent delegate")]
+    UnsupportedMintExtension,
 }
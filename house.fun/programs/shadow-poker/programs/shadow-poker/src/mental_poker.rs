@@ -0,0 +1,281 @@
+//! Cryptography for dealer-free (mental poker) tables.
+//!
+//! Cards are Ristretto points with no known discrete-log relation between
+//! them (see `card_points`). Each player encrypts the whole deck with a
+//! secret scalar `k` and shuffles it, so a card ends up as
+//! `k_1 * k_2 * ... * k_n * M`. Scalar multiplication commutes, so players
+//! can strip their layer in any order: a decryption share for a card `C`
+//! is `D = k^-1 * C`, published with a Chaum-Pedersen proof that
+//! `C = k * D` for the same `k` as the player's public key `K = k * G`.
+//!
+//! Shuffles are committed to as `blake3(k || permutation)` and opened once
+//! the hand is over, when every step is re-checked on chain.
+
+use solana_curve25519::{
+    ristretto::{multiply_ristretto, subtract_ristretto, validate_ristretto, PodRistrettoPoint},
+    scalar::PodScalar,
+};
+
+use crate::{Card, GameVariant};
+
+/// Compressed Ristretto basepoint.
+pub const BASEPOINT: [u8; 32] = [
+    0xe2, 0xf2, 0xae, 0x0a, 0x6a, 0xbc, 0x4e, 0x71, 0xa8, 0x84, 0xa9, 0x61, 0xc5, 0x00, 0x51, 0x5f,
+    0x58, 0xe3, 0x0b, 0x6a, 0xa5, 0x82, 0xdd, 0x8d, 0xb6, 0xa6, 0x59, 0x45, 0xe0, 0x8d, 0x2d, 0x76,
+];
+
+/// `blake3` of the 52 card points from `card_points`, concatenated in card
+/// index order. Lets the program accept the points without hashing to the
+/// curve itself.
+pub const CARD_POINTS_HASH: [u8; 32] = [
+    0x45, 0x5b, 0x44, 0xa0, 0x67, 0x94, 0xd9, 0x6c, 0xe4, 0xda, 0xd5, 0xbd, 0xd5, 0x06, 0x4b, 0x08,
+    0x18, 0x62, 0x95, 0xe2, 0xfa, 0xd0, 0xdb, 0x51, 0x8d, 0x04, 0x74, 0x8e, 0x10, 0xe2, 0x99, 0x4a,
+];
+
+/// Cards in a full deck.
+pub const FULL_DECK: usize = 52;
+
+/// Position of a card in the full deck: suits in order, twos to aces.
+pub fn card_index(card: &Card) -> usize {
+    card.suit as usize * 13 + (card.rank as usize - 2)
+}
+
+/// Inverse of `card_index`.
+pub fn card_at(index: usize) -> Card {
+    Card {
+        suit: (index / 13) as u8,
+        rank: (index % 13) as u8 + 2,
+    }
+}
+
+/// Full-deck indices of the cards `variant` plays with, in order.
+pub fn deck_indices(variant: GameVariant) -> impl Iterator<Item = usize> {
+    (0..FULL_DECK).filter(move |&i| card_at(i).is_in_deck(variant))
+}
+
+/// The card points, derived by hashing each card index to the curve.
+#[cfg(not(target_os = "solana"))]
+pub fn card_points() -> Vec<[u8; 32]> {
+    use curve25519_dalek::ristretto::RistrettoPoint;
+
+    (0..FULL_DECK as u8)
+        .map(|i| {
+            let mut input = b"shadow-poker card ".to_vec();
+            input.push(i);
+            RistrettoPoint::hash_from_bytes::<sha2::Sha512>(&input)
+                .compress()
+                .to_bytes()
+        })
+        .collect()
+}
+
+/// Whether `point` is a valid, non-identity Ristretto point.
+pub fn is_valid_point(point: &[u8; 32]) -> bool {
+    *point != [0u8; 32] && validate_ristretto(&PodRistrettoPoint(*point))
+}
+
+/// `scalar * point`, or `None` if either is malformed.
+pub fn multiply(scalar: &[u8; 32], point: &[u8; 32]) -> Option<[u8; 32]> {
+    multiply_ristretto(&PodScalar(*scalar), &PodRistrettoPoint(*point)).map(|p| p.0)
+}
+
+/// Commitment a player makes to their shuffle before it is used.
+pub fn shuffle_commitment(key: &[u8; 32], permutation: &[u8]) -> [u8; 32] {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(b"shadow-poker shuffle");
+    hasher.update(key);
+    hasher.update(permutation);
+    *hasher.finalize().as_bytes()
+}
+
+/// Fiat-Shamir challenge for a decryption share. The top four bits are
+/// cleared so the hash is always a canonical scalar.
+pub fn share_challenge(
+    public_key: &[u8; 32],
+    input: &[u8; 32],
+    output: &[u8; 32],
+    commit_base: &[u8; 32],
+    commit_card: &[u8; 32],
+) -> [u8; 32] {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(b"shadow-poker share");
+    for part in [public_key, input, output, commit_base, commit_card] {
+        hasher.update(part);
+    }
+    let mut challenge = *hasher.finalize().as_bytes();
+    challenge[31] &= 0x0f;
+    challenge
+}
+
+/// Check a decryption share: `output` is `input` with the layer of the key
+/// behind `public_key` removed.
+pub fn verify_share(
+    public_key: &[u8; 32],
+    input: &[u8; 32],
+    output: &[u8; 32],
+    challenge: &[u8; 32],
+    response: &[u8; 32],
+) -> bool {
+    let recompute = || -> Option<[u8; 32]> {
+        if !is_valid_point(output) {
+            return None;
+        }
+        // Commitments the prover must have used: s*G - c*K and s*D - c*C
+        let commit_base = subtract(
+            &multiply(response, &BASEPOINT)?,
+            &multiply(challenge, public_key)?,
+        )?;
+        let commit_card = subtract(&multiply(response, output)?, &multiply(challenge, input)?)?;
+        Some(share_challenge(public_key, input, output, &commit_base, &commit_card))
+    };
+    recompute() == Some(*challenge)
+}
+
+/// Check an opened shuffle: `output[j] == key * input[permutation[j]]`.
+pub fn verify_shuffle(
+    key: &[u8; 32],
+    permutation: &[u8],
+    input: &[[u8; 32]],
+    output: &[[u8; 32]],
+) -> bool {
+    if permutation.len() != input.len() || output.len() != input.len() {
+        return false;
+    }
+    let mut used = vec![false; input.len()];
+    for (out, &from) in output.iter().zip(permutation) {
+        let from = from as usize;
+        if from >= input.len() || used[from] {
+            return false;
+        }
+        used[from] = true;
+        if multiply(key, &input[from]) != Some(*out) {
+            return false;
+        }
+    }
+    true
+}
+
+fn subtract(left: &[u8; 32], right: &[u8; 32]) -> Option<[u8; 32]> {
+    subtract_ristretto(&PodRistrettoPoint(*left), &PodRistrettoPoint(*right)).map(|p| p.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use curve25519_dalek::{
+        constants::RISTRETTO_BASEPOINT_POINT, ristretto::CompressedRistretto, scalar::Scalar,
+    };
+
+    fn scalar(seed: &str) -> Scalar {
+        Scalar::from_bytes_mod_order(*blake3::hash(seed.as_bytes()).as_bytes())
+    }
+
+    fn point(bytes: &[u8; 32]) -> curve25519_dalek::ristretto::RistrettoPoint {
+        CompressedRistretto(*bytes).decompress().unwrap()
+    }
+
+    /// A decryption share of `input` under `key`, proved the way a client
+    /// proves it: returns `(output, challenge, response)`.
+    fn prove_share(key: Scalar, input: &[u8; 32]) -> ([u8; 32], [u8; 32], [u8; 32]) {
+        let public_key = (key * RISTRETTO_BASEPOINT_POINT).compress().to_bytes();
+        let output = key.invert() * point(input);
+        let nonce = scalar("nonce");
+        let commit_base = (nonce * RISTRETTO_BASEPOINT_POINT).compress().to_bytes();
+        let commit_card = (nonce * output).compress().to_bytes();
+        let output = output.compress().to_bytes();
+        let challenge = share_challenge(&public_key, input, &output, &commit_base, &commit_card);
+        let response = nonce + Scalar::from_bytes_mod_order(challenge) * key;
+        (output, challenge, response.to_bytes())
+    }
+
+    #[test]
+    fn card_points_match_their_hash() {
+        let points = card_points();
+        assert_eq!(points.len(), FULL_DECK);
+        assert!(points.iter().all(is_valid_point));
+        assert_eq!(*blake3::hash(&points.concat()).as_bytes(), CARD_POINTS_HASH);
+        assert_eq!(RISTRETTO_BASEPOINT_POINT.compress().to_bytes(), BASEPOINT);
+    }
+
+    #[test]
+    fn card_indices_round_trip() {
+        assert!((0..FULL_DECK).all(|i| card_index(&card_at(i)) == i));
+        assert_eq!(deck_indices(GameVariant::Holdem).count(), 52);
+        assert_eq!(deck_indices(GameVariant::ShortDeck).count(), 36);
+    }
+
+    #[test]
+    fn verifies_an_honest_share() {
+        let key = scalar("player key");
+        let public_key = (key * RISTRETTO_BASEPOINT_POINT).compress().to_bytes();
+        // A card encrypted under the player's key
+        let card = card_points()[7];
+        let input = (key * point(&card)).compress().to_bytes();
+
+        let (output, challenge, response) = prove_share(key, &input);
+        assert_eq!(output, card);
+        assert!(verify_share(&public_key, &input, &output, &challenge, &response));
+    }
+
+    #[test]
+    fn rejects_a_forged_share() {
+        let key = scalar("player key");
+        let public_key = (key * RISTRETTO_BASEPOINT_POINT).compress().to_bytes();
+        let points = card_points();
+        let input = (key * point(&points[7])).compress().to_bytes();
+        let (output, challenge, response) = prove_share(key, &input);
+
+        let mut forged_challenge = challenge;
+        forged_challenge[0] ^= 1;
+        assert!(!verify_share(&public_key, &input, &output, &forged_challenge, &response));
+
+        let mut forged_response = response;
+        forged_response[0] ^= 1;
+        assert!(!verify_share(&public_key, &input, &output, &challenge, &forged_response));
+
+        // Claiming the card decrypts to a different one
+        assert!(!verify_share(&public_key, &input, &points[8], &challenge, &response));
+
+        // A proof under somebody else's key
+        let (output, challenge, response) = prove_share(scalar("other key"), &input);
+        assert!(!verify_share(&public_key, &input, &output, &challenge, &response));
+
+        // Malformed points
+        assert!(!verify_share(&public_key, &input, &[0u8; 32], &challenge, &response));
+        assert!(!verify_share(&[0xff; 32], &input, &output, &challenge, &response));
+    }
+
+    #[test]
+    fn verifies_shuffles() {
+        let key = scalar("shuffler key");
+        let input: Vec<[u8; 32]> = card_points()[..5].to_vec();
+        let permutation = [3u8, 0, 4, 1, 2];
+        let output: Vec<[u8; 32]> = permutation
+            .iter()
+            .map(|&from| (key * point(&input[from as usize])).compress().to_bytes())
+            .collect();
+        let key = key.to_bytes();
+        assert!(verify_shuffle(&key, &permutation, &input, &output));
+
+        // Opening with a different permutation than was applied
+        assert!(!verify_shuffle(&key, &[0, 3, 4, 1, 2], &input, &output));
+        // A card used twice and another dropped
+        assert!(!verify_shuffle(&key, &[3, 3, 4, 1, 2], &input, &output));
+        // Out of range or the wrong length
+        assert!(!verify_shuffle(&key, &[3, 0, 5, 1, 2], &input, &output));
+        assert!(!verify_shuffle(&key, &[3, 0, 4, 1], &input, &output));
+        // The wrong key
+        assert!(!verify_shuffle(&scalar("other key").to_bytes(), &permutation, &input, &output));
+    }
+
+    #[test]
+    fn shuffle_commitment_binds_key_and_permutation() {
+        let key = scalar("shuffler key").to_bytes();
+        let other_key = scalar("other key").to_bytes();
+        let commitment = shuffle_commitment(&key, &[3, 0, 4, 1, 2]);
+        assert_eq!(commitment, shuffle_commitment(&key, &[3, 0, 4, 1, 2]));
+        assert_ne!(commitment, shuffle_commitment(&key, &[0, 3, 4, 1, 2]));
+        assert_ne!(commitment, shuffle_commitment(&key, &[3, 3, 4, 1, 2]));
+        assert_ne!(commitment, shuffle_commitment(&other_key, &[3, 0, 4, 1, 2]));
+    }
+}
@@ -0,0 +1,238 @@
+//! Main pot and side pots.
+//!
+//! A player can only win from each opponent as much as they put in
+//! themselves. The chips put in a hand are layered by the contributions of
+//! the players still in it: the main pot holds everyone's chips up to the
+//! smallest of those contributions and every player still in can win it;
+//! each side pot holds the chips between two contributions and only the
+//! players who reached the upper one can win it. Chips of folded players
+//! above the largest live contribution go to the last pot.
+//!
+//! A bet nobody called ends up alone in the last pot, whose only eligible
+//! player is the one who made it, so it goes straight back to them.
+
+/// One layer of the pot.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Pot {
+    /// Contribution band this pot collects from every player: the part of
+    /// each contribution above `from` and up to `to`.
+    pub from: u64,
+    pub to: u64,
+    pub amount: u64,
+    /// Seats that can win this pot.
+    pub eligible: u16,
+}
+
+impl Pot {
+    /// Chips this pot holds of a single `contribution`.
+    pub fn share_of(&self, contribution: u64) -> u64 {
+        contribution.min(self.to) - contribution.min(self.from)
+    }
+
+    /// Whether more than one player can win this pot.
+    pub fn contested(&self) -> bool {
+        self.eligible.count_ones() > 1
+    }
+}
+
+/// Split a hand's contributions, given as `(seat, chips put in)`, into the
+/// main pot followed by the side pots. `live` is the mask of seats still in
+/// the hand.
+pub fn build_pots(contributions: &[(u8, u64)], live: u16) -> Vec<Pot> {
+    let is_live = |seat: u8| live & (1 << seat) != 0;
+    let mut levels: Vec<u64> = contributions
+        .iter()
+        .filter(|&&(seat, chips)| is_live(seat) && chips > 0)
+        .map(|&(_, chips)| chips)
+        .collect();
+    levels.sort_unstable();
+    levels.dedup();
+
+    let Some(&top) = levels.last() else {
+        return vec![Pot {
+            from: 0,
+            to: u64::MAX,
+            amount: contributions.iter().map(|&(_, chips)| chips).sum(),
+            eligible: live,
+        }];
+    };
+
+    let mut pots = Vec::with_capacity(levels.len());
+    let mut from = 0;
+    for &level in levels.iter() {
+        let to = if level == top { u64::MAX } else { level };
+        let eligible = contributions
+            .iter()
+            .filter(|&&(seat, chips)| is_live(seat) && chips >= level)
+            .fold(0u16, |mask, &(seat, _)| mask | (1 << seat));
+        let mut pot = Pot { from, to, amount: 0, eligible };
+        pot.amount = contributions.iter().map(|&(_, chips)| pot.share_of(chips)).sum();
+        pots.push(pot);
+        from = level;
+    }
+    pots
+}
+
+/// Chips of the largest contribution that nobody matched, which go back to
+/// whoever put them in and are never raked. A player who folded their own
+/// unmatched bet leaves it in the pot.
+pub fn uncalled(contributions: &[(u8, u64)], live: u16) -> u64 {
+    let mut largest = (0, 0);
+    let mut second = 0;
+    for &(seat, chips) in contributions {
+        if chips > largest.1 {
+            second = largest.1;
+            largest = (seat, chips);
+        } else if chips > second {
+            second = chips;
+        }
+    }
+    match live & (1 << largest.0) != 0 {
+        true => largest.1 - second,
+        false => 0,
+    }
+}
+
+/// Part of a pot awarded to a single player.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Share {
+    pub pot: u8,
+    pub amount: u64,
+    pub rake: u64, // Rake taken from this pot, carried on its first share
+    pub second_run: bool,
+}
+
+/// The shares the pots are awarded in, in order. `rake` comes out of the
+/// main pot first. When the hand was run twice each pot more than one
+/// player can win is split between the boards, the odd chip to the first.
+pub fn shares(pots: &[Pot], mut rake: u64, run_twice: bool) -> Vec<Share> {
+    let mut shares = Vec::with_capacity(pots.len() * 2);
+    for (index, pot) in pots.iter().enumerate() {
+        let taken = rake.min(pot.amount);
+        rake -= taken;
+        let amount = pot.amount - taken;
+        let share = |amount, rake, second_run| Share {
+            pot: index as u8,
+            amount,
+            rake,
+            second_run,
+        };
+        if run_twice && pot.contested() {
+            shares.push(share(amount - amount / 2, taken, false));
+            shares.push(share(amount / 2, 0, true));
+        } else {
+            shares.push(share(amount, taken, false));
+        }
+    }
+    shares
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mask(seats: &[u8]) -> u16 {
+        seats.iter().fold(0, |mask, seat| mask | (1 << seat))
+    }
+
+    #[test]
+    fn single_pot_when_everyone_matches() {
+        let pots = build_pots(&[(0, 100), (1, 100), (2, 40)], mask(&[0, 1]));
+        assert_eq!(pots.len(), 1);
+        assert_eq!(pots[0].amount, 240);
+        assert_eq!(pots[0].eligible, mask(&[0, 1]));
+        assert_eq!(uncalled(&[(0, 100), (1, 100), (2, 40)], mask(&[0, 1])), 0);
+    }
+
+    #[test]
+    fn short_all_in_only_wins_what_it_matched() {
+        // Seat 0 is all-in for 30, seats 1 and 2 put in 100 each
+        let contributions = [(0, 30), (1, 100), (2, 100)];
+        let pots = build_pots(&contributions, mask(&[0, 1, 2]));
+        assert_eq!(pots.len(), 2);
+        assert_eq!((pots[0].amount, pots[0].eligible), (90, mask(&[0, 1, 2])));
+        assert_eq!((pots[1].amount, pots[1].eligible), (140, mask(&[1, 2])));
+        assert_eq!(pots[0].share_of(100), 30);
+        assert_eq!(pots[1].share_of(100), 70);
+        assert_eq!(pots[1].share_of(30), 0);
+    }
+
+    #[test]
+    fn uncalled_bet_goes_back_to_its_owner() {
+        // A short big blind all-in for 15 against a raise to 100
+        let contributions = [(0, 100), (1, 15)];
+        let pots = build_pots(&contributions, mask(&[0, 1]));
+        assert_eq!((pots[0].amount, pots[0].eligible), (30, mask(&[0, 1])));
+        assert_eq!((pots[1].amount, pots[1].eligible), (85, mask(&[0])));
+        assert!(!pots[1].contested());
+        assert_eq!(uncalled(&contributions, mask(&[0, 1])), 85);
+    }
+
+    #[test]
+    fn folded_chips_stay_in_the_pots_they_reached() {
+        // Seat 2 called 60 and folded; seat 3 folded to a raise after
+        // putting in 120
+        let contributions = [(0, 50), (1, 150), (2, 60), (3, 120)];
+        let live = mask(&[0, 1]);
+        let pots = build_pots(&contributions, live);
+        assert_eq!(pots.len(), 2);
+        assert_eq!((pots[0].amount, pots[0].eligible), (200, mask(&[0, 1])));
+        assert_eq!((pots[1].amount, pots[1].eligible), (180, mask(&[1])));
+        assert_eq!(pots.iter().map(|p| p.amount).sum::<u64>(), 380);
+        assert_eq!(uncalled(&contributions, live), 30);
+        // A folded bet nobody called stays in the pot
+        assert_eq!(uncalled(&[(0, 50), (1, 80)], mask(&[0])), 0);
+    }
+
+    #[test]
+    fn disconnected_player_wins_only_what_they_matched() {
+        // Seat 1 timed out in the big blind facing a raise to 30 and was
+        // kept in as all-in for 10; seat 0 then bet 100 more on the flop
+        let contributions = [(0, 130), (1, 10), (2, 5)];
+        let live = mask(&[0, 1]);
+        let pots = build_pots(&contributions, live);
+        assert_eq!((pots[0].amount, pots[0].eligible), (25, mask(&[0, 1])));
+        assert_eq!((pots[1].amount, pots[1].eligible), (120, mask(&[0])));
+        assert_eq!(uncalled(&contributions, live), 120);
+
+        // Winning the showdown only takes the main pot, and the bigger bet
+        // goes back to seat 0 whole
+        let awarded = shares(&pots, 1, false);
+        assert_eq!(awarded.len(), 2);
+        assert_eq!((awarded[0].pot, awarded[0].amount, awarded[0].rake), (0, 24, 1));
+        assert!(pots[awarded[0].pot as usize].contested());
+        assert_eq!((awarded[1].pot, awarded[1].amount, awarded[1].rake), (1, 120, 0));
+        assert_eq!(pots[1].eligible.trailing_zeros(), 0);
+    }
+
+    #[test]
+    fn three_levels_of_all_in() {
+        let contributions = [(0, 20), (1, 50), (2, 80), (3, 80)];
+        let pots = build_pots(&contributions, mask(&[0, 1, 2, 3]));
+        let amounts: Vec<(u64, u16)> = pots.iter().map(|p| (p.amount, p.eligible)).collect();
+        assert_eq!(
+            amounts,
+            vec![(80, mask(&[0, 1, 2, 3])), (90, mask(&[1, 2, 3])), (60, mask(&[2, 3]))]
+        );
+    }
+
+    #[test]
+    fn rake_comes_out_of_the_main_pot_first() {
+        let pots = build_pots(&[(0, 10), (1, 100), (2, 100)], mask(&[0, 1, 2]));
+        let amounts = |rake| -> Vec<(u8, u64, u64)> {
+            shares(&pots, rake, false).iter().map(|s| (s.pot, s.amount, s.rake)).collect()
+        };
+        assert_eq!(amounts(5), vec![(0, 25, 5), (1, 180, 0)]);
+        assert_eq!(amounts(40), vec![(0, 0, 30), (1, 170, 10)]);
+    }
+
+    #[test]
+    fn running_it_twice_splits_contested_pots() {
+        let pots = build_pots(&[(0, 100), (1, 25)], mask(&[0, 1]));
+        let split = shares(&pots, 1, true);
+        let amounts: Vec<(u8, u64, u64, bool)> =
+            split.iter().map(|s| (s.pot, s.amount, s.rake, s.second_run)).collect();
+        // The uncalled 75 isn't split
+        assert_eq!(amounts, vec![(0, 25, 1, false), (0, 24, 0, true), (1, 75, 0, false)]);
+    }
+}
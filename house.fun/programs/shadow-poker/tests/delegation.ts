@@ -0,0 +1,180 @@
+import * as anchor from "@coral-xyz/anchor";
+import { BN, Program } from "@coral-xyz/anchor";
+import {
+  AccountInfo,
+  Connection,
+  Keypair,
+  PublicKey,
+  SystemProgram,
+} from "@solana/web3.js";
+import { expect } from "chai";
+import {
+  createTable,
+  fundedKeypair,
+  housePda,
+  initializeHouse,
+  joinTable,
+  seatedStates,
+  shadowPoker,
+} from "./setup";
+
+// Round trip of a table through a MagicBlock ephemeral rollup, checking the
+// hand-written delegation and magic program instructions in
+// `ephemeral_rollup.rs` against the real programs. The local validator
+// loads the delegation program from tests/fixtures (see Anchor.toml); it
+// also needs a local ephemeral validator on top of it at
+// EPHEMERAL_PROVIDER_ENDPOINT.
+
+const DELEGATION_PROGRAM_ID = new PublicKey(
+  "DELeGGvXpWV2fqJUhqcF5ZSYMS4JTLjteaAMARRSaeSh"
+);
+const MAGIC_PROGRAM_ID = new PublicKey(
+  "Magic11111111111111111111111111111111111111"
+);
+const MAGIC_CONTEXT_ID = new PublicKey(
+  "MagicContext1111111111111111111111111111111"
+);
+const EPHEMERAL_PROVIDER_ENDPOINT =
+  process.env.EPHEMERAL_PROVIDER_ENDPOINT ?? "http://localhost:7799";
+const EPHEMERAL_WS_ENDPOINT =
+  process.env.EPHEMERAL_WS_ENDPOINT ?? "ws://localhost:7800";
+
+/** Accounts the delegation program keeps for one delegated account. */
+function delegationAccounts(program: Program<any>, account: PublicKey) {
+  const pda = (seed: string, programId: PublicKey) =>
+    PublicKey.findProgramAddressSync(
+      [Buffer.from(seed), account.toBuffer()],
+      programId
+    )[0];
+  return {
+    buffer: pda("buffer", program.programId),
+    delegationRecord: pda("delegation", DELEGATION_PROGRAM_ID),
+    delegationMetadata: pda("delegation-metadata", DELEGATION_PROGRAM_ID),
+  };
+}
+
+async function accountInfos(
+  connection: Connection,
+  accounts: PublicKey[]
+): Promise<AccountInfo<Buffer>[]> {
+  const infos = await connection.getMultipleAccountsInfo(accounts, "confirmed");
+  return infos.map((info) => info!);
+}
+
+describe("Shadow Poker - ephemeral rollups", () => {
+  const program: Program<any> = shadowPoker();
+  const provider = program.provider as anchor.AnchorProvider;
+  const rollup = new Program(
+    program.idl,
+    new anchor.AnchorProvider(
+      new Connection(EPHEMERAL_PROVIDER_ENDPOINT, {
+        wsEndpoint: EPHEMERAL_WS_ENDPOINT,
+      }),
+      provider.wallet,
+      { commitment: "confirmed" }
+    )
+  );
+
+  let creator: Keypair;
+
+  before(async () => {
+    await initializeHouse(program);
+    creator = await fundedKeypair(program);
+  });
+
+  it("Delegates and undelegates a table without changing it", async () => {
+    const { totalTables } = await program.account.shadowPokerHouse.fetch(
+      housePda(program)
+    );
+    const table = await createTable(program, creator, "dealer");
+    for (let i = 0; i < 2; i++) {
+      await joinTable(program, table, await fundedKeypair(program));
+    }
+    const states = await seatedStates(program, table);
+    await program.methods
+      .startHand()
+      .accounts({
+        table,
+        house: housePda(program),
+        dealerApproval: null,
+        deck: null,
+        authority: creator.publicKey,
+      })
+      .remainingAccounts(states)
+      .signers([creator])
+      .rpc({ commitment: "confirmed" });
+
+    const delegated = [table, ...states.map((state) => state.pubkey)];
+    const before = await accountInfos(provider.connection, delegated);
+
+    // Each player state is followed by its buffer, record and metadata
+    const records = states.flatMap((state) =>
+      Object.values(delegationAccounts(program, state.pubkey)).map(
+        (pubkey) => ({ pubkey, isWritable: true, isSigner: false })
+      )
+    );
+    await program.methods
+      .delegateTable(totalTables, 100)
+      .accounts({
+        table,
+        house: housePda(program),
+        dealerApproval: null,
+        ...delegationAccounts(program, table),
+        authority: creator.publicKey,
+        ownerProgram: program.programId,
+        delegationProgram: DELEGATION_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .remainingAccounts([...states, ...records])
+      .signers([creator])
+      .rpc({ commitment: "confirmed" });
+
+    // The delegation program owns the accounts now, with the data the
+    // program parked in the buffers restored, and the buffers are gone
+    const handedOver = await accountInfos(provider.connection, delegated);
+    handedOver.forEach((info, i) => {
+      expect(info.owner.toBase58()).to.equal(DELEGATION_PROGRAM_ID.toBase58());
+      expect(info.data.equals(before[i].data)).to.equal(true);
+      expect(info.lamports).to.equal(before[i].lamports);
+    });
+    const buffers = delegated.map(
+      (account) => delegationAccounts(program, account).buffer
+    );
+    const leftover = await provider.connection.getMultipleAccountsInfo(buffers);
+    expect(leftover.every((info) => info === null)).to.equal(true);
+
+    // The house authority can hand a running hand back from the rollup
+    await rollup.methods
+      .undelegateTable()
+      .accounts({
+        table,
+        house: housePda(program),
+        dealerApproval: null,
+        payer: provider.wallet.publicKey,
+        magicContext: MAGIC_CONTEXT_ID,
+        magicProgram: MAGIC_PROGRAM_ID,
+      })
+      .remainingAccounts(states)
+      .rpc({ skipPreflight: true });
+
+    // The rollup commits and the delegation program calls back into
+    // `process_undelegation` on the base layer
+    let returned: AccountInfo<Buffer>[] = [];
+    for (let attempt = 0; attempt < 60; attempt++) {
+      returned = await accountInfos(provider.connection, delegated);
+      if (returned.every((info) => info?.owner.equals(program.programId))) {
+        break;
+      }
+      await new Promise((resolve) => setTimeout(resolve, 500));
+    }
+    returned.forEach((info, i) => {
+      expect(info.owner.toBase58()).to.equal(program.programId.toBase58());
+      expect(info.data.equals(before[i].data)).to.equal(true);
+      expect(info.lamports).to.equal(before[i].lamports);
+    });
+
+    const hand = await program.account.table.fetch(table);
+    expect(hand.status).to.deep.equal({ dealing: {} });
+    expect((hand.handNumber as BN).toNumber()).to.equal(1);
+  });
+});
@@ -0,0 +1,8 @@
+#!/usr/bin/env bash
+# Dump the MagicBlock delegation program deployed on devnet into dlp.so,
+# the fixture Anchor.toml loads into the local test validator. Commit the
+# result so `anchor test` runs without network access.
+set -euo pipefail
+
+cd "$(dirname "$0")"
+solana program dump --url devnet DELeGGvXpWV2fqJUhqcF5ZSYMS4JTLjteaAMARRSaeSh dlp.so
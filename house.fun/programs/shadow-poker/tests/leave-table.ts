@@ -0,0 +1,167 @@
+import { BN, Program } from "@coral-xyz/anchor";
+import { Keypair, PublicKey, SystemProgram } from "@solana/web3.js";
+import { expect } from "chai";
+import {
+  BIG_BLIND,
+  BUY_IN,
+  SMALL_BLIND,
+  createTable,
+  fundedKeypair,
+  housePda,
+  initializeHouse,
+  joinTable,
+  playerStatePda,
+  seatedStates,
+  shadowPoker,
+} from "./setup";
+
+describe("Shadow Poker - leaving mid-hand", () => {
+  const program: Program<any> = shadowPoker();
+
+  let creator: Keypair;
+
+  before(async () => {
+    await initializeHouse(program);
+    creator = await fundedKeypair(program);
+  });
+
+  async function act(
+    table: PublicKey,
+    player: Keypair,
+    action: object,
+    amount: BN | null = null
+  ) {
+    await program.methods
+      .playerAction(action, amount)
+      .accounts({
+        table,
+        playerState: playerStatePda(program, table, player.publicKey),
+        sessionToken: null,
+        player: player.publicKey,
+      })
+      .signers([player])
+      .rpc({ commitment: "confirmed" });
+  }
+
+  async function leaveTable(table: PublicKey, player: Keypair) {
+    await program.methods
+      .leaveTable()
+      .accounts({
+        table,
+        house: housePda(program),
+        playerState: playerStatePda(program, table, player.publicKey),
+        chipMint: null,
+        playerTokens: null,
+        vault: null,
+        tokenProgram: null,
+        sessionToken: null,
+        player: player.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([player])
+      .rpc({ commitment: "confirmed" });
+  }
+
+  it("Resolves a hand after a folded player leaves", async () => {
+    const table = await createTable(program, creator, "dealer", 3);
+    const players = [
+      await fundedKeypair(program),
+      await fundedKeypair(program),
+      await fundedKeypair(program),
+    ];
+    for (const player of players) {
+      await joinTable(program, table, player);
+    }
+    await program.methods
+      .startHand()
+      .accounts({
+        table,
+        house: housePda(program),
+        dealerApproval: null,
+        deck: null,
+        authority: creator.publicKey,
+      })
+      .remainingAccounts(await seatedStates(program, table))
+      .signers([creator])
+      .rpc({ commitment: "confirmed" });
+
+    const started = await program.account.table.fetch(table);
+    const at = (seat: number) =>
+      players.find((p) => p.publicKey.equals(started.players[seat]))!;
+    const button = at(started.dealerIndex);
+    const smallBlind = at(started.smallBlindIndex);
+    const bigBlind = at(started.bigBlindIndex);
+
+    // The small blind folds and walks away with their blind in the pot
+    await act(table, button, { call: {} });
+    await act(table, smallBlind, { fold: {} });
+    await leaveTable(table, smallBlind);
+
+    const leaverState = playerStatePda(program, table, smallBlind.publicKey);
+    const leaver = await program.account.playerState.fetch(leaverState);
+    expect(leaver.leavePending).to.equal(true);
+    expect(leaver.handContribution.toString()).to.equal(SMALL_BLIND.toString());
+
+    // Their blind still counts towards the pot, so the hand resolves
+    await program.methods
+      .resolveHand(
+        Buffer.from([started.bigBlindIndex, started.dealerIndex]),
+        null
+      )
+      .accounts({
+        table,
+        house: housePda(program),
+        dealerApproval: null,
+        deck: null,
+        authority: creator.publicKey,
+      })
+      .remainingAccounts(await seatedStates(program, table))
+      .signers([creator])
+      .rpc({ commitment: "confirmed" });
+
+    const resolved = await program.account.table.fetch(table);
+    expect(resolved.status).to.deep.equal({ finished: {} });
+    expect((resolved.pot as BN).toNumber()).to.equal(0);
+
+    // No flop, no rake: the big blind wins the small blind and the call
+    const winner = await program.account.playerState.fetch(
+      playerStatePda(program, table, bigBlind.publicKey)
+    );
+    expect(winner.stack.toString()).to.equal(
+      BUY_IN.add(SMALL_BLIND).add(BIG_BLIND).toString()
+    );
+
+    // Anyone can then cash the leaver out with what they had left
+    const balanceBefore = await program.provider.connection.getBalance(
+      smallBlind.publicKey
+    );
+    await program.methods
+      .cashOutPlayer()
+      .accounts({
+        table,
+        house: housePda(program),
+        playerState: leaverState,
+        chipMint: null,
+        playerTokens: null,
+        vault: null,
+        tokenProgram: null,
+        sessionToken: null,
+        player: smallBlind.publicKey,
+      })
+      .rpc({ commitment: "confirmed" });
+
+    const balanceAfter = await program.provider.connection.getBalance(
+      smallBlind.publicKey
+    );
+    expect(balanceAfter - balanceBefore).to.be.greaterThan(
+      BUY_IN.sub(SMALL_BLIND).toNumber()
+    );
+    expect(
+      await program.provider.connection.getAccountInfo(leaverState)
+    ).to.equal(null);
+    const { players: seated } = await program.account.table.fetch(table);
+    expect(seated[started.smallBlindIndex].equals(PublicKey.default)).to.equal(
+      true
+    );
+  });
+});
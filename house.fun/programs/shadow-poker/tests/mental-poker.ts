@@ -0,0 +1,345 @@
+import * as anchor from "@coral-xyz/anchor";
+import { BN, Program } from "@coral-xyz/anchor";
+import { ComputeBudgetProgram, Keypair, PublicKey } from "@solana/web3.js";
+import { expect } from "chai";
+import { RistrettoPoint, ed25519 } from "@noble/curves/ed25519";
+import { invert, mod } from "@noble/curves/abstract/modular";
+import {
+  bytesToNumberLE,
+  concatBytes,
+  numberToBytesLE,
+} from "@noble/curves/abstract/utils";
+import { blake3 } from "@noble/hashes/blake3";
+import { sha512 } from "@noble/hashes/sha512";
+import { randomBytes } from "crypto";
+import {
+  BIG_BLIND,
+  BUY_IN,
+  createTable,
+  deckPda,
+  fundedKeypair,
+  housePda,
+  initializeHouse,
+  joinTable,
+  playerStatePda,
+  seatedStates,
+  shadowPoker,
+} from "./setup";
+
+// Client side of `mental_poker.rs`: the same card points, shuffle
+// commitments and Chaum-Pedersen decryption shares the program checks.
+
+const ORDER = ed25519.CURVE.n;
+const MENTAL_POKER_PENALTY_BBS = 10;
+const POINTS_PER_TX = 20;
+const SHARES_PER_TX = 6;
+const text = (s: string) => new TextEncoder().encode(s);
+
+function cardPoints(): Uint8Array[] {
+  return Array.from({ length: 52 }, (_, i) =>
+    RistrettoPoint.hashToCurve(
+      sha512(concatBytes(text("shadow-poker card "), Uint8Array.of(i)))
+    ).toRawBytes()
+  );
+}
+
+function randomScalar(): bigint {
+  return mod(bytesToNumberLE(randomBytes(64)), ORDER) || BigInt(1);
+}
+
+const scalarBytes = (k: bigint) => numberToBytesLE(k, 32);
+const multiply = (k: bigint, point: Uint8Array) =>
+  RistrettoPoint.fromHex(point).multiply(k).toRawBytes();
+const bytes = (b: Uint8Array) => Array.from(b);
+
+/** A shuffler's secret key and permutation for one hand. */
+class Shuffler {
+  key = randomScalar();
+  publicKey = RistrettoPoint.BASE.multiply(this.key).toRawBytes();
+
+  constructor(public player: Keypair, public permutation: number[]) {}
+
+  static random(player: Keypair, size: number): Shuffler {
+    const permutation = Array.from({ length: size }, (_, i) => i);
+    for (let i = size - 1; i > 0; i--) {
+      const j = randomBytes(1)[0] % (i + 1);
+      [permutation[i], permutation[j]] = [permutation[j], permutation[i]];
+    }
+    return new Shuffler(player, permutation);
+  }
+
+  commitment(): Uint8Array {
+    return blake3(
+      concatBytes(
+        text("shadow-poker shuffle"),
+        scalarBytes(this.key),
+        Uint8Array.from(this.permutation)
+      )
+    );
+  }
+
+  /** `output[j] = key * input[permutation[j]]` */
+  shuffle(input: Uint8Array[]): Uint8Array[] {
+    return this.permutation.map((from) => multiply(this.key, input[from]));
+  }
+
+  /** Remove this player's layer from `input`, with a proof that it was done honestly. */
+  share(position: number, input: Uint8Array) {
+    const output = RistrettoPoint.fromHex(input).multiply(invert(this.key, ORDER));
+    const nonce = randomScalar();
+    const commitBase = RistrettoPoint.BASE.multiply(nonce).toRawBytes();
+    const commitCard = output.multiply(nonce).toRawBytes();
+    const point = output.toRawBytes();
+    const challenge = blake3(
+      concatBytes(
+        text("shadow-poker share"),
+        this.publicKey,
+        input,
+        point,
+        commitBase,
+        commitCard
+      )
+    );
+    challenge[31] &= 0x0f;
+    const response = mod(nonce + bytesToNumberLE(challenge) * this.key, ORDER);
+    return {
+      position,
+      point: bytes(point),
+      challenge: bytes(challenge),
+      response: bytes(scalarBytes(response)),
+    };
+  }
+}
+
+describe("Shadow Poker - mental poker", () => {
+  const program: Program<any> = shadowPoker();
+  const computeBudget = ComputeBudgetProgram.setComputeUnitLimit({ units: 1_400_000 });
+
+  let creator: Keypair;
+  let players: Keypair[];
+
+  before(async () => {
+    await initializeHouse(program);
+    creator = await fundedKeypair(program);
+  });
+
+  /** A heads-up mental poker table with its deck loaded and a hand started. */
+  async function startHand(): Promise<{ table: PublicKey; deck: PublicKey }> {
+    const table = await createTable(program, creator, "mentalPoker");
+    const deck = deckPda(program, table);
+    await program.methods
+      .initDeck()
+      .accounts({
+        table,
+        deck,
+        creator: creator.publicKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([creator])
+      .rpc({ commitment: "confirmed" });
+    const points = cardPoints();
+    for (let i = 0; i < points.length; i += 13) {
+      await program.methods
+        .loadCardPoints(points.slice(i, i + 13).map(bytes))
+        .accounts({ table, deck, creator: creator.publicKey })
+        .signers([creator])
+        .rpc({ commitment: "confirmed" });
+    }
+    expect((await program.account.deck.fetch(deck)).ready).to.equal(true);
+
+    players = [await fundedKeypair(program), await fundedKeypair(program)];
+    for (const player of players) {
+      await joinTable(program, table, player);
+    }
+    await program.methods
+      .startHand()
+      .accounts({
+        table,
+        house: housePda(program),
+        dealerApproval: null,
+        deck,
+        authority: players[0].publicKey,
+      })
+      .remainingAccounts(await seatedStates(program, table))
+      .signers([players[0]])
+      .rpc({ commitment: "confirmed" });
+    return { table, deck };
+  }
+
+  /** The player sitting in `seat`. */
+  async function playerAt(table: PublicKey, seat: number): Promise<Keypair> {
+    const { players: seated } = await program.account.table.fetch(table);
+    return players.find((p) => p.publicKey.equals(seated[seat]))!;
+  }
+
+  async function submitShuffle(
+    table: PublicKey,
+    deck: PublicKey,
+    shuffler: Shuffler,
+    output: Uint8Array[]
+  ) {
+    const player = shuffler.player;
+    for (let i = 0; i < output.length; i += POINTS_PER_TX) {
+      await program.methods
+        .submitShuffle(
+          bytes(shuffler.publicKey),
+          bytes(shuffler.commitment()),
+          output.slice(i, i + POINTS_PER_TX).map(bytes)
+        )
+        .accounts({
+          table,
+          deck,
+          playerState: playerStatePda(program, table, player.publicKey),
+          player: player.publicKey,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .signers([player])
+        .rpc({ commitment: "confirmed" });
+    }
+  }
+
+  /** Submit every share `shuffler` owes for the positions open for decryption. */
+  async function submitShares(
+    table: PublicKey,
+    deck: PublicKey,
+    shuffler: Shuffler,
+    seat: number
+  ) {
+    const state = await program.account.deck.fetch(deck);
+    const own = state.shufflers.indexOf(seat) * 2;
+    const shares = [];
+    for (let position = 0; position < state.requested; position++) {
+      const done = (state.shareMasks[position] & (1 << seat)) !== 0;
+      if (!done && (position < own || position >= own + 2)) {
+        shares.push(shuffler.share(position, Uint8Array.from(state.cards[position])));
+      }
+    }
+    const player = shuffler.player;
+    for (let i = 0; i < shares.length; i += SHARES_PER_TX) {
+      await program.methods
+        .submitShares(shares.slice(i, i + SHARES_PER_TX))
+        .accounts({
+          table,
+          deck,
+          playerState: playerStatePda(program, table, player.publicKey),
+          player: player.publicKey,
+        })
+        .preInstructions([computeBudget])
+        .signers([player])
+        .rpc({ commitment: "confirmed" });
+    }
+  }
+
+  async function openShuffle(table: PublicKey, deck: PublicKey, shuffler: Shuffler) {
+    const player = shuffler.player;
+    await program.methods
+      .verifyShuffle(bytes(scalarBytes(shuffler.key)), Buffer.from(shuffler.permutation))
+      .accounts({
+        table,
+        deck,
+        playerState: playerStatePda(program, table, player.publicKey),
+        player: player.publicKey,
+      })
+      .preInstructions([computeBudget])
+      .signers([player])
+      .rpc({ commitment: "confirmed" });
+  }
+
+  it("Deals a hand without a dealer", async () => {
+    const { table, deck } = await startHand();
+    const started = await program.account.table.fetch(table);
+    expect(started.status).to.deep.equal({ shuffling: {} });
+
+    // Everyone dealt in shuffles in turn, each on top of the last
+    const order: number[] = (await program.account.deck.fetch(deck)).shufflers;
+    expect(order).to.have.length(2);
+    const shufflers = new Map<number, Shuffler>();
+    let input = cardPoints();
+    for (const seat of order) {
+      const shuffler = Shuffler.random(await playerAt(table, seat), input.length);
+      const output = shuffler.shuffle(input);
+      await submitShuffle(table, deck, shuffler, output);
+      shufflers.set(seat, shuffler);
+      input = output;
+    }
+
+    // Each player strips their layer off the other's hole cards
+    for (const seat of order) {
+      await submitShares(table, deck, shufflers.get(seat)!, seat);
+    }
+    const dealing = await program.account.table.fetch(table);
+    expect(dealing.status).to.deep.equal({ dealing: {} });
+
+    // What's left on a player's own hole cards is only their own key, so
+    // they can read them and nobody else can
+    const state = await program.account.deck.fetch(deck);
+    const points = cardPoints().map((p) => Buffer.from(p).toString("hex"));
+    const dealt = new Set<number>();
+    for (const seat of order) {
+      const own = order.indexOf(seat) * 2;
+      expect(state.shareMasks[own]).to.equal(1 << order.find((s) => s !== seat)!);
+      for (const position of [own, own + 1]) {
+        const encrypted = Uint8Array.from(state.cards[position]);
+        const key = shufflers.get(seat)!.key;
+        const card = multiply(invert(key, ORDER), encrypted);
+        const index = points.indexOf(Buffer.from(card).toString("hex"));
+        expect(index).to.be.greaterThanOrEqual(0);
+        expect(points).to.not.include(Buffer.from(encrypted).toString("hex"));
+        dealt.add(index);
+      }
+    }
+    expect(dealt.size).to.equal(4);
+
+    // Opening both shuffles checks out
+    for (const seat of order) {
+      await openShuffle(table, deck, shufflers.get(seat)!);
+    }
+    const opened = await program.account.deck.fetch(deck);
+    expect(opened.verifiedMask).to.equal((1 << order[0]) | (1 << order[1]));
+    expect(opened.cheatedMask).to.equal(0);
+  });
+
+  it("Aborts a hand when a shuffle was cheated", async () => {
+    const { table, deck } = await startHand();
+    const order: number[] = (await program.account.deck.fetch(deck)).shufflers;
+    const [cheatSeat, honestSeat] = order;
+    const cheat = Shuffler.random(await playerAt(table, cheatSeat), 52);
+
+    // Commit to one permutation but stack the deck: two copies of the
+    // ace of spades, and no two of hearts
+    const input = cardPoints();
+    const output = cheat.shuffle(input);
+    output[cheat.permutation.indexOf(0)] = multiply(cheat.key, input[51]);
+    await submitShuffle(table, deck, cheat, output);
+
+    // Opening the shuffle exposes it
+    await openShuffle(table, deck, cheat);
+    const opened = await program.account.deck.fetch(deck);
+    expect(opened.cheatedMask).to.equal(1 << cheatSeat);
+    expect(opened.verifiedMask).to.equal(0);
+
+    const cheatState = playerStatePda(program, table, cheat.player.publicKey);
+    const honestPlayer = await playerAt(table, honestSeat);
+    const honestState = playerStatePda(program, table, honestPlayer.publicKey);
+
+    // Anyone can call the hand off: blinds go back and the cheat pays
+    // the penalty
+    await program.methods
+      .abortHand()
+      .accounts({ table, house: housePda(program), deck })
+      .remainingAccounts(await seatedStates(program, table))
+      .rpc({ commitment: "confirmed" });
+
+    const penalty = BIG_BLIND.muln(MENTAL_POKER_PENALTY_BBS);
+    const cheated = await program.account.playerState.fetch(cheatState);
+    const honest = await program.account.playerState.fetch(honestState);
+    expect(cheated.stack.toString()).to.equal(BUY_IN.sub(penalty).toString());
+    expect(honest.stack.toString()).to.equal(BUY_IN.add(penalty).toString());
+    expect(cheated.isSittingOut).to.equal(true);
+    expect(honest.isSittingOut).to.equal(false);
+
+    const finished = await program.account.table.fetch(table);
+    expect(finished.status).to.deep.equal({ finished: {} });
+    expect((finished.pot as BN).toNumber()).to.equal(0);
+  });
+});
@@ -0,0 +1,147 @@
+import * as anchor from "@coral-xyz/anchor";
+import { BN, Program } from "@coral-xyz/anchor";
+import {
+  Keypair,
+  LAMPORTS_PER_SOL,
+  PublicKey,
+  SystemProgram,
+} from "@solana/web3.js";
+
+// Shared setup for the local-validator tests. Run them with
+// `anchor test --provider.cluster localnet`.
+
+export const BUY_IN = new BN(LAMPORTS_PER_SOL / 2);
+export const SMALL_BLIND = new BN(1_000_000);
+export const BIG_BLIND = new BN(2_000_000);
+
+export function shadowPoker(): Program<any> {
+  anchor.setProvider(anchor.AnchorProvider.env());
+  return anchor.workspace.ShadowPoker as Program<any>;
+}
+
+export function housePda(program: Program<any>): PublicKey {
+  return PublicKey.findProgramAddressSync(
+    [Buffer.from("shadow_poker_house")],
+    program.programId
+  )[0];
+}
+
+export function playerStatePda(
+  program: Program<any>,
+  table: PublicKey,
+  player: PublicKey
+): PublicKey {
+  return PublicKey.findProgramAddressSync(
+    [Buffer.from("player_state"), table.toBuffer(), player.toBuffer()],
+    program.programId
+  )[0];
+}
+
+export function deckPda(program: Program<any>, table: PublicKey): PublicKey {
+  return PublicKey.findProgramAddressSync(
+    [Buffer.from("deck"), table.toBuffer()],
+    program.programId
+  )[0];
+}
+
+/** Create the house once per validator. */
+export async function initializeHouse(program: Program<any>): Promise<void> {
+  const provider = program.provider as anchor.AnchorProvider;
+  try {
+    await program.methods
+      .initializeHouse()
+      .accounts({
+        house: housePda(program),
+        authority: provider.wallet.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc({ commitment: "confirmed" });
+  } catch (e: any) {
+    if (!e.message.includes("already in use")) {
+      throw e;
+    }
+  }
+}
+
+export async function fundedKeypair(program: Program<any>, sol = 5): Promise<Keypair> {
+  const keypair = Keypair.generate();
+  const connection = program.provider.connection;
+  const signature = await connection.requestAirdrop(
+    keypair.publicKey,
+    sol * LAMPORTS_PER_SOL
+  );
+  await connection.confirmTransaction(signature, "confirmed");
+  return keypair;
+}
+
+/** Create a Hold'em table, heads-up by default, and return its address. */
+export async function createTable(
+  program: Program<any>,
+  creator: Keypair,
+  dealingMode: "dealer" | "mentalPoker",
+  maxPlayers = 2
+): Promise<PublicKey> {
+  const house = housePda(program);
+  const { totalTables } = await program.account.shadowPokerHouse.fetch(house);
+  const [table] = PublicKey.findProgramAddressSync(
+    [Buffer.from("table"), (totalTables as BN).toArrayLike(Buffer, "le", 8)],
+    program.programId
+  );
+  await program.methods
+    .createTable(
+      new BN(LAMPORTS_PER_SOL / 10),
+      new BN(LAMPORTS_PER_SOL),
+      SMALL_BLIND,
+      BIG_BLIND,
+      maxPlayers,
+      new BN(0),
+      { holdem: {} },
+      { noLimit: {} },
+      { [dealingMode]: {} }
+    )
+    .accounts({
+      table,
+      house,
+      chipMint: null,
+      creator: creator.publicKey,
+      systemProgram: SystemProgram.programId,
+    })
+    .signers([creator])
+    .rpc({ commitment: "confirmed" });
+  return table;
+}
+
+export async function joinTable(
+  program: Program<any>,
+  table: PublicKey,
+  player: Keypair
+): Promise<void> {
+  await program.methods
+    .joinTable(BUY_IN, null)
+    .accounts({
+      table,
+      tableAccess: null,
+      inviteToken: null,
+      playerState: playerStatePda(program, table, player.publicKey),
+      chipMint: null,
+      playerTokens: null,
+      vault: null,
+      tokenProgram: null,
+      player: player.publicKey,
+      systemProgram: SystemProgram.programId,
+    })
+    .signers([player])
+    .rpc({ commitment: "confirmed" });
+}
+
+/** Every seated player's `PlayerState`, writable and in seat order. */
+export async function seatedStates(program: Program<any>, table: PublicKey) {
+  const { players } = await program.account.table.fetch(table);
+  return (players as PublicKey[])
+    .filter((player) => !player.equals(PublicKey.default))
+    .map((player) => ({
+      pubkey: playerStatePda(program, table, player),
+      isWritable: true,
+      isSigner: false,
+    }));
+}